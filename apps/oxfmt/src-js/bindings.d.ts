@@ -26,12 +26,23 @@ export declare const enum Severity {
   Warning = 'Warning',
   Advice = 'Advice'
 }
+/**
+ * Result of a single file formatted by the JS `formatFiles` callback.
+ * Either `code` or `error` is set, or neither if no parser or plugin can handle the file.
+ */
+export interface FormatFileResult {
+  /** The formatted code. */
+  code?: string
+  /** The error message, if formatting failed. */
+  error?: string
+}
+
 /**
  * NAPI based format API entry point.
  *
  * Since it internally uses `await prettier.format()` in JS side, `formatSync()` cannot be provided.
  */
-export declare function format(filename: string, sourceText: string, options: any | undefined | null, initExternalFormatterCb: (numThreads: number) => Promise<string[]>, formatEmbeddedCb: (options: Record<string, any>, tagName: string, code: string) => Promise<string>, formatFileCb: (options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string>): Promise<FormatResult>
+export declare function format(filename: string, sourceText: string, options: any | undefined | null, initExternalFormatterCb: (numThreads: number) => Promise<string[]>, formatEmbeddedCb: (options: Record<string, any>, tagName: string, code: string) => Promise<string>, formatFileCb: (options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string | null>): Promise<FormatResult>
 
 export interface FormatResult {
   /** The formatted code. */
@@ -49,9 +60,11 @@ export interface FormatResult {
  * 2. `init_external_formatter_cb`: Callback to initialize external formatter
  * 3. `format_embedded_cb`: Callback to format embedded code in templates
  * 4. `format_file_cb`: Callback to format files
+ * 5. `format_files_cb`: Callback to format multiple files at once, used by the language server
+ * 6. `load_config_cb`: Callback to load JS config files, used by the language server
  *
  * Returns a tuple of `[mode, exitCode]`:
  * - `mode`: If main logic will run in JS side, use this to indicate which mode
  * - `exitCode`: If main logic already ran in Rust side, return the exit code
  */
-export declare function runCli(args: Array<string>, initExternalFormatterCb: (numThreads: number) => Promise<string[]>, formatEmbeddedCb: (options: Record<string, any>, tagName: string, code: string) => Promise<string>, formatFileCb: (options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string>): Promise<[string, number | undefined | null]>
+export declare function runCli(args: Array<string>, initExternalFormatterCb: (numThreads: number) => Promise<string[]>, formatEmbeddedCb: (options: Record<string, any>, tagName: string, code: string) => Promise<string>, formatFileCb: (options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string | null>, formatFilesCb: (requests: Array<Record<string, any>>) => Promise<FormatFileResult[]>, loadConfigCb: (path: string) => Promise<Record<string, any>>): Promise<[string, number | undefined | null]>
@@ -1,5 +1,11 @@
 import { runCli } from "./bindings";
-import { initExternalFormatter, formatEmbeddedCode, formatFile } from "./cli/worker-proxy";
+import {
+  initExternalFormatter,
+  formatEmbeddedCode,
+  formatFile,
+  formatFiles,
+} from "./cli/worker-proxy";
+import { loadConfig } from "./libs/config";
 
 // napi-JS `oxfmt` CLI entry point
 // See also `run_cli()` function in `./src/main_napi.rs`
@@ -14,6 +20,8 @@ void (async () => {
     initExternalFormatter,
     formatEmbeddedCode,
     formatFile,
+    formatFiles,
+    loadConfig,
   );
 
   // Migration modes are handled by JS
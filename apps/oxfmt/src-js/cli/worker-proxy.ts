@@ -2,6 +2,7 @@ import Tinypool from "tinypool";
 import { resolvePlugins } from "../libs/prettier";
 import type { FormatEmbeddedCodeParam, FormatFileParam } from "../libs/prettier";
 import type { Options } from "prettier";
+import type { FormatFileResult } from "../bindings";
 
 // Worker pool for parallel Prettier formatting
 let pool: Tinypool | null = null;
@@ -31,8 +32,23 @@ export async function formatFile(
   parserName: string,
   fileName: string,
   code: string,
-): Promise<string> {
+): Promise<string | null> {
   return pool!.run({ options, code, fileName, parserName } satisfies FormatFileParam, {
     name: "formatFile",
   });
 }
+
+export async function formatFiles(requests: FormatFileParam[]): Promise<FormatFileResult[]> {
+  // Settle each file separately, so one failure does not reject the whole batch
+  return Promise.all(
+    requests.map((request) =>
+      pool!
+        .run(request satisfies FormatFileParam, { name: "formatFile" })
+        .then(
+          // Neither `code` nor `error` is set for unsupported files
+          (code: string | null) => (code === null ? {} : { code }),
+          (err: unknown) => ({ error: String(err) }),
+        ),
+    ),
+  );
+}
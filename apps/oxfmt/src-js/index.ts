@@ -88,10 +88,23 @@ export type FormatOptions = {
   embeddedLanguageFormatting?: "auto" | "off";
   /** Whether to insert a final newline at the end of the file. (Default: `true`) */
   insertFinalNewline?: boolean;
+  /**
+   * Whether to remove extra newlines at the end of the file. (Default: `true`)
+   * If disabled, trailing blank lines of the original file are kept.
+   */
+  trimTrailingNewlines?: boolean;
   /** Experimental: Sort import statements. Disabled by default. */
   experimentalSortImports?: SortImportsOptions;
-  /** Experimental: Sort `package.json` keys. (Default: `true`) */
-  experimentalSortPackageJson?: boolean;
+  /**
+   * Experimental: Sort `package.json` keys. (Default: `true`)
+   * Pass an object to configure how they are sorted.
+   */
+  experimentalSortPackageJson?: boolean | SortPackageJsonOptions;
+  /**
+   * Sort the keys of objects in JSON, JSONC and JSON5 files,
+   * except for objects containing comments. (Default: `false`)
+   */
+  sortJsonKeys?: boolean;
 } & Record<string, unknown>; // Also allow additional options for we don't have typed yet.
 
 /**
@@ -119,3 +132,13 @@ export type SortImportsOptions = {
    */
   groups?: (string | string[])[];
 };
+
+/**
+ * Configuration options for sorting `package.json`.
+ */
+export type SortPackageJsonOptions = {
+  /** Sort the `scripts` field alphabetically. (Default: `false`) */
+  sortScripts?: boolean;
+  /** Print the sorted `package.json` with 2-space indentation before formatting it. (Default: `false`) */
+  pretty?: boolean;
+};
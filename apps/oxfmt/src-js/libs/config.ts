@@ -0,0 +1,13 @@
+import { pathToFileURL } from "node:url";
+
+/**
+ * Load a JS config file like `.oxfmtrc.js`, `.oxfmtrc.cjs` or `.oxfmtrc.mjs`.
+ * Used by the language server, see `load_config()` in `src/lsp/external_formatter_bridge.rs`.
+ */
+export async function loadConfig(path: string): Promise<Record<string, any>> {
+  // The config file can be edited while the language server is running,
+  // so bypass the module cache with a query string
+  const url = `${pathToFileURL(path).href}?t=${Date.now()}`;
+  const mod = await import(url);
+  return mod.default ?? mod;
+}
@@ -54,7 +54,8 @@ export async function formatEmbeddedCode({
   options,
 }: FormatEmbeddedCodeParam): Promise<string> {
   // TODO: This should be resolved in Rust side
-  const parserName = TAG_TO_PARSER[tagName];
+  // Member expression tags like `styled.div` are resolved by their object
+  const parserName = TAG_TO_PARSER[tagName.split(".")[0]];
 
   // Unknown tag, return original code
   if (!parserName) return code;
@@ -83,14 +84,14 @@ export type FormatFileParam = {
 /**
  * Format non-js file
  *
- * @returns Formatted code
+ * @returns Formatted code, or `null` if no parser or plugin can handle the file
  */
 export async function formatFile({
   code,
   parserName,
   fileName,
   options,
-}: FormatFileParam): Promise<string> {
+}: FormatFileParam): Promise<string | null> {
   if (!prettierCache) {
     prettierCache = await import("prettier");
   }
@@ -100,5 +101,19 @@ export async function formatFile({
   options.parser = parserName;
   // But some plugins rely on `filepath`, so we set it too
   options.filepath = fileName;
-  return prettierCache.format(code, options);
+  try {
+    return await prettierCache.format(code, options);
+  } catch (err) {
+    // Let the Rust side tell an unsupported file apart from a formatting error
+    if (isUnsupportedError(err)) return null;
+    throw err;
+  }
+}
+
+function isUnsupportedError(err: unknown): boolean {
+  return (
+    err instanceof Error &&
+    err.name === "ConfigError" &&
+    err.message.startsWith("Couldn't resolve parser")
+  );
 }
@@ -147,7 +147,7 @@ pub struct ConfigOptions {
 #[derive(Debug, Clone, Bpaf)]
 pub struct IgnoreOptions {
     /// Path to ignore file(s). Can be specified multiple times.
-    /// If not specified, .gitignore and .prettierignore in the current directory are used.
+    /// If not specified, .gitignore, .prettierignore and .oxfmtignore in the current directory are used.
     #[bpaf(argument("PATH"), many, hide_usage)]
     pub ignore_path: Vec<PathBuf>,
     /// Format code in node_modules directory (skipped by default)
@@ -68,7 +68,7 @@ impl Walk {
         // NOTE: Git ignore files are handled by `WalkBuilder` itself
         let mut matchers: Vec<Gitignore> = vec![];
 
-        // 1. Handle formatter ignore files (`.prettierignore`, or `--ignore-path`)
+        // 1. Handle formatter ignore files (`.prettierignore`, `.oxfmtignore`, or `--ignore-path`)
         // Patterns are relative to the ignore file location
         for ignore_path in &load_ignore_paths(cwd, ignore_paths)? {
             let (gitignore, err) = Gitignore::new(ignore_path);
@@ -250,7 +250,8 @@ fn load_ignore_paths(cwd: &Path, ignore_paths: &[PathBuf]) -> Result<Vec<PathBuf
 
     // Else, search for default ignore files in cwd
     // These are optional, do not error if not found
-    Ok(std::iter::once(".prettierignore")
+    Ok([".prettierignore", ".oxfmtignore"]
+        .into_iter()
         .filter_map(|file_name| {
             let path = cwd.join(file_name);
             path.exists().then_some(path)
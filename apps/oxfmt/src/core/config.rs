@@ -1,19 +1,65 @@
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use editorconfig_parser::{
     EditorConfig, EditorConfigProperties, EditorConfigProperty, EndOfLine, IndentStyle,
     MaxLineLength,
 };
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use oxc_toml::Options as TomlFormatterOptions;
-use serde_json::Value;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde_json::{Map, Value};
 
 use oxc_formatter::{
-    FormatOptions,
+    FormatOptions, LineEnding, QuoteProperties, TrailingCommas,
     oxfmtrc::{EndOfLineConfig, OxfmtOptions, Oxfmtrc},
 };
 
 use super::{FormatFileStrategy, utils};
 
+/// JSON config files looked up in each directory, `.json` is preferred if both exist.
+pub const JSON_CONFIG_FILE_NAMES: [&str; 2] = [".oxfmtrc.json", ".oxfmtrc.jsonc"];
+
+/// Options of a config file which are not merged into the configs of nested directories,
+/// since they only apply to the directory of the config file.
+const NON_INHERITED_CONFIG_KEYS: [&str; 3] = ["ignorePatterns", "overrides", "root"];
+
+/// Removes the ignore patterns which are repeated later on, keeping the order of the others.
+/// The last matching pattern wins, so only the last occurrence of a pattern has an effect.
+/// Blank lines and comments are kept as is.
+pub fn dedup_ignore_patterns<T>(patterns: Vec<T>, pattern: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut seen = FxHashSet::default();
+    let mut deduped = patterns
+        .into_iter()
+        .rev()
+        .filter(|item| {
+            let line = pattern(item).trim();
+            line.is_empty() || line.starts_with('#') || seen.insert(line.to_string())
+        })
+        .collect::<Vec<_>>();
+    deduped.reverse();
+    deduped
+}
+
+/// Returns the ignore patterns which are negated by a later pattern, e.g. `dist` followed by `!dist`,
+/// paired with the negation. Neither of them has any effect then.
+fn find_cancelled_ignore_patterns(patterns: &[String]) -> Vec<(&str, &str)> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter(|(_, pattern)| !pattern.starts_with('!') && !pattern.starts_with('#'))
+        .filter_map(|(index, pattern)| {
+            patterns[index + 1..]
+                .iter()
+                .find(|later| later.strip_prefix('!') == Some(pattern.as_str()))
+                .map(|negation| (pattern.as_str(), negation.as_str()))
+        })
+        .collect()
+}
+
 /// Resolve config file path from cwd and optional explicit path.
 pub fn resolve_oxfmtrc_path(cwd: &Path, config_path: Option<&Path>) -> Option<PathBuf> {
     // If `--config` is explicitly specified, use that path
@@ -26,16 +72,55 @@ pub fn resolve_oxfmtrc_path(cwd: &Path, config_path: Option<&Path>) -> Option<Pa
     }
 
     // If `--config` is not specified, search the nearest config file from cwd upwards
-    // Support both `.json` and `.jsonc`, but prefer `.json` if both exist
-    cwd.ancestors().find_map(|dir| {
-        for filename in [".oxfmtrc.json", ".oxfmtrc.jsonc"] {
-            let config_path = dir.join(filename);
-            if config_path.exists() {
-                return Some(config_path);
+    cwd.ancestors().find_map(find_json_config_path)
+}
+
+/// Find the JSON config file in `dir`, see [`JSON_CONFIG_FILE_NAMES`].
+fn find_json_config_path(dir: &Path) -> Option<PathBuf> {
+    JSON_CONFIG_FILE_NAMES.iter().map(|file_name| dir.join(file_name)).find(|path| path.exists())
+}
+
+/// Read a JSON config file, comments are allowed.
+fn read_config_file(path: &Path) -> Result<Value, String> {
+    let mut json_string = utils::read_to_string(path)
+        // Do not include OS error, it differs between platforms
+        .map_err(|_| format!("Failed to read {}: File not found", path.display()))?;
+    // Strip comments (JSONC support)
+    json_strip_comments::strip(&mut json_string)
+        .map_err(|err| format!("Failed to strip comments from {}: {err}", path.display()))?;
+    serde_json::from_str(&json_string).map_err(|err| format!("Failed to parse config: {err}"))
+}
+
+/// Whether the raw config has `root: true`, config files of parent directories are not used then.
+fn is_root_config(raw_config: &Value) -> bool {
+    raw_config.get("root").and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// Merge the options of the config files found in the parent directories of `dir` into `raw_config`,
+/// until one of them has `root: true`. The closest config wins.
+fn merge_parent_configs(raw_config: &mut Value, dir: &Path) -> Result<(), String> {
+    if is_root_config(raw_config) {
+        return Ok(());
+    }
+    for parent_dir in dir.ancestors().skip(1) {
+        let Some(path) = find_json_config_path(parent_dir) else {
+            continue;
+        };
+        let parent_config = read_config_file(&path)?;
+        if let (Some(obj), Some(parent_obj)) =
+            (raw_config.as_object_mut(), parent_config.as_object())
+        {
+            for (key, value) in parent_obj {
+                if !NON_INHERITED_CONFIG_KEYS.contains(&key.as_str()) {
+                    obj.entry(key.clone()).or_insert_with(|| value.clone());
+                }
             }
         }
-        None
-    })
+        if is_root_config(&parent_config) {
+            break;
+        }
+    }
+    Ok(())
 }
 
 pub fn resolve_editorconfig_path(cwd: &Path) -> Option<PathBuf> {
@@ -43,10 +128,17 @@ pub fn resolve_editorconfig_path(cwd: &Path) -> Option<PathBuf> {
     cwd.ancestors().map(|dir| dir.join(".editorconfig")).find(|p| p.exists())
 }
 
+/// Prettier options read by [`ConfigResolver::from_prettier_config_path`].
+/// They are named the same in `.oxfmtrc`, other options are ignored.
+#[cfg(feature = "napi")]
+const PRETTIER_CONFIG_KEYS: &[&str] =
+    &["printWidth", "tabWidth", "semi", "singleQuote", "trailingComma"];
+
 // ---
 
 /// Resolved options for each file type.
 /// Each variant contains only the options needed for that formatter.
+#[derive(Clone)]
 pub enum ResolvedOptions {
     /// For JS/TS files formatted by oxc_formatter.
     OxcFormatter {
@@ -54,76 +146,200 @@ pub enum ResolvedOptions {
         /// For embedded language formatting (e.g., CSS in template literals)
         external_options: Value,
         insert_final_newline: bool,
+        trim_trailing_newlines: bool,
+        #[cfg_attr(not(feature = "napi"), expect(dead_code))]
+        final_newline: FinalNewline,
     },
     /// For TOML files.
-    OxfmtToml { toml_options: TomlFormatterOptions, insert_final_newline: bool },
+    OxfmtToml {
+        toml_options: TomlFormatterOptions,
+        insert_final_newline: bool,
+        trim_trailing_newlines: bool,
+        #[cfg_attr(not(feature = "napi"), expect(dead_code))]
+        final_newline: FinalNewline,
+    },
+    /// For JSON-family files formatted by oxc_formatter.
+    OxcFormatterJson {
+        format_options: FormatOptions,
+        /// Sort the keys of objects, from `sortJsonKeys`.
+        sort_keys: bool,
+        insert_final_newline: bool,
+        trim_trailing_newlines: bool,
+        #[cfg_attr(not(feature = "napi"), expect(dead_code))]
+        final_newline: FinalNewline,
+    },
     /// For non-JS files formatted by external formatter (Prettier).
     #[cfg(feature = "napi")]
-    ExternalFormatter { external_options: Value, insert_final_newline: bool },
+    ExternalFormatter {
+        external_options: Value,
+        insert_final_newline: bool,
+        trim_trailing_newlines: bool,
+        final_newline: FinalNewline,
+    },
     /// For `package.json` files: optionally sorted then formatted.
     #[cfg(feature = "napi")]
     ExternalFormatterPackageJson {
         external_options: Value,
-        sort_package_json: bool,
+        /// `None` if the keys are not sorted, from `experimentalSortPackageJson`.
+        sort_package_json: Option<oxc_formatter::oxfmtrc::SortPackageJsonOptions>,
         insert_final_newline: bool,
+        trim_trailing_newlines: bool,
+        final_newline: FinalNewline,
     },
 }
 
+/// Line ending of the final newline, resolved from `endOfLine` of `.oxfmtrc` or `.editorconfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalNewline {
+    Lf,
+    CrLf,
+    Cr,
+    /// `endOfLine` is not set, follow the first line ending of the source text.
+    Auto,
+}
+
+impl FinalNewline {
+    fn new(line_ending: LineEnding, keep_line_endings: bool) -> Self {
+        if keep_line_endings {
+            return Self::Auto;
+        }
+        match line_ending {
+            LineEnding::Lf => Self::Lf,
+            LineEnding::Crlf => Self::CrLf,
+            LineEnding::Cr => Self::Cr,
+        }
+    }
+}
+
 /// Configuration resolver that derives all config values from a single `serde_json::Value`.
 ///
-/// Priority order: `Oxfmtrc::default()` → `.editorconfig` → user's `.oxfmtrc`
+/// Priority order: `Oxfmtrc::default()` → `.editorconfig` → user's `.oxfmtrc` → matching `overrides`
+///
+/// `.editorconfig` files are merged from the file's directory upwards until `root = true`,
+/// the closest one wins. So are `.oxfmtrc.json` files until `root: true`,
+/// see [`ConfigResolver::config_dir`].
 pub struct ConfigResolver {
     /// User's raw config as JSON value.
     /// It contains every possible field, even those not recognized by `Oxfmtrc`.
     /// e.g. `printWidth`: recognized by both `Oxfmtrc` and Prettier
     /// e.g. `vueIndentScriptAndStyle`: not recognized by `Oxfmtrc`, but used by Prettier
     /// e.g. `svelteSortAttributes`: not recognized by Prettier by default
+    /// `overrides` field is moved out to [`ConfigResolver::overrides`] by `build_and_validate()`.
     raw_config: Value,
+    /// Root directory for `overrides` globs, `None` if config is passed as a value.
+    cwd: Option<PathBuf>,
+    /// Per-file `overrides` from `.oxfmtrc`, in the order they are defined.
+    overrides: Vec<ConfigOverride>,
     /// Parsed `.editorconfig`, if any.
     editorconfig: Option<EditorConfig>,
+    /// Directory of the `.editorconfig` above, or cwd if not found.
+    /// Nested `.editorconfig` files are looked up from each file's directory up to this one (exclusive).
+    /// `None` if nested `.editorconfig` files are not supported, e.g. config passed as a value.
+    editorconfig_dir: Option<PathBuf>,
+    /// Parsed nested `.editorconfig` for each visited directory, `None` if the directory has none.
+    nested_editorconfigs: Mutex<FxHashMap<PathBuf, Option<Arc<NestedEditorConfig>>>>,
+    /// Directory of the config file found from cwd upwards, or cwd otherwise.
+    /// Nested config files are looked up from each file's directory up to this one (exclusive),
+    /// the config files of its parent directories are already merged into `raw_config`.
+    /// `None` if nested config files are not supported, e.g. config passed as a value.
+    config_dir: Option<PathBuf>,
+    /// Parsed nested config for each visited directory, `None` if the directory has none.
+    nested_configs: Mutex<FxHashMap<PathBuf, Option<Arc<NestedConfig>>>>,
     /// Cached parsed options after validation.
     /// Used to avoid re-parsing during per-file resolution, if `.editorconfig` is not used.
-    /// NOTE: Currently, only `.editorconfig` provides per-file overrides, `.oxfmtrc` does not.
+    /// NOTE: Both `.editorconfig` and `overrides` of `.oxfmtrc` provide per-file overrides.
     cached_options: Option<(FormatOptions, OxfmtOptions, Value)>,
 }
 
 impl ConfigResolver {
     /// Create a new resolver from a raw JSON config value.
-    #[cfg(feature = "napi")]
     pub fn from_value(raw_config: Value) -> Self {
-        Self { raw_config, editorconfig: None, cached_options: None }
+        Self {
+            raw_config,
+            cwd: None,
+            overrides: vec![],
+            editorconfig: None,
+            editorconfig_dir: None,
+            nested_editorconfigs: Mutex::default(),
+            config_dir: None,
+            nested_configs: Mutex::default(),
+            cached_options: None,
+        }
     }
 
     /// Create a resolver by loading config from a file path.
+    /// The options of the config files in its parent directories are merged, until one has `root: true`.
     ///
     /// # Errors
     /// Returns error if:
     /// - Config file is specified but not found or invalid
-    /// - Config file parsing fails
+    /// - Config file parsing fails, including the config files of its parent directories
     pub fn from_config_paths(
         cwd: &Path,
         oxfmtrc_path: Option<&Path>,
         editorconfig_path: Option<&Path>,
     ) -> Result<Self, String> {
         // Read and parse config file, or use empty JSON if not found
-        let json_string = match oxfmtrc_path {
-            Some(path) => {
-                let mut json_string = utils::read_to_string(path)
-                    // Do not include OS error, it differs between platforms
-                    .map_err(|_| format!("Failed to read {}: File not found", path.display()))?;
-                // Strip comments (JSONC support)
-                json_strip_comments::strip(&mut json_string).map_err(|err| {
-                    format!("Failed to strip comments from {}: {err}", path.display())
-                })?;
-                json_string
-            }
-            None => "{}".to_string(),
+        let Some(path) = oxfmtrc_path else {
+            return Self::from_value_with_editorconfig(
+                cwd,
+                Value::Object(Map::new()),
+                editorconfig_path,
+            );
         };
+        let mut raw_config = read_config_file(path)?;
+        // Only a config file found from cwd upwards is merged with those of its parent directories,
+        // not the one specified in another directory, e.g. `.config/oxfmt.json`
+        let config_dir = path.parent().filter(|dir| cwd.starts_with(dir));
+        if let Some(config_dir) = config_dir {
+            merge_parent_configs(&mut raw_config, config_dir)?;
+        }
 
-        // Parse as raw JSON value
-        let raw_config: Value = serde_json::from_str(&json_string)
-            .map_err(|err| format!("Failed to parse config: {err}"))?;
+        let mut resolver = Self::from_value_with_editorconfig(cwd, raw_config, editorconfig_path)?;
+        if let Some(config_dir) = config_dir {
+            resolver.config_dir = Some(config_dir.to_path_buf());
+        }
+        Ok(resolver)
+    }
+
+    /// Create a resolver from a Prettier config file, e.g. `.prettierrc`, for projects without `.oxfmtrc`.
+    /// Only JSON is supported, and only the options in [`PRETTIER_CONFIG_KEYS`] are used.
+    ///
+    /// # Errors
+    /// Returns error if the config file cannot be read or is not a JSON object,
+    /// or if `.editorconfig` is specified but not found.
+    #[cfg(feature = "napi")]
+    pub fn from_prettier_config_path(
+        cwd: &Path,
+        prettierrc_path: &Path,
+        editorconfig_path: Option<&Path>,
+    ) -> Result<Self, String> {
+        let json_string = utils::read_to_string(prettierrc_path)
+            .map_err(|_| format!("Failed to read {}: File not found", prettierrc_path.display()))?;
+        let prettier_config: Value = serde_json::from_str(&json_string).map_err(|err| {
+            format!("Failed to parse Prettier config, only JSON is supported: {err}")
+        })?;
+        let Value::Object(prettier_config) = prettier_config else {
+            return Err("Failed to parse Prettier config: expected an object".to_string());
+        };
 
+        let raw_config = prettier_config
+            .into_iter()
+            .filter(|(key, _)| PRETTIER_CONFIG_KEYS.contains(&key.as_str()))
+            .collect::<Map<_, _>>();
+        Self::from_value_with_editorconfig(cwd, Value::Object(raw_config), editorconfig_path)
+    }
+
+    /// Create a resolver from a raw JSON config value, loading `.editorconfig` from a file path.
+    /// Used for configs which are not read from JSON files, e.g. `.oxfmtrc.js` loaded by JS side.
+    ///
+    /// # Errors
+    /// Returns error if `.editorconfig` is specified but not found.
+    pub fn from_value_with_editorconfig(
+        cwd: &Path,
+        raw_config: Value,
+        editorconfig_path: Option<&Path>,
+    ) -> Result<Self, String> {
         let editorconfig = match editorconfig_path {
             Some(path) => {
                 let str = utils::read_to_string(path)
@@ -136,7 +352,22 @@ impl ConfigResolver {
             None => None,
         };
 
-        Ok(Self { raw_config, editorconfig, cached_options: None })
+        let editorconfig_dir = match editorconfig_path {
+            Some(path) => path.parent().unwrap_or(cwd).to_path_buf(),
+            None => cwd.to_path_buf(),
+        };
+
+        Ok(Self {
+            raw_config,
+            cwd: Some(cwd.to_path_buf()),
+            overrides: vec![],
+            editorconfig,
+            editorconfig_dir: Some(editorconfig_dir),
+            nested_editorconfigs: Mutex::default(),
+            config_dir: Some(cwd.to_path_buf()),
+            nested_configs: Mutex::default(),
+            cached_options: None,
+        })
     }
 
     /// Validate config and return ignore patterns for file walking.
@@ -169,7 +400,19 @@ impl ConfigResolver {
         let mut external_options = self.raw_config.clone();
         Oxfmtrc::populate_prettier_config(&format_options, &mut external_options);
 
-        let ignore_patterns_clone = oxfmt_options.ignore_patterns.clone();
+        let ignore_patterns_clone =
+            dedup_ignore_patterns(oxfmt_options.ignore_patterns.clone(), String::as_str);
+        for (pattern, negation) in find_cancelled_ignore_patterns(&ignore_patterns_clone) {
+            tracing::warn!(
+                "Ignore pattern `{pattern}` is cancelled by the later `{negation}` in `ignorePatterns`, consider removing both"
+            );
+        }
+
+        // Validate each `overrides` entry eagerly, so `resolve()` can assume they are valid
+        self.overrides = self.build_overrides()?;
+        if let Some(obj) = external_options.as_object_mut() {
+            obj.remove("overrides");
+        }
 
         // NOTE: Save cache for fast path: no per-file overrides
         self.cached_options = Some((format_options, oxfmt_options, external_options));
@@ -179,36 +422,203 @@ impl ConfigResolver {
 
     /// Resolve format options for a specific file.
     pub fn resolve(&self, strategy: &FormatFileStrategy) -> ResolvedOptions {
-        let (format_options, oxfmt_options, external_options) = if let Some(editorconfig) =
-            &self.editorconfig
-            && let Some(props) = get_editorconfig_overrides(editorconfig, strategy.path())
+        let path = strategy.path();
+        let nested_editorconfigs = self.find_nested_editorconfigs(path);
+        let raw_config = self.apply_config_overrides(path);
+
+        let (format_options, oxfmt_options, external_options) = if !nested_editorconfigs.is_empty()
+        {
+            Self::resolve_with_overrides(
+                &raw_config,
+                &self.collect_editorconfig_properties(&nested_editorconfigs, path),
+            )
+        } else if let Some(editorconfig) = &self.editorconfig
+            && let Some(props) = get_editorconfig_overrides(editorconfig, path)
         {
-            self.resolve_with_overrides(&props)
+            Self::resolve_with_overrides(&raw_config, &[props])
+        } else if let Cow::Owned(raw_config) = &raw_config {
+            // Only `.oxfmtrc` has per-file overrides, the root `[*]` section still applies
+            let props_list = self.editorconfig.iter().map(|e| e.resolve(path)).collect::<Vec<_>>();
+            Self::resolve_with_overrides(raw_config, &props_list)
         } else {
             // Fast path: no per-file overrides
             // Either:
-            // - `.editorconfig` is NOT used
+            // - `.editorconfig` is NOT used and no `overrides` match this file
             // - or used but per-file overrides do NOT exist for this file
             self.cached_options
                 .clone()
                 .expect("`build_and_validate()` must be called before `resolve()`")
         };
 
+        Self::to_resolved_options(
+            strategy,
+            &raw_config,
+            format_options,
+            &oxfmt_options,
+            external_options,
+        )
+    }
+
+    /// Resolve format options for a specific file, with fallback indentation and line width.
+    ///
+    /// `indent` (`use_tabs`, `tab_width`) and `print_width` are only used if neither `.editorconfig` nor `.oxfmtrc` sets them,
+    /// e.g. to respect the editor settings sent by the LSP client.
+    /// Priority order: `Oxfmtrc::default()` → `indent`, `print_width` → `.editorconfig` → user's `.oxfmtrc`
+    #[cfg(feature = "napi")]
+    pub fn resolve_with_fallbacks(
+        &self,
+        strategy: &FormatFileStrategy,
+        indent: Option<(bool, u8)>,
+        print_width: Option<u16>,
+    ) -> ResolvedOptions {
+        let path = strategy.path();
+        let raw_config = self.apply_config_overrides(path);
+        let mut oxfmtrc: Oxfmtrc = serde_json::from_value(raw_config.clone().into_owned())
+            .expect("`build_and_validate()` should catch this before `resolve()`");
+
+        let nested_editorconfigs = self.find_nested_editorconfigs(path);
+        for props in self.collect_editorconfig_properties(&nested_editorconfigs, path) {
+            apply_editorconfig(&mut oxfmtrc, &props);
+        }
+        if let Some((use_tabs, tab_width)) = indent {
+            oxfmtrc.use_tabs.get_or_insert(use_tabs);
+            // Invalid widths are ignored, instead of failing the whole resolution
+            if oxc_formatter::IndentWidth::try_from(tab_width).is_ok() {
+                oxfmtrc.tab_width.get_or_insert(tab_width);
+            }
+        }
+        if let Some(print_width) = print_width
+            && oxc_formatter::LineWidth::try_from(print_width).is_ok()
+        {
+            oxfmtrc.print_width.get_or_insert(print_width);
+        }
+
+        let (format_options, oxfmt_options) = oxfmtrc
+            .into_options()
+            .expect("If this fails, there is an issue with the fallback insertion above");
+
+        let mut external_options = raw_config.clone().into_owned();
+        Oxfmtrc::populate_prettier_config(&format_options, &mut external_options);
+
+        Self::to_resolved_options(
+            strategy,
+            &raw_config,
+            format_options,
+            &oxfmt_options,
+            external_options,
+        )
+    }
+
+    /// Resolve the effective options for a specific file, in the same shape as `.oxfmtrc`,
+    /// with `.editorconfig` and `overrides` applied, and defaults filled in.
+    /// Used to show users how the options are resolved, e.g. by the language server.
+    #[cfg(feature = "napi")]
+    pub fn resolve_effective_config(&self, path: &Path) -> Value {
+        let nested_editorconfigs = self.find_nested_editorconfigs(path);
+        let props_list = self.collect_editorconfig_properties(&nested_editorconfigs, path);
+        let raw_config = self.apply_config_overrides(path);
+        let (_, oxfmt_options, mut effective_config) =
+            Self::resolve_with_overrides(&raw_config, &props_list);
+
+        if let Some(obj) = effective_config.as_object_mut() {
+            obj.insert(
+                "insertFinalNewline".to_string(),
+                Value::from(oxfmt_options.insert_final_newline),
+            );
+            obj.insert(
+                "trimTrailingNewlines".to_string(),
+                Value::from(oxfmt_options.trim_trailing_newlines),
+            );
+        }
+        effective_config
+    }
+
+    /// Paths of the `.editorconfig` files applied to this path, ordered from the closest to the farthest.
+    #[cfg(feature = "napi")]
+    pub fn editorconfig_paths(&self, path: &Path) -> Vec<PathBuf> {
+        let nested_editorconfigs = self.find_nested_editorconfigs(path);
+        let is_rooted = nested_editorconfigs.last().is_some_and(|nested| nested.is_root);
+        let root_editorconfig_path = self
+            .editorconfig
+            .as_ref()
+            .filter(|_| !is_rooted)
+            .and(self.editorconfig_dir.as_ref())
+            .map(|dir| dir.join(".editorconfig"));
+        nested_editorconfigs
+            .iter()
+            .map(|nested| nested.path.clone())
+            .chain(root_editorconfig_path)
+            .collect()
+    }
+
+    /// Index and the matched glob of each `overrides` entry applied to this path, in the order they are applied.
+    #[cfg(feature = "napi")]
+    pub fn matched_overrides(&self, path: &Path) -> Vec<(usize, String)> {
+        self.overrides
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, config_override)| match config_override.files.matched(path, false) {
+                ignore::Match::Ignore(glob) => Some((idx, glob.original().to_string())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `raw_config` is the user's config the options are resolved from,
+    /// to tell which options are explicitly set.
+    fn to_resolved_options(
+        strategy: &FormatFileStrategy,
+        #[cfg_attr(not(feature = "napi"), expect(unused_variables))] raw_config: &Value,
+        format_options: FormatOptions,
+        oxfmt_options: &OxfmtOptions,
+        external_options: Value,
+    ) -> ResolvedOptions {
         let insert_final_newline = oxfmt_options.insert_final_newline;
+        let trim_trailing_newlines = oxfmt_options.trim_trailing_newlines;
+        let final_newline =
+            FinalNewline::new(format_options.line_ending, oxfmt_options.keep_line_endings);
 
         match strategy {
             FormatFileStrategy::OxcFormatter { .. } => ResolvedOptions::OxcFormatter {
                 format_options,
                 external_options,
                 insert_final_newline,
+                trim_trailing_newlines,
+                final_newline,
             },
             FormatFileStrategy::OxfmtToml { .. } => ResolvedOptions::OxfmtToml {
                 toml_options: build_toml_options(&format_options),
                 insert_final_newline,
+                trim_trailing_newlines,
+                final_newline,
             },
+            FormatFileStrategy::OxcFormatterJson { parser_name, .. } => {
+                ResolvedOptions::OxcFormatterJson {
+                    format_options: build_json_options(format_options, parser_name),
+                    sort_keys: oxfmt_options.sort_json_keys,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                }
+            }
             #[cfg(feature = "napi")]
-            FormatFileStrategy::ExternalFormatter { .. } => {
-                ResolvedOptions::ExternalFormatter { external_options, insert_final_newline }
+            FormatFileStrategy::ExternalFormatter { parser_name, .. } => {
+                let mut external_options = external_options;
+                // Code blocks in Markdown are formatted by the external formatter itself,
+                // so use its default `"auto"`, unless explicitly disabled.
+                // Our default `"off"` is only for the xxx-in-js support, which is still experimental.
+                if matches!(*parser_name, "markdown" | "mdx")
+                    && raw_config.get("embeddedLanguageFormatting").is_none()
+                    && let Some(obj) = external_options.as_object_mut()
+                {
+                    obj.insert("embeddedLanguageFormatting".to_string(), Value::from("auto"));
+                }
+                ResolvedOptions::ExternalFormatter {
+                    external_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                }
             }
             #[cfg(feature = "napi")]
             FormatFileStrategy::ExternalFormatterPackageJson { .. } => {
@@ -216,6 +626,8 @@ impl ConfigResolver {
                     external_options,
                     sort_package_json: oxfmt_options.sort_package_json,
                     insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
                 }
             }
             #[cfg(not(feature = "napi"))]
@@ -225,16 +637,21 @@ impl ConfigResolver {
         }
     }
 
-    /// Resolve format options for a specific file with `.editorconfig` overrides.
+    /// Resolve format options for a specific file with `.editorconfig` and `overrides` applied.
     /// This is the slow path, for fast path, see [`ConfigResolver::build_and_validate`].
+    /// `raw_config` already has the matching `overrides` merged, see [`ConfigResolver::apply_config_overrides`].
+    /// `props_list` is ordered from the closest `.editorconfig` to the farthest.
     fn resolve_with_overrides(
-        &self,
-        props: &EditorConfigProperties,
+        raw_config: &Value,
+        props_list: &[EditorConfigProperties],
     ) -> (FormatOptions, OxfmtOptions, Value) {
-        let mut oxfmtrc: Oxfmtrc = serde_json::from_value(self.raw_config.clone())
+        let mut oxfmtrc: Oxfmtrc = serde_json::from_value(raw_config.clone())
             .expect("`build_and_validate()` should catch this before `resolve()`");
 
-        apply_editorconfig(&mut oxfmtrc, props);
+        // Only unset values are applied, so the closest `.editorconfig` wins
+        for props in props_list {
+            apply_editorconfig(&mut oxfmtrc, props);
+        }
 
         let (format_options, oxfmt_options) = oxfmtrc
             .into_options()
@@ -242,7 +659,7 @@ impl ConfigResolver {
 
         // Apply our defaults for Prettier options too
         // e.g. set `printWidth: 100` if not specified (= Prettier default: 80)
-        let mut external_options = self.raw_config.clone();
+        let mut external_options = raw_config.clone();
         Oxfmtrc::populate_prettier_config(&format_options, &mut external_options);
 
         (format_options, oxfmt_options, external_options)
@@ -251,6 +668,267 @@ impl ConfigResolver {
 
 // ---
 
+/// An entry of `overrides` in `.oxfmtrc`.
+struct ConfigOverride {
+    /// Matcher built from `files`, using the same syntax as `ignorePatterns`.
+    files: Gitignore,
+    /// Raw `options`, merged over the base config when `files` matches.
+    options: Map<String, Value>,
+}
+
+impl ConfigResolver {
+    /// Take `overrides` out of the raw config and build matchers for them.
+    /// The shape is already validated by deserializing `Oxfmtrc` in `build_and_validate()`,
+    /// each entry is validated here by resolving options with it merged over the base config.
+    fn build_overrides(&mut self) -> Result<Vec<ConfigOverride>, String> {
+        let Some(Value::Array(entries)) =
+            self.raw_config.as_object_mut().and_then(|obj| obj.remove("overrides"))
+        else {
+            return Ok(vec![]);
+        };
+
+        let root = self.cwd.as_deref().unwrap_or(Path::new(""));
+        let mut overrides = vec![];
+        for (idx, mut entry) in entries.into_iter().enumerate() {
+            let options = match entry.get_mut("options").map(Value::take) {
+                Some(Value::Object(options)) => options,
+                _ => Map::new(),
+            };
+            for key in ["ignorePatterns", "overrides", "root"] {
+                if options.contains_key(key) {
+                    return Err(format!(
+                        "Failed to parse configuration.\n`{key}` is not allowed in `overrides[{idx}].options`"
+                    ));
+                }
+            }
+
+            let mut builder = GitignoreBuilder::new(root);
+            let patterns = entry.get("files").and_then(Value::as_array).into_iter().flatten();
+            for pattern in patterns.filter_map(Value::as_str) {
+                builder.add_line(None, pattern).map_err(|err| {
+                    format!(
+                        "Failed to parse configuration.\nInvalid `overrides[{idx}].files`: {err}"
+                    )
+                })?;
+            }
+            let files = builder.build().map_err(|err| {
+                format!("Failed to parse configuration.\nInvalid `overrides[{idx}].files`: {err}")
+            })?;
+
+            let mut merged = self.raw_config.clone();
+            merge_options(&mut merged, &options);
+            let oxfmtrc: Oxfmtrc = serde_json::from_value(merged)
+                .map_err(|err| format!("Failed to deserialize `overrides[{idx}]`: {err}"))?;
+            oxfmtrc
+                .into_options()
+                .map_err(|err| format!("Failed to parse configuration.\n{err}"))?;
+
+            overrides.push(ConfigOverride { files, options });
+        }
+        Ok(overrides)
+    }
+
+    /// Merge `options` of every `overrides` entry matching this path over the base config.
+    /// Later entries win over earlier ones.
+    /// Then the nested config files of this path are merged, they are closer to the file.
+    /// Returns the base config as is, if none of them match.
+    fn apply_config_overrides(&self, path: &Path) -> Cow<'_, Value> {
+        let mut raw_config = Cow::Borrowed(&self.raw_config);
+        for config_override in &self.overrides {
+            if config_override.files.matched(path, false).is_ignore() {
+                merge_options(raw_config.to_mut(), &config_override.options);
+            }
+        }
+
+        let nested_configs = self.find_nested_configs(path);
+        if nested_configs.last().is_some_and(|nested| nested.is_root) {
+            raw_config = Cow::Owned(Value::Object(Map::new()));
+        }
+        for nested in nested_configs.iter().rev() {
+            merge_options(raw_config.to_mut(), &nested.options);
+        }
+        raw_config
+    }
+}
+
+/// Shallowly merge `options` into `raw_config`, overwriting existing keys.
+fn merge_options(raw_config: &mut Value, options: &Map<String, Value>) {
+    if let Some(obj) = raw_config.as_object_mut() {
+        for (key, value) in options {
+            obj.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+// ---
+
+/// A `.oxfmtrc.json` found between a file and [`ConfigResolver::config_dir`].
+struct NestedConfig {
+    /// Options merged over the config files of parent directories,
+    /// without [`NON_INHERITED_CONFIG_KEYS`] which are not supported in nested config files.
+    options: Map<String, Value>,
+    /// Whether it has `root: true`, config files of parent directories are not used then.
+    is_root: bool,
+}
+
+impl ConfigResolver {
+    /// Find nested config files for this path, ordered from the closest to the farthest.
+    /// Stops at the first one with `root: true`.
+    fn find_nested_configs(&self, path: &Path) -> Vec<Arc<NestedConfig>> {
+        let Some(config_dir) = &self.config_dir else {
+            return vec![];
+        };
+        let Some(parent) = path.parent() else {
+            return vec![];
+        };
+
+        let mut found = vec![];
+        for dir in parent.ancestors() {
+            if dir == config_dir || !dir.starts_with(config_dir) {
+                break;
+            }
+            if let Some(nested) = self.load_nested_config(dir) {
+                let is_root = nested.is_root;
+                found.push(nested);
+                if is_root {
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    /// Invalid nested config files are skipped with a warning, instead of failing every file.
+    fn load_nested_config(&self, dir: &Path) -> Option<Arc<NestedConfig>> {
+        if let Ok(cache) = self.nested_configs.lock()
+            && let Some(nested) = cache.get(dir)
+        {
+            return nested.clone();
+        }
+
+        let nested = find_json_config_path(dir).and_then(|path| {
+            let raw_config = read_config_file(&path)
+                .and_then(|raw_config| {
+                    let oxfmtrc: Oxfmtrc = serde_json::from_value(raw_config.clone())
+                        .map_err(|err| format!("Failed to deserialize Oxfmtrc: {err}"))?;
+                    oxfmtrc.into_options()?;
+                    Ok(raw_config)
+                })
+                .inspect_err(|err| {
+                    tracing::warn!("Skipping invalid config file {}: {err}", path.display());
+                })
+                .ok()?;
+            let is_root = is_root_config(&raw_config);
+            let Value::Object(mut options) = raw_config else {
+                return None;
+            };
+            options.retain(|key, _| !NON_INHERITED_CONFIG_KEYS.contains(&key.as_str()));
+            Some(Arc::new(NestedConfig { options, is_root }))
+        });
+
+        if let Ok(mut cache) = self.nested_configs.lock() {
+            cache.insert(dir.to_path_buf(), nested.clone());
+        }
+        nested
+    }
+}
+
+// ---
+
+/// A `.editorconfig` found between a file and [`ConfigResolver::editorconfig_dir`].
+struct NestedEditorConfig {
+    #[cfg_attr(not(feature = "napi"), expect(dead_code))]
+    path: PathBuf,
+    editorconfig: EditorConfig,
+    /// Whether it has `root = true`, parent `.editorconfig` files are not used then.
+    is_root: bool,
+}
+
+impl ConfigResolver {
+    /// Find nested `.editorconfig` files for this path, ordered from the closest to the farthest.
+    /// Stops at the first one with `root = true`.
+    fn find_nested_editorconfigs(&self, path: &Path) -> Vec<Arc<NestedEditorConfig>> {
+        let Some(editorconfig_dir) = &self.editorconfig_dir else {
+            return vec![];
+        };
+        let Some(parent) = path.parent() else {
+            return vec![];
+        };
+
+        let mut found = vec![];
+        for dir in parent.ancestors() {
+            if dir == editorconfig_dir || !dir.starts_with(editorconfig_dir) {
+                break;
+            }
+            if let Some(nested) = self.load_nested_editorconfig(dir) {
+                let is_root = nested.is_root;
+                found.push(nested);
+                if is_root {
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    fn load_nested_editorconfig(&self, dir: &Path) -> Option<Arc<NestedEditorConfig>> {
+        if let Ok(cache) = self.nested_editorconfigs.lock()
+            && let Some(nested) = cache.get(dir)
+        {
+            return nested.clone();
+        }
+
+        let path = dir.join(".editorconfig");
+        let nested = utils::read_to_string(&path).ok().map(|str| {
+            Arc::new(NestedEditorConfig {
+                path: path.clone(),
+                is_root: is_root_editorconfig(&str),
+                editorconfig: EditorConfig::parse(&str).with_cwd(dir),
+            })
+        });
+
+        if let Ok(mut cache) = self.nested_editorconfigs.lock() {
+            cache.insert(dir.to_path_buf(), nested.clone());
+        }
+        nested
+    }
+
+    /// Collect the `.editorconfig` properties for this path, ordered from the closest to the farthest.
+    /// The `.editorconfig` found from cwd is included, unless a nested one has `root = true`.
+    fn collect_editorconfig_properties(
+        &self,
+        nested_editorconfigs: &[Arc<NestedEditorConfig>],
+        path: &Path,
+    ) -> Vec<EditorConfigProperties> {
+        let is_rooted = nested_editorconfigs.last().is_some_and(|nested| nested.is_root);
+        nested_editorconfigs
+            .iter()
+            .map(|nested| &nested.editorconfig)
+            .chain(self.editorconfig.as_ref().filter(|_| !is_rooted))
+            .map(|editorconfig| editorconfig.resolve(path))
+            .collect()
+    }
+}
+
+/// Check if the `.editorconfig` content has `root = true` in its preamble, before any section.
+fn is_root_editorconfig(source: &str) -> bool {
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return false;
+        }
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim().eq_ignore_ascii_case("root")
+        {
+            return value.trim().eq_ignore_ascii_case("true");
+        }
+    }
+    false
+}
+
 /// Check if `.editorconfig` has per-file overrides for this path.
 ///
 /// Returns `Some(props)` if the resolved properties differ from the root `[*]` section.
@@ -367,3 +1045,86 @@ fn build_toml_options(format_options: &FormatOptions) -> TomlFormatterOptions {
         ..Default::default()
     }
 }
+
+/// Build `oxc_formatter` options for JSON-family files.
+/// The same as Prettier's `json`, `jsonc` and `json5` parsers,
+/// keys and strings are printed as is, and trailing commas are not allowed in `json`.
+fn build_json_options(mut format_options: FormatOptions, parser_name: &str) -> FormatOptions {
+    format_options.quote_properties = QuoteProperties::Preserve;
+    format_options.preserve_string_literals = true;
+    if parser_name == "json" {
+        format_options.trailing_commas = TrailingCommas::None;
+    }
+    format_options
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "napi")]
+    use std::path::PathBuf;
+
+    #[cfg(feature = "napi")]
+    use serde_json::json;
+
+    #[cfg(feature = "napi")]
+    use super::{ConfigResolver, FormatFileStrategy, ResolvedOptions};
+    use super::{dedup_ignore_patterns, find_cancelled_ignore_patterns, is_root_editorconfig};
+
+    #[test]
+    fn test_is_root_editorconfig() {
+        assert!(is_root_editorconfig("root = true\n[*]\nindent_size = 2\n"));
+        assert!(is_root_editorconfig("# comment\n\nROOT=True\n"));
+        assert!(!is_root_editorconfig("root = false\n"));
+        assert!(!is_root_editorconfig("[*]\nroot = true\n"));
+        assert!(!is_root_editorconfig(""));
+    }
+
+    #[test]
+    fn test_dedup_ignore_patterns() {
+        let patterns =
+            |patterns: &[&str]| patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        // Only the last occurrence is kept, so `dist` stays ignored
+        assert_eq!(
+            dedup_ignore_patterns(patterns(&["dist", "!dist", "build", "dist"]), String::as_str),
+            patterns(&["!dist", "build", "dist"])
+        );
+        assert_eq!(
+            dedup_ignore_patterns(patterns(&["# a", "", "# a", ""]), String::as_str),
+            patterns(&["# a", "", "# a", ""])
+        );
+
+        let patterns =
+            patterns(&["dist", "build", "!dist", "!coverage", "coverage", "!build/keep"]);
+        assert_eq!(find_cancelled_ignore_patterns(&patterns), vec![("dist", "!dist")]);
+    }
+
+    #[cfg(feature = "napi")]
+    #[test]
+    fn test_markdown_embedded_language_formatting() {
+        let embedded_language_formatting = |config, file_name: &str| {
+            let mut config_resolver = ConfigResolver::from_value(config);
+            config_resolver.build_and_validate().unwrap();
+            let strategy = FormatFileStrategy::try_from(PathBuf::from(file_name)).unwrap();
+            let ResolvedOptions::ExternalFormatter { external_options, .. } =
+                config_resolver.resolve(&strategy)
+            else {
+                unreachable!("should be formatted by the external formatter");
+            };
+            external_options["embeddedLanguageFormatting"].clone()
+        };
+
+        // Code blocks in Markdown are formatted by default
+        assert_eq!(embedded_language_formatting(json!({}), "README.md"), json!("auto"));
+        assert_eq!(embedded_language_formatting(json!({}), "page.mdx"), json!("auto"));
+        assert_eq!(embedded_language_formatting(json!({}), "index.html"), json!("off"));
+        // Unless explicitly disabled
+        assert_eq!(
+            embedded_language_formatting(
+                json!({ "embeddedLanguageFormatting": "off" }),
+                "README.md"
+            ),
+            json!("off")
+        );
+    }
+}
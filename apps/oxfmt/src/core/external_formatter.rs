@@ -1,11 +1,19 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use napi::{
     Status,
-    bindgen_prelude::{FnArgs, Promise, block_on},
+    bindgen_prelude::{FnArgs, Promise, ToNapiValue, block_on},
+    sys,
     threadsafe_function::ThreadsafeFunction,
 };
 use serde_json::Value;
+use tracing::debug;
+
+/// How many times a failed call to the JS `formatFile` callback is retried by default.
+pub const DEFAULT_CALL_RETRIES: u32 = 1;
+
+/// How long to wait before the first retry, doubled for each following retry.
+const CALL_RETRY_BACKOFF: Duration = Duration::from_millis(20);
 
 /// Type alias for the init external formatter callback function signature.
 /// Takes num_threads as argument and returns plugin languages.
@@ -26,11 +34,11 @@ pub type JsInitExternalFormatterCb = ThreadsafeFunction<
 /// Takes (options, tag_name, code) as separate arguments and returns formatted code.
 pub type JsFormatEmbeddedCb = ThreadsafeFunction<
     // Input arguments
-    FnArgs<(Value, String, String)>, // (options, tag_name, code)
+    FnArgs<(SharedOptions, String, String)>, // (options, tag_name, code)
     // Return type (what JS function returns)
     Promise<String>,
     // Arguments (repeated)
-    FnArgs<(Value, String, String)>,
+    FnArgs<(SharedOptions, String, String)>,
     // Error status
     Status,
     // CalleeHandled
@@ -38,29 +46,51 @@ pub type JsFormatEmbeddedCb = ThreadsafeFunction<
 >;
 
 /// Type alias for the callback function signature.
-/// Takes (options, parser_name, file_name, code) as separate arguments and returns formatted code.
+/// Takes (options, parser_name, file_name, code) as separate arguments and returns formatted code,
+/// or `null` if no parser or plugin can handle the file.
 pub type JsFormatFileCb = ThreadsafeFunction<
     // Input arguments
-    FnArgs<(Value, String, String, String)>, // (options, parser_name, file_name, code)
+    FnArgs<(SharedOptions, String, String, String)>, // (options, parser_name, file_name, code)
     // Return type (what JS function returns)
-    Promise<String>,
+    Promise<Option<String>>,
     // Arguments (repeated)
-    FnArgs<(Value, String, String, String)>,
+    FnArgs<(SharedOptions, String, String, String)>,
     // Error status
     Status,
     // CalleeHandled
     false,
 >;
 
+/// Options passed to the JS callbacks.
+///
+/// The same options are passed to every call for a file, e.g. for each embedded code,
+/// so they are shared instead of deep-cloned per call,
+/// and only converted to a JS object when the call is made on the JS thread.
+#[derive(Clone)]
+pub struct SharedOptions(Arc<Value>);
+
+impl From<Arc<Value>> for SharedOptions {
+    fn from(options: Arc<Value>) -> Self {
+        Self(options)
+    }
+}
+
+impl ToNapiValue for SharedOptions {
+    unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> napi::Result<sys::napi_value> {
+        // SAFETY: Same as converting the `Value` itself, `env` is passed as is from napi
+        unsafe { <&Value>::to_napi_value(env, &val.0) }
+    }
+}
+
 /// Callback function type for formatting embedded code with config.
 /// Takes (options, tag_name, code) and returns formatted code or an error.
 type FormatEmbeddedWithConfigCallback =
-    Arc<dyn Fn(&Value, &str, &str) -> Result<String, String> + Send + Sync>;
+    Arc<dyn Fn(&SharedOptions, &str, &str) -> Result<String, String> + Send + Sync>;
 
 /// Callback function type for formatting files with config.
 /// Takes (options, parser_name, file_name, code) and returns formatted code or an error.
 type FormatFileWithConfigCallback =
-    Arc<dyn Fn(&Value, &str, &str, &str) -> Result<String, String> + Send + Sync>;
+    Arc<dyn Fn(&SharedOptions, &str, &str, &str) -> Result<String, String> + Send + Sync>;
 
 /// Callback function type for init external formatter.
 /// Takes num_threads and returns plugin languages.
@@ -87,14 +117,16 @@ impl std::fmt::Debug for ExternalFormatter {
 
 impl ExternalFormatter {
     /// Create an [`ExternalFormatter`] from JS callbacks.
+    /// A `formatFile` call failing to reach the JS side is retried up to `call_retries` times.
     pub fn new(
         init_cb: JsInitExternalFormatterCb,
         format_embedded_cb: JsFormatEmbeddedCb,
         format_file_cb: JsFormatFileCb,
+        call_retries: u32,
     ) -> Self {
         let rust_init = wrap_init_external_formatter(init_cb);
         let rust_format_embedded = wrap_format_embedded(format_embedded_cb);
-        let rust_format_file = wrap_format_file(format_file_cb);
+        let rust_format_file = wrap_format_file(format_file_cb, call_retries);
         Self {
             init: rust_init,
             format_embedded: rust_format_embedded,
@@ -108,8 +140,9 @@ impl ExternalFormatter {
     }
 
     /// Convert this external formatter to the oxc_formatter::EmbeddedFormatter type.
-    /// The options is captured in the closure and passed to JS on each call.
+    /// The options is captured in the closure and shared with JS on each call.
     pub fn to_embedded_formatter(&self, options: Value) -> oxc_formatter::EmbeddedFormatter {
+        let options = SharedOptions(Arc::new(options));
         let format_embedded = Arc::clone(&self.format_embedded);
         let callback =
             Arc::new(move |tag_name: &str, code: &str| (format_embedded)(&options, tag_name, code));
@@ -119,12 +152,12 @@ impl ExternalFormatter {
     /// Format non-js file using the JS callback.
     pub fn format_file(
         &self,
-        options: &Value,
+        options: Value,
         parser_name: &str,
         file_name: &str,
         code: &str,
     ) -> Result<String, String> {
-        (self.format_file)(options, parser_name, file_name, code)
+        (self.format_file)(&SharedOptions(Arc::new(options)), parser_name, file_name, code)
     }
 }
 
@@ -161,7 +194,7 @@ fn wrap_init_external_formatter(cb: JsInitExternalFormatterCb) -> InitExternalFo
 
 /// Wrap JS `formatEmbeddedCode` callback as a normal Rust function.
 fn wrap_format_embedded(cb: JsFormatEmbeddedCb) -> FormatEmbeddedWithConfigCallback {
-    Arc::new(move |options: &Value, tag_name: &str, code: &str| {
+    Arc::new(move |options: &SharedOptions, tag_name: &str, code: &str| {
         block_on(async {
             let status = cb
                 .call_async(FnArgs::from((options.clone(), tag_name.to_string(), code.to_string())))
@@ -182,20 +215,24 @@ fn wrap_format_embedded(cb: JsFormatEmbeddedCb) -> FormatEmbeddedWithConfigCallb
 }
 
 /// Wrap JS `formatFile` callback as a normal Rust function.
-fn wrap_format_file(cb: JsFormatFileCb) -> FormatFileWithConfigCallback {
-    Arc::new(move |options: &Value, parser_name: &str, file_name: &str, code: &str| {
+fn wrap_format_file(cb: JsFormatFileCb, call_retries: u32) -> FormatFileWithConfigCallback {
+    Arc::new(move |options: &SharedOptions, parser_name: &str, file_name: &str, code: &str| {
         block_on(async {
-            let status = cb
-                .call_async(FnArgs::from((
+            let status = call_with_retries(call_retries, || {
+                cb.call_async(FnArgs::from((
                     options.clone(),
                     parser_name.to_string(),
                     file_name.to_string(),
                     code.to_string(),
                 )))
-                .await;
+            })
+            .await;
             match status {
                 Ok(promise) => match promise.await {
-                    Ok(formatted_code) => Ok(formatted_code),
+                    Ok(Some(formatted_code)) => Ok(formatted_code),
+                    Ok(None) => Err(format!(
+                        "No parser or plugin found for file: '{file_name}', parser: '{parser_name}'"
+                    )),
                     Err(err) => Err(format!(
                         "JS formatFile promise rejected for file: '{file_name}', parser: '{parser_name}': {err}"
                     )),
@@ -207,3 +244,103 @@ fn wrap_format_file(cb: JsFormatFileCb) -> FormatFileWithConfigCallback {
         })
     })
 }
+
+/// Run `call` and retry it up to `retries` times while it fails, waiting a bit longer each time.
+///
+/// Only meant for calling a JS callback, where an `Err` means the call could not be queued,
+/// which is usually transient under load.
+/// The returned promise is not awaited here,
+/// since its rejection is a formatting error which would fail again.
+pub async fn call_with_retries<T, E, F, Fut>(retries: u32, mut call: F) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = CALL_RETRY_BACKOFF;
+    for retry in 1..=retries {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                debug!("Failed to call JS callback, retrying ({retry}/{retries}): {err}");
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+    }
+    call().await
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::Cell,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::{Value, json};
+
+    use super::{ExternalFormatter, SharedOptions, call_with_retries};
+
+    #[test]
+    fn test_embedded_formatter_shares_options() {
+        let received = Arc::new(Mutex::new(Vec::<Arc<Value>>::new()));
+        let external_formatter = ExternalFormatter {
+            init: Arc::new(|_: usize| Ok(vec![])),
+            format_embedded: Arc::new({
+                let received = Arc::clone(&received);
+                move |options: &SharedOptions, _: &str, code: &str| {
+                    received.lock().unwrap().push(Arc::clone(&options.0));
+                    Ok(code.to_string())
+                }
+            }),
+            format_file: Arc::new(|_: &SharedOptions, _: &str, _: &str, code: &str| {
+                Ok(code.to_string())
+            }),
+        };
+
+        let embedded_formatter = external_formatter.to_embedded_formatter(json!({ "semi": false }));
+        embedded_formatter.format("css", "a{}").unwrap();
+        embedded_formatter.format("html", "<a></a>").unwrap();
+
+        // Every call receives the same options, without cloning them
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(Arc::ptr_eq(&received[0], &received[1]));
+        assert_eq!(*received[0], json!({ "semi": false }));
+    }
+
+    #[tokio::test]
+    async fn test_call_with_retries() {
+        // Succeeds after a transient failure
+        let calls = Cell::new(0);
+        let result = call_with_retries(1, || {
+            calls.set(calls.get() + 1);
+            let result = if calls.get() == 1 { Err("busy") } else { Ok("formatted") };
+            async move { result }
+        })
+        .await;
+        assert_eq!(result, Ok("formatted"));
+        assert_eq!(calls.get(), 2);
+
+        // Gives up after the configured retries
+        let calls = Cell::new(0);
+        let result = call_with_retries(2, || {
+            calls.set(calls.get() + 1);
+            async { Err::<(), _>("busy") }
+        })
+        .await;
+        assert_eq!(result, Err("busy"));
+        assert_eq!(calls.get(), 3);
+
+        // Not retried when disabled
+        let calls = Cell::new(0);
+        let result = call_with_retries(0, || {
+            calls.set(calls.get() + 1);
+            async { Err::<(), _>("busy") }
+        })
+        .await;
+        assert_eq!(result, Err("busy"));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -1,15 +1,16 @@
 #[cfg(feature = "napi")]
 use std::borrow::Cow;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use oxc_allocator::AllocatorPool;
 use oxc_diagnostics::OxcDiagnostic;
-use oxc_formatter::{FormatOptions, Formatter, enable_jsx_source_type, get_parse_options};
+use oxc_formatter::{FormatOptions, Formatter, get_parse_options};
 use oxc_parser::Parser;
 use oxc_span::SourceType;
+use rayon::prelude::*;
 use serde_json::Value;
 
-use super::{FormatFileStrategy, ResolvedOptions};
+use super::{ConfigResolver, FormatFileStrategy, ResolvedOptions, format_json, utils};
 
 pub enum FormatResult {
     Success { is_changed: bool, code: String },
@@ -48,13 +49,18 @@ impl SourceFormatter {
         source_text: &str,
         resolved_options: ResolvedOptions,
     ) -> FormatResult {
-        let (result, insert_final_newline) = match (entry, resolved_options) {
+        // NOTE: `final_newline` is only used by the language server,
+        // the CLI always writes the line endings printed by each formatter
+        let (result, insert_final_newline, trim_trailing_newlines) = match (entry, resolved_options)
+        {
             (
                 FormatFileStrategy::OxcFormatter { path, source_type },
                 ResolvedOptions::OxcFormatter {
                     format_options,
                     external_options,
                     insert_final_newline,
+                    trim_trailing_newlines,
+                    ..
                 },
             ) => (
                 self.format_by_oxc_formatter(
@@ -65,18 +71,48 @@ impl SourceFormatter {
                     external_options,
                 ),
                 insert_final_newline,
+                trim_trailing_newlines,
             ),
             (
                 FormatFileStrategy::OxfmtToml { .. },
-                ResolvedOptions::OxfmtToml { toml_options, insert_final_newline },
-            ) => (Ok(Self::format_by_toml(source_text, toml_options)), insert_final_newline),
+                ResolvedOptions::OxfmtToml {
+                    toml_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    ..
+                },
+            ) => (
+                Ok(Self::format_by_toml(source_text, toml_options)),
+                insert_final_newline,
+                trim_trailing_newlines,
+            ),
+            (
+                FormatFileStrategy::OxcFormatterJson { .. },
+                ResolvedOptions::OxcFormatterJson {
+                    format_options,
+                    sort_keys,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    ..
+                },
+            ) => (
+                self.format_by_oxc_formatter_json(source_text, format_options, sort_keys),
+                insert_final_newline,
+                trim_trailing_newlines,
+            ),
             #[cfg(feature = "napi")]
             (
                 FormatFileStrategy::ExternalFormatter { path, parser_name },
-                ResolvedOptions::ExternalFormatter { external_options, insert_final_newline },
+                ResolvedOptions::ExternalFormatter {
+                    external_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    ..
+                },
             ) => (
                 self.format_by_external_formatter(source_text, path, parser_name, external_options),
                 insert_final_newline,
+                trim_trailing_newlines,
             ),
             #[cfg(feature = "napi")]
             (
@@ -85,6 +121,8 @@ impl SourceFormatter {
                     external_options,
                     sort_package_json,
                     insert_final_newline,
+                    trim_trailing_newlines,
+                    ..
                 },
             ) => (
                 self.format_by_external_formatter_package_json(
@@ -95,19 +133,29 @@ impl SourceFormatter {
                     sort_package_json,
                 ),
                 insert_final_newline,
+                trim_trailing_newlines,
             ),
             _ => unreachable!("FormatFileStrategy and ResolvedOptions variant mismatch"),
         };
 
         match result {
+            Ok(code) if has_significant_trailing_newlines(entry, &code) => {
+                FormatResult::Success { is_changed: source_text != code, code }
+            }
             Ok(mut code) => {
-                // NOTE: `insert_final_newline` relies on the fact that:
-                // - each formatter already ensures there is traliling newline
+                // NOTE: `insert_final_newline` and `trim_trailing_newlines` rely on the fact that:
+                // - each formatter already ensures there is exactly one trailing newline
                 // - each formatter does not have an option to disable trailing newline
-                // So we can trim it here without allocating new string.
-                if !insert_final_newline {
+                // So we only need to touch the code if other than one newline is expected.
+                let newlines =
+                    final_newline_count(source_text, insert_final_newline, trim_trailing_newlines);
+                if newlines == 0 {
                     let trimmed_len = code.trim_end().len();
                     code.truncate(trimmed_len);
+                } else if newlines > 1 {
+                    let trimmed_len = code.trim_end_matches(['\r', '\n']).len();
+                    let line_ending = code.split_off(trimmed_len);
+                    code.push_str(&line_ending.repeat(newlines));
                 }
 
                 FormatResult::Success { is_changed: source_text != code, code }
@@ -125,7 +173,6 @@ impl SourceFormatter {
         format_options: FormatOptions,
         external_options: Value,
     ) -> Result<String, OxcDiagnostic> {
-        let source_type = enable_jsx_source_type(source_type);
         let allocator = self.allocator_pool.get();
 
         let ret = Parser::new(&allocator, source_text, source_type)
@@ -179,6 +226,17 @@ impl SourceFormatter {
         Ok(code.into_code())
     }
 
+    /// Format JSON-family file using oxc_formatter.
+    fn format_by_oxc_formatter_json(
+        &self,
+        source_text: &str,
+        format_options: FormatOptions,
+        sort_keys: bool,
+    ) -> Result<String, OxcDiagnostic> {
+        let allocator = self.allocator_pool.get();
+        format_json(&allocator, source_text, format_options, sort_keys)
+    }
+
     /// Format TOML file using `toml`.
     fn format_by_toml(source_text: &str, options: oxc_toml::Options) -> String {
         oxc_toml::format(source_text, options)
@@ -186,7 +244,6 @@ impl SourceFormatter {
 
     /// Format non-JS/TS file using external formatter (Prettier).
     #[cfg(feature = "napi")]
-    #[expect(clippy::needless_pass_by_value)]
     fn format_by_external_formatter(
         &self,
         source_text: &str,
@@ -205,10 +262,13 @@ impl SourceFormatter {
         //
         // We are specifying the `parser` for perf, so `filepath` is not actually necessary,
         // but since some plugins might depend on `filepath`, we pass the actual file name as well.
-        let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        let file_name = utils::file_name_lossy(path);
+        if let Cow::Owned(name) = &file_name {
+            tracing::warn!("File name is not valid UTF-8, using `{name}`: {}", path.display());
+        }
 
         external_formatter
-            .format_file(&external_options, parser_name, file_name, source_text)
+            .format_file(external_options, parser_name, &file_name, source_text)
             .map_err(|err| {
                 OxcDiagnostic::error(format!(
                     "Failed to format file with external formatter: {}\n{err}",
@@ -225,10 +285,13 @@ impl SourceFormatter {
         path: &Path,
         parser_name: &str,
         external_options: Value,
-        sort_package_json: bool,
+        sort_package_json: Option<oxc_formatter::oxfmtrc::SortPackageJsonOptions>,
     ) -> Result<String, OxcDiagnostic> {
-        let source_text: Cow<'_, str> = if sort_package_json {
-            let options = sort_package_json::SortOptions { sort_scripts: false, pretty: false };
+        let source_text: Cow<'_, str> = if let Some(sort_options) = sort_package_json {
+            let options = sort_package_json::SortOptions {
+                sort_scripts: sort_options.sort_scripts,
+                pretty: sort_options.pretty,
+            };
             Cow::Owned(
                 sort_package_json::sort_package_json_with_options(source_text, &options).map_err(
                     |err| {
@@ -246,3 +309,339 @@ impl SourceFormatter {
         self.format_by_external_formatter(&source_text, path, parser_name, external_options)
     }
 }
+
+// ---
+
+/// Returns how many line breaks the formatted code should end with.
+///
+/// - `insert_final_newline`: at least one
+/// - `trim_trailing_newlines`: at most one, otherwise the trailing line breaks of `source_text` are kept
+///
+/// Empty files should stay empty regardless of the result.
+pub fn final_newline_count(
+    source_text: &str,
+    insert_final_newline: bool,
+    trim_trailing_newlines: bool,
+) -> usize {
+    let kept = if trim_trailing_newlines {
+        0
+    } else {
+        // `\r\n` is a single line break
+        let trailing = &source_text[source_text.trim_end_matches(['\r', '\n']).len()..];
+        trailing.len() - trailing.matches("\r\n").count()
+    };
+    if insert_final_newline { kept.max(1) } else { kept }
+}
+
+/// Returns whether the trailing line breaks of the formatted `code` are part of its content,
+/// then they are kept as printed regardless of `insert_final_newline` and `trim_trailing_newlines`.
+///
+/// This is the case for YAML ending with a block scalar with the keep chomping indicator,
+/// e.g. `key: |+`, whose value includes all the trailing line breaks.
+pub fn has_significant_trailing_newlines(entry: &FormatFileStrategy, code: &str) -> bool {
+    matches!(entry, FormatFileStrategy::ExternalFormatter { parser_name: "yaml", .. })
+        && ends_with_keep_block_scalar(code)
+}
+
+/// Returns whether the YAML `code` ends inside a block scalar with the keep chomping indicator.
+/// The content of block scalars is skipped, so lines looking like a header there are not misread.
+fn ends_with_keep_block_scalar(code: &str) -> bool {
+    // Indentation of the line with the header of the current block scalar, and whether it keeps line breaks
+    let mut block_scalar: Option<(usize, bool)> = None;
+    for line in code.lines() {
+        let content = line.trim_start();
+        // Empty lines belong to the block scalar
+        if content.is_empty() {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        if block_scalar.is_some_and(|(header_indent, _)| indent > header_indent) {
+            continue;
+        }
+        block_scalar = block_scalar_header(content).map(|keep| (indent, keep));
+    }
+    block_scalar.is_some_and(|(_, keep)| keep)
+}
+
+/// Returns whether the line starts a block scalar, e.g. `key: |`, `- >-` or `key: !!str |2+ # comment`,
+/// and whether it has the keep chomping indicator `+`.
+fn block_scalar_header(content: &str) -> Option<bool> {
+    let content = content.split(" #").next().unwrap_or(content);
+    let mut tokens = content.split_whitespace().rev();
+    let indicators = tokens.next()?.strip_prefix(['|', '>'])?;
+    if indicators.len() > 2 || !indicators.chars().all(|c| matches!(c, '+' | '-' | '1'..='9')) {
+        return None;
+    }
+    // Tags and anchors can precede the header
+    let preceding = tokens.find(|token| !token.starts_with(['!', '&']));
+    match preceding {
+        None | Some("-" | "---") => Some(indicators.contains('+')),
+        Some(key) if key.ends_with(':') => Some(indicators.contains('+')),
+        Some(_) => None,
+    }
+}
+
+// ---
+
+/// Format the given files in parallel on the global `rayon` thread pool,
+/// without walking directories or writing back like `FormatRunner`.
+///
+/// `options` is the same as `.oxfmtrc.json`, it is resolved once and shared by all threads.
+/// Each thread reuses its own allocator from the pool of [`SourceFormatter`].
+///
+/// Returns the formatted code or the error for each path, in the same order as `paths`.
+pub fn format_paths(
+    paths: &[PathBuf],
+    options: Value,
+    #[cfg(feature = "napi")] external_formatter: super::ExternalFormatter,
+) -> Vec<(PathBuf, Result<String, String>)> {
+    let fail_all = |err: &str| -> Vec<(PathBuf, Result<String, String>)> {
+        paths.iter().map(|path| (path.clone(), Err(err.to_string()))).collect()
+    };
+
+    let mut config_resolver = ConfigResolver::from_value(options);
+    if let Err(err) = config_resolver.build_and_validate() {
+        return fail_all(&format!("Failed to parse configuration: {err}"));
+    }
+
+    let num_of_threads = rayon::current_num_threads();
+
+    // Same as `FormatRunner`, the JS side is initialized with the same number of threads as `rayon`.
+    // `ExternalFormatter` only holds `Send + Sync` callbacks, so it can be shared by all threads.
+    // Use `block_in_place()` to avoid nested async runtime access
+    #[cfg(feature = "napi")]
+    if let Err(err) = tokio::task::block_in_place(|| external_formatter.init(num_of_threads)) {
+        return fail_all(&format!("Failed to setup external formatter: {err}"));
+    }
+
+    let source_formatter = SourceFormatter::new(num_of_threads);
+    #[cfg(feature = "napi")]
+    let source_formatter = source_formatter.with_external_formatter(Some(external_formatter));
+
+    paths
+        .par_iter()
+        .map(|path| (path.clone(), format_path(&source_formatter, &config_resolver, path)))
+        .collect()
+}
+
+/// Format `source_text` as if it was read from `filepath`, without touching the file system.
+/// The same as `--stdin-filepath`, e.g. for editors formatting an unsaved buffer.
+///
+/// `filepath` only picks the formatter by its name, it does not need to exist.
+/// `parser` picks the formatter instead, like Prettier's `--parser`, see [`FormatFileStrategy::from_parser_name`].
+/// `options` is the same as `.oxfmtrc.json`.
+///
+/// # Errors
+/// Returns error if the options are invalid, the file type or the parser is not supported,
+/// or formatting fails.
+pub fn format_stdin(
+    source_text: &str,
+    filepath: &Path,
+    parser: Option<&str>,
+    options: Value,
+    #[cfg(feature = "napi")] external_formatter: super::ExternalFormatter,
+) -> Result<String, String> {
+    // Single threaded, the same as `StdinRunner`
+    let num_of_threads = 1;
+
+    let mut config_resolver = ConfigResolver::from_value(options);
+    config_resolver
+        .build_and_validate()
+        .map_err(|err| format!("Failed to parse configuration: {err}"))?;
+
+    // Use `block_in_place()` to avoid nested async runtime access
+    #[cfg(feature = "napi")]
+    tokio::task::block_in_place(|| external_formatter.init(num_of_threads))
+        .map_err(|err| format!("Failed to setup external formatter: {err}"))?;
+
+    let strategy = match parser {
+        Some(parser) => FormatFileStrategy::from_parser_name(filepath.to_path_buf(), parser)?,
+        None => FormatFileStrategy::try_from(filepath.to_path_buf())
+            .map_err(|()| format!("Unsupported file type: {}", filepath.display()))?,
+    };
+
+    let source_formatter = SourceFormatter::new(num_of_threads);
+    #[cfg(feature = "napi")]
+    let source_formatter = source_formatter.with_external_formatter(Some(external_formatter));
+
+    // Use `block_in_place()` to avoid nested async runtime access
+    tokio::task::block_in_place(|| {
+        format_source_text(&source_formatter, &config_resolver, &strategy, source_text)
+    })
+}
+
+fn format_path(
+    source_formatter: &SourceFormatter,
+    config_resolver: &ConfigResolver,
+    path: &Path,
+) -> Result<String, String> {
+    let strategy = FormatFileStrategy::try_from(path.to_path_buf())
+        .map_err(|()| format!("Unsupported file type: {}", path.display()))?;
+    let source_text = utils::read_to_string(path)
+        .map_err(|err| format!("Failed to read file: {}\n{err}", path.display()))?;
+
+    format_source_text(source_formatter, config_resolver, &strategy, &source_text)
+}
+
+fn format_source_text(
+    source_formatter: &SourceFormatter,
+    config_resolver: &ConfigResolver,
+    strategy: &FormatFileStrategy,
+    source_text: &str,
+) -> Result<String, String> {
+    let resolved_options = config_resolver.resolve(strategy);
+    match source_formatter.format(strategy, source_text, resolved_options) {
+        FormatResult::Success { code, .. } => Ok(code),
+        FormatResult::Error(diagnostics) => {
+            Err(diagnostics.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "napi"))]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use serde_json::json;
+
+    use super::{ends_with_keep_block_scalar, final_newline_count, format_paths, format_stdin};
+    use crate::core::{ExternalFormatter, SharedOptions};
+
+    fn external_formatter() -> ExternalFormatter {
+        ExternalFormatter {
+            init: Arc::new(|_: usize| Ok(vec![])),
+            format_embedded: Arc::new(
+                |_: &SharedOptions, _: &str, code: &str| Ok(code.to_string()),
+            ),
+            format_file: Arc::new(|_: &SharedOptions, _: &str, _: &str, code: &str| {
+                Ok(code.to_string())
+            }),
+        }
+    }
+
+    #[test]
+    fn test_format_paths() {
+        let paths = vec![
+            PathBuf::from("test/fixtures/multiple_files/simple.js"),
+            PathBuf::from("test/fixtures/multiple_files/simple.ts"),
+            PathBuf::from("test/fixtures/multiple_files/unknown.xyz"),
+            PathBuf::from("test/fixtures/multiple_files/missing.ts"),
+        ];
+
+        let results = format_paths(&paths, json!({ "semi": false }), external_formatter());
+        assert_eq!(
+            results.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+            paths.iter().collect::<Vec<_>>()
+        );
+        assert!(results[0].1.as_ref().is_ok_and(|code| code.starts_with("const foo = \"bar\"\n")));
+        assert!(results[1].1.is_ok());
+        assert!(results[2].1.as_ref().is_err_and(|err| err.starts_with("Unsupported file type")));
+        assert!(results[3].1.as_ref().is_err_and(|err| err.starts_with("Failed to read file")));
+    }
+
+    #[test]
+    fn test_format_paths_invalid_options() {
+        let paths = vec![PathBuf::from("test/fixtures/multiple_files/simple.js")];
+
+        let results = format_paths(&paths, json!({ "printWidth": "wide" }), external_formatter());
+        assert!(
+            results[0]
+                .1
+                .as_ref()
+                .is_err_and(|err| err.starts_with("Failed to parse configuration"))
+        );
+    }
+
+    #[test]
+    fn test_final_newline_count() {
+        assert_eq!(final_newline_count("a\n\n\n", true, true), 1);
+        assert_eq!(final_newline_count("a\n\n\n", true, false), 3);
+        assert_eq!(final_newline_count("a", true, false), 1);
+        assert_eq!(final_newline_count("a\n\n", false, true), 0);
+        assert_eq!(final_newline_count("a\r\n\r\n", false, false), 2);
+        assert_eq!(final_newline_count("a", false, false), 0);
+    }
+
+    #[test]
+    fn test_ends_with_keep_block_scalar() {
+        assert!(ends_with_keep_block_scalar("a: 1\nb: |+\n  text\n\n"));
+        assert!(ends_with_keep_block_scalar("- >+\n  text\n"));
+        assert!(ends_with_keep_block_scalar("a: !!str &anchor |2+ # comment\n    text\n\n\n"));
+        assert!(ends_with_keep_block_scalar("a:\n  b: |+\n    c: |\n    d\n"));
+        // Not kept or not at the end
+        assert!(!ends_with_keep_block_scalar("a: |\n  text\n\n"));
+        assert!(!ends_with_keep_block_scalar("a: |+\n  text\nb: 1\n"));
+        assert!(!ends_with_keep_block_scalar("a: |-\n  text\n"));
+        // Not a header
+        assert!(!ends_with_keep_block_scalar("a: b |+\n"));
+        assert!(!ends_with_keep_block_scalar("a: \"|+\"\n"));
+    }
+
+    #[test]
+    fn test_format_stdin_yaml_keep_block_scalar() {
+        let format = |source_text: &str| {
+            let options = json!({ "insertFinalNewline": false });
+            format_stdin(source_text, Path::new("config.yaml"), None, options, external_formatter())
+        };
+
+        // Trailing line breaks are part of the value of the block scalar
+        assert_eq!(format("a: |+\n  text\n\n\n"), Ok("a: |+\n  text\n\n\n".to_string()));
+        assert_eq!(format("a: |\n  text\n\n\n"), Ok("a: |\n  text".to_string()));
+    }
+
+    #[test]
+    fn test_format_stdin() {
+        let format = |source_text: &str, filepath: &str| {
+            format_stdin(source_text, Path::new(filepath), None, json!({}), external_formatter())
+        };
+
+        // The file does not need to exist
+        assert_eq!(format("const a   =   1", "missing/file.ts"), Ok("const a = 1;\n".to_string()));
+        assert_eq!(format(r#"{"a":1}"#, "missing/data.json"), Ok("{ \"a\": 1 }\n".to_string()));
+        assert!(format("a", "file.xyz").is_err_and(|err| err.starts_with("Unsupported file type")));
+        assert!(format("const a = ;", "file.ts").is_err());
+    }
+
+    #[test]
+    fn test_format_stdin_with_parser() {
+        let format = |source_text: &str, filepath: &str, parser: &str| {
+            format_stdin(
+                source_text,
+                Path::new(filepath),
+                Some(parser),
+                json!({}),
+                external_formatter(),
+            )
+        };
+
+        // The parser takes precedence over the extension
+        assert_eq!(
+            format("const a:number   =   1", "file.txt", "typescript"),
+            Ok("const a: number = 1;\n".to_string())
+        );
+        assert_eq!(format("<a   />", "file.ts", "babel"), Ok("<a />;\n".to_string()));
+        assert_eq!(format(r#"{"a":1}"#, "file.txt", "json"), Ok("{ \"a\": 1 }\n".to_string()));
+        assert_eq!(format("a   =   1", "file.txt", "toml"), Ok("a = 1\n".to_string()));
+        // Unknown parsers are not replaced by the extension
+        assert_eq!(
+            format("const a   =   1", "file.ts", "unknown"),
+            Err("Unknown parser: unknown".to_string())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_format_stdin_in_async_context() {
+        // Called from the napi async context, `block_in_place()` must not panic
+        let result = format_stdin(
+            "const a   =   1",
+            Path::new("file.ts"),
+            None,
+            json!({}),
+            external_formatter(),
+        );
+        assert_eq!(result, Ok("const a = 1;\n".to_string()));
+    }
+}
@@ -0,0 +1,281 @@
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    Comment,
+    ast::{ArrayExpressionElement, Expression, ObjectPropertyKind, Program, Statement},
+};
+use oxc_diagnostics::OxcDiagnostic;
+use oxc_formatter::{FormatOptions, Formatter, Semicolons, get_parse_options};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType, Span};
+
+/// Format JSON, JSONC or JSON5 with `oxc_formatter`, without the external formatter.
+///
+/// Like Prettier's JSON parsers, the value is formatted as a JS expression.
+/// To prevent `{` from being parsed as a block, the value is wrapped in parentheses,
+/// which are removed from the formatted code along with the semicolon.
+///
+/// With `sort_keys`, the keys of objects are sorted lexicographically before formatting,
+/// see [`write_with_sorted_keys`].
+///
+/// `format_options` are expected to be built for JSON files, see `ConfigResolver`.
+pub fn format_json(
+    allocator: &Allocator,
+    source_text: &str,
+    mut format_options: FormatOptions,
+    sort_keys: bool,
+) -> Result<String, OxcDiagnostic> {
+    // Nothing to format, e.g. an empty `.json` file
+    if source_text.trim().is_empty() {
+        return Ok(String::new());
+    }
+
+    let source_type = SourceType::mjs();
+    let value_span = Parser::new(allocator, source_text, source_type)
+        .with_options(get_parse_options())
+        .parse_expression()
+        .map_err(|errors| errors.into_iter().next().unwrap())?
+        .span();
+
+    let (start, end) = (value_span.start as usize, value_span.end as usize);
+    let wrapped_text = allocator.alloc_str(&format!(
+        "{}({}){}",
+        &source_text[..start],
+        &source_text[start..end],
+        &source_text[end..]
+    ));
+
+    let mut ret =
+        Parser::new(allocator, wrapped_text, source_type).with_options(get_parse_options()).parse();
+    if !ret.errors.is_empty() {
+        // Return the first error for simplicity
+        return Err(ret.errors.into_iter().next().unwrap());
+    }
+    if json_value_spans(&ret.program).is_none() {
+        return Err(OxcDiagnostic::error("Expected a single JSON value"));
+    }
+
+    // A string value may be parsed as a directive, but it has no keys to sort anyway
+    if sort_keys && let Some(value) = json_value(&ret.program) {
+        let value_span = value.span();
+        let mut sorted_text = String::with_capacity(wrapped_text.len());
+        sorted_text.push_str(&wrapped_text[..value_span.start as usize]);
+        write_with_sorted_keys(value, wrapped_text, &ret.program.comments, &mut sorted_text);
+        sorted_text.push_str(&wrapped_text[value_span.end as usize..]);
+        let sorted_text = allocator.alloc_str(&sorted_text);
+        ret = Parser::new(allocator, sorted_text, source_type)
+            .with_options(get_parse_options())
+            .parse();
+    }
+
+    // Always print the semicolon, otherwise it is printed before the parentheses
+    format_options.semicolons = Semicolons::Always;
+    let code = Formatter::new(allocator, format_options)
+        .format(&ret.program)
+        .print()
+        .map_err(|err| OxcDiagnostic::error(format!("Failed to print formatted code\n{err}")))?
+        .into_code();
+
+    // Remove the parentheses and the semicolon added around the value
+    let ret = Parser::new(allocator, &code, source_type).with_options(get_parse_options()).parse();
+    let Some((statement_span, value_span)) = json_value_spans(&ret.program) else {
+        return Err(OxcDiagnostic::error("Failed to unwrap formatted JSON value"));
+    };
+
+    let mut unwrapped = String::with_capacity(code.len());
+    unwrapped.push_str(&code[..statement_span.start as usize]);
+    unwrapped.push_str(value_span.source_text(&code));
+    unwrapped.push_str(&code[statement_span.end as usize..]);
+    Ok(unwrapped)
+}
+
+/// Returns the value wrapped in parentheses, if the program is a single JSON expression.
+fn json_value<'a, 'b>(program: &'b Program<'a>) -> Option<&'b Expression<'a>> {
+    match program.body.as_slice() {
+        [Statement::ExpressionStatement(statement)] if program.directives.is_empty() => {
+            Some(statement.expression.without_parentheses())
+        }
+        _ => None,
+    }
+}
+
+/// Writes the source text of `value` with the keys of each object sorted lexicographically.
+/// Values are sorted recursively, while the order of array elements is kept.
+///
+/// Objects containing comments keep their order,
+/// since the comments cannot be reliably moved along with their keys.
+fn write_with_sorted_keys(
+    value: &Expression<'_>,
+    source_text: &str,
+    comments: &[Comment],
+    out: &mut String,
+) {
+    let span = value.span();
+    let has_comments = || {
+        comments
+            .iter()
+            .any(|comment| span.start <= comment.span.start && comment.span.end <= span.end)
+    };
+    match value {
+        Expression::ObjectExpression(object) => {
+            let properties = object
+                .properties
+                .iter()
+                .map(|property| match property {
+                    ObjectPropertyKind::ObjectProperty(property) => Some(property),
+                    ObjectPropertyKind::SpreadProperty(_) => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(mut properties) = properties.filter(|_| !has_comments()) else {
+                let children = object.properties.iter().filter_map(|property| match property {
+                    ObjectPropertyKind::ObjectProperty(property) => Some(&property.value),
+                    ObjectPropertyKind::SpreadProperty(_) => None,
+                });
+                write_with_children(span, children, source_text, comments, out);
+                return;
+            };
+            let Some(first) = properties.first() else {
+                out.push_str(span.source_text(source_text));
+                return;
+            };
+
+            // Keep the whitespace after `{`, it decides whether the object is expanded
+            out.push_str(&source_text[span.start as usize..first.span.start as usize]);
+            // Stable sort, so duplicate keys keep their order
+            properties.sort_by_cached_key(|property| {
+                property.key.static_name().map_or_else(
+                    || property.key.span().source_text(source_text).to_string(),
+                    Into::into,
+                )
+            });
+            for (i, property) in properties.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(
+                    &source_text
+                        [property.span.start as usize..property.value.span().start as usize],
+                );
+                write_with_sorted_keys(&property.value, source_text, comments, out);
+            }
+            out.push('}');
+        }
+        Expression::ArrayExpression(array) => {
+            let children = array.elements.iter().filter_map(ArrayExpressionElement::as_expression);
+            write_with_children(span, children, source_text, comments, out);
+        }
+        _ => out.push_str(span.source_text(source_text)),
+    }
+}
+
+/// Writes the source text of `span` as is, except for `children` written by [`write_with_sorted_keys`].
+fn write_with_children<'b, 'a: 'b>(
+    span: Span,
+    children: impl Iterator<Item = &'b Expression<'a>>,
+    source_text: &str,
+    comments: &[Comment],
+    out: &mut String,
+) {
+    let mut last_end = span.start;
+    for child in children {
+        out.push_str(&source_text[last_end as usize..child.span().start as usize]);
+        write_with_sorted_keys(child, source_text, comments, out);
+        last_end = child.span().end;
+    }
+    out.push_str(&source_text[last_end as usize..span.end as usize]);
+}
+
+/// Returns the spans of the only statement and its value, if the program is a single JSON value.
+/// A string value without parentheses is parsed as a directive.
+fn json_value_spans(program: &Program<'_>) -> Option<(Span, Span)> {
+    match (program.directives.as_slice(), program.body.as_slice()) {
+        ([directive], []) => Some((directive.span, directive.expression.span)),
+        ([], [Statement::ExpressionStatement(statement)]) => {
+            Some((statement.span, statement.expression.span()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxc_allocator::Allocator;
+    use oxc_formatter::{FormatOptions, QuoteProperties, TrailingCommas};
+
+    use super::format_json;
+
+    fn format_options(trailing_commas: TrailingCommas) -> FormatOptions {
+        FormatOptions {
+            quote_properties: QuoteProperties::Preserve,
+            preserve_string_literals: true,
+            trailing_commas,
+            ..FormatOptions::default()
+        }
+    }
+
+    fn format(source_text: &str, trailing_commas: TrailingCommas) -> String {
+        format_json(&Allocator::default(), source_text, format_options(trailing_commas), false)
+            .unwrap()
+    }
+
+    fn format_sorted(source_text: &str) -> String {
+        format_json(&Allocator::default(), source_text, format_options(TrailingCommas::None), true)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_format_json() {
+        assert_eq!(
+            format(r#"{"a":1,"b":[1,2]}"#, TrailingCommas::None),
+            "{ \"a\": 1, \"b\": [1, 2] }\n"
+        );
+        assert_eq!(format("[ 1 ]\n", TrailingCommas::None), "[1]\n");
+        assert_eq!(format(" \"a\" ", TrailingCommas::None), "\"a\"\n");
+        assert_eq!(format("", TrailingCommas::None), "");
+        // Quotes and escapes are kept as is
+        assert_eq!(
+            format(r#"{"a":"say \"hi\"","b":"\/"}"#, TrailingCommas::None),
+            r#"{ "a": "say \"hi\"", "b": "\/" }"#.to_string() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_format_jsonc() {
+        let source_text = "// comment\n{\n\"a\": 1, // trailing\n\"b\": 2,\n}\n";
+        assert_eq!(
+            format(source_text, TrailingCommas::None),
+            "// comment\n{\n  \"a\": 1, // trailing\n  \"b\": 2\n}\n"
+        );
+        assert_eq!(
+            format(source_text, TrailingCommas::All),
+            "// comment\n{\n  \"a\": 1, // trailing\n  \"b\": 2,\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_json_sorted_keys() {
+        // Nested objects are sorted, arrays and strings keep their order
+        assert_eq!(
+            format_sorted(r#"{"b":{"d":1,"c":2},"a":["z","y",{"f":1,"e":2}]}"#),
+            r#"{ "a": ["z", "y", { "e": 2, "f": 1 }], "b": { "c": 2, "d": 1 } }"#.to_string()
+                + "\n"
+        );
+        // Expanded objects stay expanded
+        assert_eq!(format_sorted("{\n\"b\": 1,\n\"a\": 2\n}\n"), "{\n  \"a\": 2,\n  \"b\": 1\n}\n");
+        // Duplicate keys keep their order
+        assert_eq!(format_sorted(r#"{"b":1,"a":1,"b":2}"#), "{ \"a\": 1, \"b\": 1, \"b\": 2 }\n");
+        // Objects with comments are not sorted, but their values are
+        assert_eq!(
+            format_sorted("{\n\"b\": 1, // b\n\"a\": {\"d\": 1, \"c\": 2}\n}\n"),
+            "{\n  \"b\": 1, // b\n  \"a\": { \"c\": 2, \"d\": 1 }\n}\n"
+        );
+        assert_eq!(format_sorted("[]"), "[]\n");
+        assert_eq!(format_sorted("\"a\""), "\"a\"\n");
+    }
+
+    #[test]
+    fn test_format_invalid_json() {
+        let allocator = Allocator::default();
+        assert!(format_json(&allocator, "{", FormatOptions::default(), false).is_err());
+        assert!(format_json(&allocator, "{}\n{}", FormatOptions::default(), false).is_err());
+    }
+}
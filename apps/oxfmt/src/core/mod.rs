@@ -1,7 +1,9 @@
 mod config;
 mod format;
+mod json;
 mod support;
 pub mod utils;
+mod walk;
 
 #[cfg(feature = "napi")]
 mod external_formatter;
@@ -9,10 +11,20 @@ mod external_formatter;
 pub use config::{
     ConfigResolver, ResolvedOptions, resolve_editorconfig_path, resolve_oxfmtrc_path,
 };
-pub use format::{FormatResult, SourceFormatter};
+pub use format::{FormatResult, SourceFormatter, format_paths, format_stdin};
+pub use json::format_json;
 pub use support::FormatFileStrategy;
+pub use walk::collect_formattable;
+
+#[cfg(feature = "napi")]
+pub use config::{FinalNewline, JSON_CONFIG_FILE_NAMES, dedup_ignore_patterns};
+#[cfg(feature = "napi")]
+pub use format::{final_newline_count, has_significant_trailing_newlines};
+#[cfg(feature = "napi")]
+pub use support::BUILTIN_EXTERNAL_PARSER_NAMES;
 
 #[cfg(feature = "napi")]
 pub use external_formatter::{
-    ExternalFormatter, JsFormatEmbeddedCb, JsFormatFileCb, JsInitExternalFormatterCb,
+    DEFAULT_CALL_RETRIES, ExternalFormatter, JsFormatEmbeddedCb, JsFormatFileCb,
+    JsInitExternalFormatterCb, SharedOptions, call_with_retries,
 };
@@ -2,27 +2,32 @@ use std::path::{Path, PathBuf};
 
 use phf::phf_set;
 
-use oxc_formatter::get_supported_source_type;
+use oxc_formatter::{enable_jsx_source_type, get_supported_source_type};
 use oxc_span::SourceType;
 
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub enum FormatFileStrategy {
     OxcFormatter {
         path: PathBuf,
+        /// JSX is already enabled for JS files, see [`FormatFileStrategy::oxc_formatter`].
         source_type: SourceType,
     },
     /// TOML files formatted by taplo (Pure Rust).
     OxfmtToml {
         path: PathBuf,
     },
+    /// JSON, JSONC and JSON5 files formatted by oxc_formatter, without the external formatter.
+    OxcFormatterJson {
+        path: PathBuf,
+        parser_name: &'static str,
+    },
     ExternalFormatter {
         path: PathBuf,
-        #[cfg_attr(not(feature = "napi"), expect(dead_code))]
         parser_name: &'static str,
     },
     /// `package.json` is special: sorted by `sort-package-json` then formatted by external formatter.
     ExternalFormatterPackageJson {
         path: PathBuf,
-        #[cfg_attr(not(feature = "napi"), expect(dead_code))]
         parser_name: &'static str,
     },
 }
@@ -34,7 +39,7 @@ impl TryFrom<PathBuf> for FormatFileStrategy {
         // Check JS/TS files first
         // TODO: This logic should(can) move to this file, after LSP support is also moved here.
         if let Some(source_type) = get_supported_source_type(&path) {
-            return Ok(Self::OxcFormatter { path, source_type });
+            return Ok(Self::oxc_formatter(path, source_type));
         }
 
         // Extract file_name and extension once for all subsequent checks
@@ -60,6 +65,10 @@ impl TryFrom<PathBuf> for FormatFileStrategy {
 
         let extension = path.extension().and_then(|ext| ext.to_str());
         if let Some(parser_name) = get_external_parser_name(file_name, extension) {
+            // Except for `json-stringify`, JSON-family files do not need the external formatter
+            if matches!(parser_name, "json" | "jsonc" | "json5") {
+                return Ok(Self::OxcFormatterJson { path, parser_name });
+            }
             return Ok(Self::ExternalFormatter { path, parser_name });
         }
 
@@ -68,15 +77,48 @@ impl TryFrom<PathBuf> for FormatFileStrategy {
 }
 
 impl FormatFileStrategy {
+    /// Create the strategy of a JS/TS file.
+    /// JSX is enabled once here, instead of before every parse of the file.
+    pub fn oxc_formatter(path: PathBuf, source_type: SourceType) -> Self {
+        Self::OxcFormatter { path, source_type: enable_jsx_source_type(source_type) }
+    }
+
+    /// Create the strategy for a parser name regardless of the extension of `path`, like Prettier's `--parser`,
+    /// e.g. for a `.txt` file containing TypeScript.
+    /// Parser names are the same as Prettier's, and `toml`.
+    ///
+    /// # Errors
+    /// Returns error if the parser name is unknown.
+    pub fn from_parser_name(path: PathBuf, parser_name: &str) -> Result<Self, String> {
+        match parser_name {
+            "babel" | "acorn" | "espree" | "meriyah" => {
+                Ok(Self::oxc_formatter(path, SourceType::mjs()))
+            }
+            "typescript" | "babel-ts" => Ok(Self::oxc_formatter(path, SourceType::ts())),
+            "toml" => Ok(Self::OxfmtToml { path }),
+            _ => match BUILTIN_EXTERNAL_PARSER_NAMES.get_key(parser_name) {
+                Some(&parser_name @ ("json" | "jsonc" | "json5")) => {
+                    Ok(Self::OxcFormatterJson { path, parser_name })
+                }
+                Some(&parser_name) => Ok(Self::ExternalFormatter { path, parser_name }),
+                None => Err(format!("Unknown parser: {parser_name}")),
+            },
+        }
+    }
+
     #[cfg(not(feature = "napi"))]
     pub fn can_format_without_external(&self) -> bool {
-        matches!(self, Self::OxcFormatter { .. } | Self::OxfmtToml { .. })
+        matches!(
+            self,
+            Self::OxcFormatter { .. } | Self::OxfmtToml { .. } | Self::OxcFormatterJson { .. }
+        )
     }
 
     pub fn path(&self) -> &Path {
         match self {
             Self::OxcFormatter { path, .. }
             | Self::OxfmtToml { path }
+            | Self::OxcFormatterJson { path, .. }
             | Self::ExternalFormatter { path, .. }
             | Self::ExternalFormatterPackageJson { path, .. } => path,
         }
@@ -127,6 +169,27 @@ static TOML_FILENAMES: phf::Set<&'static str> = phf_set! {
 
 // ---
 
+/// Parser names returned by [`get_external_parser_name`] and used for `package.json`.
+/// These are all built into Prettier, so they are available without any plugins.
+pub static BUILTIN_EXTERNAL_PARSER_NAMES: phf::Set<&'static str> = phf_set! {
+    "json",
+    "json-stringify",
+    "jsonc",
+    "json5",
+    "yaml",
+    "markdown",
+    "mdx",
+    "angular",
+    "html",
+    "vue",
+    "mjml",
+    "css",
+    "less",
+    "scss",
+    "graphql",
+    "glimmer",
+};
+
 /// Returns parser name for external formatter, if supported.
 /// See also `prettier --support-info | jq '.languages[]'`
 fn get_external_parser_name(file_name: &str, extension: Option<&str>) -> Option<&'static str> {
@@ -420,9 +483,36 @@ mod tests {
         for (file_name, expected) in test_cases {
             let result = get_parser_name(file_name);
             assert_eq!(result, expected, "`{file_name}` should be parsed as {expected:?}");
+            if let Some(parser_name) = result {
+                assert!(
+                    BUILTIN_EXTERNAL_PARSER_NAMES.contains(parser_name),
+                    "`{parser_name}` should be listed in `BUILTIN_EXTERNAL_PARSER_NAMES`"
+                );
+            }
         }
     }
 
+    #[test]
+    fn test_from_parser_name() {
+        let from =
+            |parser_name| FormatFileStrategy::from_parser_name(PathBuf::from("a.txt"), parser_name);
+        assert!(matches!(
+            from("typescript"),
+            Ok(FormatFileStrategy::OxcFormatter { source_type, .. }) if source_type.is_typescript()
+        ));
+        assert!(matches!(
+            from("babel"),
+            Ok(FormatFileStrategy::OxcFormatter { source_type, .. }) if source_type.is_javascript()
+        ));
+        assert!(matches!(
+            from("json5"),
+            Ok(FormatFileStrategy::OxcFormatterJson { parser_name: "json5", .. })
+        ));
+        assert!(matches!(from("toml"), Ok(FormatFileStrategy::OxfmtToml { .. })));
+        assert!(matches!(from("css"), Ok(FormatFileStrategy::ExternalFormatter { .. })));
+        assert!(from("flow").is_err());
+    }
+
     #[test]
     fn test_package_json_is_special() {
         let source = FormatFileStrategy::try_from(PathBuf::from("package.json")).unwrap();
@@ -432,6 +522,52 @@ mod tests {
         assert!(matches!(source, FormatFileStrategy::ExternalFormatter { .. }));
     }
 
+    #[test]
+    fn test_typescript_declaration_files() {
+        // Declaration files are parsed as such, e.g. ambient declarations without bodies
+        for file_name in ["types.d.ts", "types.d.mts", "types.d.cts", "types.d.css.ts"] {
+            let Ok(FormatFileStrategy::OxcFormatter { source_type, .. }) =
+                FormatFileStrategy::try_from(PathBuf::from(file_name))
+            else {
+                panic!("`{file_name}` should be formatted by oxc_formatter");
+            };
+            assert!(source_type.is_typescript_definition(), "`{file_name}` is a declaration file");
+        }
+
+        let Ok(FormatFileStrategy::OxcFormatter { source_type, .. }) =
+            FormatFileStrategy::try_from(PathBuf::from("types.ts"))
+        else {
+            panic!("`types.ts` should be formatted by oxc_formatter");
+        };
+        assert!(!source_type.is_typescript_definition());
+    }
+
+    #[test]
+    fn test_module_kind_extensions() {
+        // `.mts` and `.mjs` are ES modules, `.cts` and `.cjs` are CommonJS scripts
+        for (file_name, is_typescript, is_module) in [
+            ("index.mts", true, true),
+            ("index.cts", true, false),
+            ("index.mjs", false, true),
+            ("index.cjs", false, false),
+        ] {
+            let Ok(FormatFileStrategy::OxcFormatter { source_type, .. }) =
+                FormatFileStrategy::try_from(PathBuf::from(file_name))
+            else {
+                panic!("`{file_name}` should be formatted by oxc_formatter");
+            };
+            assert_eq!(source_type.is_typescript(), is_typescript, "`{file_name}`");
+            assert_eq!(source_type.is_module(), is_module, "`{file_name}`");
+        }
+    }
+
+    #[test]
+    fn test_vue_is_external() {
+        // The whole SFC is formatted by the external formatter, including `<script>`
+        let source = FormatFileStrategy::try_from(PathBuf::from("App.vue")).unwrap();
+        assert!(matches!(source, FormatFileStrategy::ExternalFormatter { .. }));
+    }
+
     #[test]
     fn test_toml_files() {
         // Files that should be detected as TOML
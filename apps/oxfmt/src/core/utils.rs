@@ -14,6 +14,14 @@ pub fn read_to_string(path: &Path) -> io::Result<String> {
     Ok(unsafe { String::from_utf8_unchecked(bytes) })
 }
 
+/// Returns the file name of `path`, with invalid UTF-8 sequences replaced by `U+FFFD`,
+/// or an empty string if `path` has no file name.
+/// The name is only owned if the lossy conversion altered it.
+#[cfg(feature = "napi")]
+pub fn file_name_lossy(path: &Path) -> std::borrow::Cow<'_, str> {
+    path.file_name().map_or(std::borrow::Cow::Borrowed(""), |name| name.to_string_lossy())
+}
+
 pub fn print_and_flush(writer: &mut dyn Write, message: &str) {
     use std::io::{Error, ErrorKind};
     fn check_for_writer_error(error: Error) -> Result<(), Error> {
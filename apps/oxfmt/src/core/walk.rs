@@ -0,0 +1,119 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use ignore::gitignore::Gitignore;
+
+use super::FormatFileStrategy;
+
+/// Collects all formattable files under `root`, sorted by path.
+///
+/// Paths matched by `ignore` are skipped, ignored directories are not entered.
+/// VCS directories and `node_modules` are also skipped, the same as the CLI.
+/// Unlike the CLI, nested `.gitignore` files are not respected,
+/// `ignore` is expected to already contain every pattern to apply.
+pub fn collect_formattable(root: &Path, ignore: &Gitignore) -> Vec<PathBuf> {
+    let ignore = ignore.clone();
+    let walker = ignore::WalkBuilder::new(root)
+        .filter_entry(move |entry| {
+            let Some(file_type) = entry.file_type() else {
+                return false;
+            };
+            let is_dir = file_type.is_dir();
+
+            if is_dir
+                && matches!(
+                    entry.file_name().to_str(),
+                    Some(".git" | ".jj" | ".sl" | ".svn" | ".hg" | "node_modules")
+                )
+            {
+                return false;
+            }
+
+            !ignore.matched(entry.path(), is_dir).is_ignore()
+        })
+        // Same as the CLI, see `cli/walk.rs`
+        .follow_links(false)
+        .hidden(false)
+        .ignore(false)
+        .parents(false)
+        .git_global(false)
+        .git_ignore(false)
+        .git_exclude(false)
+        .require_git(false)
+        .build_parallel();
+
+    let paths = Mutex::new(vec![]);
+    walker.run(|| {
+        let paths = &paths;
+        Box::new(move |entry| {
+            let Ok(entry) = entry else {
+                return ignore::WalkState::Skip;
+            };
+
+            #[expect(clippy::filetype_is_file)]
+            if entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+                let Ok(strategy) = FormatFileStrategy::try_from(entry.into_path()) else {
+                    return ignore::WalkState::Continue;
+                };
+
+                #[cfg(not(feature = "napi"))]
+                if !strategy.can_format_without_external() {
+                    return ignore::WalkState::Continue;
+                }
+
+                paths
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(strategy.path().to_path_buf());
+            }
+
+            ignore::WalkState::Continue
+        })
+    });
+
+    let mut paths = paths.into_inner().unwrap_or_else(PoisonError::into_inner);
+    paths.sort_unstable();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use cow_utils::CowUtils;
+    use ignore::gitignore::Gitignore;
+
+    use super::collect_formattable;
+
+    #[test]
+    fn test_collect_formattable() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/fixtures/lsp/ignore-dir");
+        let relative = |paths: Vec<PathBuf>| {
+            paths
+                .iter()
+                .map(|path| {
+                    path.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .cow_replace('\\', "/")
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Without ignores, every formattable file is collected, `.gitignore` itself is not
+        assert_eq!(
+            relative(collect_formattable(&root, &Gitignore::empty())),
+            vec!["build/x/y/deep.ts", "src/build/nested.ts", "src/kept.ts"]
+        );
+
+        // Ignored directories are not entered, at any depth
+        let (ignore, err) = Gitignore::new(root.join(".gitignore"));
+        assert!(err.is_none());
+        assert_eq!(relative(collect_formattable(&root, &ignore)), vec!["src/kept.ts"]);
+
+        assert!(collect_formattable(Path::new("/non/existent"), &Gitignore::empty()).is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod cli;
 mod core;
+pub use core::{collect_formattable, format_paths, format_stdin};
 #[cfg(feature = "napi")]
 pub mod lsp;
 #[cfg(feature = "napi")]
@@ -0,0 +1,545 @@
+use std::{
+    fmt,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use napi::{
+    Status,
+    bindgen_prelude::{FnArgs, Promise, block_on},
+    threadsafe_function::ThreadsafeFunction,
+};
+use napi_derive::napi;
+use serde_json::{Value, json};
+
+use crate::core::{
+    JsFormatEmbeddedCb, JsFormatFileCb, JsInitExternalFormatterCb, SharedOptions, call_with_retries,
+};
+
+/// Type alias for the callback function signature.
+/// Takes a list of `{ options, parserName, fileName, code }` and returns the result of each file in the same order.
+pub type JsFormatFilesCb = ThreadsafeFunction<
+    // Input arguments
+    FnArgs<(Vec<Value>,)>, // (requests,)
+    // Return type (what JS function returns)
+    Promise<Vec<FormatFileResult>>,
+    // Arguments (repeated)
+    FnArgs<(Vec<Value>,)>,
+    // Error status
+    Status,
+    // CalleeHandled
+    false,
+>;
+
+/// Type alias for the callback function signature.
+/// Takes the path of a JS config file and returns its default export.
+pub type JsLoadConfigCb = ThreadsafeFunction<
+    // Input arguments
+    FnArgs<(String,)>, // (path,)
+    // Return type (what JS function returns)
+    Promise<Value>,
+    // Arguments (repeated)
+    FnArgs<(String,)>,
+    // Error status
+    Status,
+    // CalleeHandled
+    false,
+>;
+
+/// Result of a single file formatted by the JS `formatFiles` callback.
+/// Either `code` or `error` is set, or neither if no parser or plugin can handle the file.
+#[napi(object)]
+pub struct FormatFileResult {
+    /// The formatted code.
+    pub code: Option<String>,
+    /// The error message, if formatting failed.
+    pub error: Option<String>,
+}
+
+/// A single file to format with [`ExternalFormatterBridge::format_files`].
+pub struct FormatRequest<'a> {
+    pub options: &'a Value,
+    pub parser_name: &'a str,
+    pub file_name: &'a str,
+    pub code: &'a str,
+}
+
+/// Optional features of an [`ExternalFormatterBridge`], see [`ExternalFormatterBridge::capabilities`].
+///
+/// New bridge methods should come with a flag here,
+/// so bridges not implementing them are never called for them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeCapabilities {
+    /// Whether [`ExternalFormatterBridge::format_embedded`] is implemented.
+    pub supports_embedded: bool,
+    /// Whether [`ExternalFormatterBridge::format_files`] formats all files in a single round-trip.
+    pub supports_batch: bool,
+    /// Whether [`ExternalFormatterBridge::load_config`] is implemented.
+    pub supports_config_load: bool,
+}
+
+/// Why an [`ExternalFormatterBridge`] could not format a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeError {
+    /// The external formatter rejected the file, e.g. because of a syntax error.
+    /// The JS side did respond, so it is still healthy.
+    Rejected(String),
+    /// The JS side could not be called, timed out, or the request was cancelled.
+    Failed(String),
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(message) | Self::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Bridge between the language server and the external formatter (Prettier) running on the JS side.
+///
+/// The language server only depends on this trait,
+/// so it can run without the JS side, e.g. in tests.
+pub trait ExternalFormatterBridge: Send + Sync {
+    /// Initialize the external formatter.
+    /// Returns the languages supported by the loaded plugins.
+    ///
+    /// # Errors
+    /// Returns error if the JS side fails to initialize.
+    fn init(&self, num_threads: usize) -> Result<Vec<String>, String>;
+
+    /// The optional features this bridge supports, queried once after [`ExternalFormatterBridge::init`].
+    ///
+    /// The default implementation supports none of them.
+    fn capabilities(&self) -> BridgeCapabilities {
+        BridgeCapabilities::default()
+    }
+
+    /// Format a non-JS file.
+    /// Returns `Ok(None)` if no parser or plugin can handle the file.
+    /// Gives up if the external formatter does not respond within `timeout`,
+    /// or as soon as `is_cancelled` is set, e.g. by `$/cancelRequest`.
+    ///
+    /// # Errors
+    /// Returns [`BridgeError::Rejected`] if the external formatter fails to format the file,
+    /// and [`BridgeError::Failed`] if the JS side can not be called, times out, or is cancelled.
+    fn format_file(
+        &self,
+        options: &Value,
+        parser_name: &str,
+        file_name: &str,
+        code: &str,
+        timeout: Duration,
+        is_cancelled: &AtomicBool,
+    ) -> Result<Option<String>, BridgeError>;
+
+    /// Format code embedded in a tagged template literal, e.g. CSS in `` css`...` ``.
+    /// `tag_name` is the full tag text, e.g. `styled.div`.
+    /// `options` is shared, since it is the same for every embedded code in a file.
+    ///
+    /// The default implementation does not support any embedded language.
+    ///
+    /// # Errors
+    /// Returns error if the JS side fails to format the code or times out.
+    fn format_embedded(
+        &self,
+        _options: &Arc<Value>,
+        tag_name: &str,
+        _code: &str,
+        _timeout: Duration,
+    ) -> Result<String, String> {
+        Err(format!("embedded formatting is not supported for tag '{tag_name}'"))
+    }
+
+    /// Load a JS config file, e.g. `.oxfmtrc.js`, and return its default export.
+    ///
+    /// The default implementation does not support JS config files.
+    ///
+    /// # Errors
+    /// Returns error if the JS side fails to load the config file.
+    fn load_config(&self, path: &Path) -> Result<Value, String> {
+        Err(format!("JS config files are not supported: {}", path.display()))
+    }
+
+    /// Load the parsers of `languages` ahead of time, e.g. the languages of the loaded plugins,
+    /// so the first [`ExternalFormatterBridge::format_file`] for them does not wait for it.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Errors
+    /// Returns error if the JS side fails to load a parser.
+    fn warmup(&self, _languages: &[String]) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Check whether the JS side responds, e.g. to tell if it crashed or hangs.
+    ///
+    /// The default implementation always succeeds.
+    ///
+    /// # Errors
+    /// Returns error if the JS side does not respond.
+    fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Format multiple non-JS files at once.
+    /// Returns the result of each request in the same order.
+    ///
+    /// The default implementation calls [`ExternalFormatterBridge::format_file`] for each request,
+    /// implementations should override it if they can format all files in a single round-trip.
+    fn format_files(
+        &self,
+        requests: &[FormatRequest<'_>],
+        timeout: Duration,
+        is_cancelled: &AtomicBool,
+    ) -> Vec<Result<Option<String>, BridgeError>> {
+        requests
+            .iter()
+            .map(|request| {
+                self.format_file(
+                    request.options,
+                    request.parser_name,
+                    request.file_name,
+                    request.code,
+                    timeout,
+                    is_cancelled,
+                )
+            })
+            .collect()
+    }
+}
+
+/// [`ExternalFormatterBridge`] calling the JS callbacks passed to `runCli()`.
+pub struct NapiExternalFormatterBridge {
+    init_cb: JsInitExternalFormatterCb,
+    format_embedded_cb: JsFormatEmbeddedCb,
+    format_file_cb: JsFormatFileCb,
+    format_files_cb: JsFormatFilesCb,
+    load_config_cb: JsLoadConfigCb,
+    /// How many times a `formatFile` call failing to reach the JS side is retried.
+    call_retries: u32,
+}
+
+impl NapiExternalFormatterBridge {
+    pub fn new(
+        init_cb: JsInitExternalFormatterCb,
+        format_embedded_cb: JsFormatEmbeddedCb,
+        format_file_cb: JsFormatFileCb,
+        format_files_cb: JsFormatFilesCb,
+        load_config_cb: JsLoadConfigCb,
+        call_retries: u32,
+    ) -> Self {
+        Self {
+            init_cb,
+            format_embedded_cb,
+            format_file_cb,
+            format_files_cb,
+            load_config_cb,
+            call_retries,
+        }
+    }
+}
+
+// NOTE: The language server always runs inside the `napi`'s `async` context,
+// so `block_in_place()` is used to call `block_on` without nested async runtime access.
+// See also `crate::core::external_formatter`.
+impl ExternalFormatterBridge for NapiExternalFormatterBridge {
+    fn init(&self, num_threads: usize) -> Result<Vec<String>, String> {
+        tokio::task::block_in_place(|| {
+            block_on(async {
+                #[expect(clippy::cast_possible_truncation)]
+                let status = self.init_cb.call_async(FnArgs::from((num_threads as u32,))).await;
+                match status {
+                    Ok(promise) => match promise.await {
+                        Ok(languages) => Ok(languages),
+                        Err(err) => {
+                            Err(format!("JS initExternalFormatter promise rejected: {err}"))
+                        }
+                    },
+                    Err(err) => {
+                        Err(format!("Failed to call JS initExternalFormatter callback: {err}"))
+                    }
+                }
+            })
+        })
+    }
+
+    fn capabilities(&self) -> BridgeCapabilities {
+        BridgeCapabilities {
+            supports_embedded: true,
+            supports_batch: true,
+            supports_config_load: true,
+        }
+    }
+
+    fn load_config(&self, path: &Path) -> Result<Value, String> {
+        let path = path.to_string_lossy().to_string();
+        tokio::task::block_in_place(|| {
+            block_on(async {
+                let status = self.load_config_cb.call_async(FnArgs::from((path.clone(),))).await;
+                match status {
+                    Ok(promise) => match promise.await {
+                        Ok(config) => Ok(config),
+                        Err(err) => {
+                            Err(format!("JS loadConfig promise rejected for '{path}': {err}"))
+                        }
+                    },
+                    Err(err) => Err(format!("Failed to call JS loadConfig callback: {err}")),
+                }
+            })
+        })
+    }
+
+    fn warmup(&self, languages: &[String]) -> Result<(), String> {
+        // Formatting empty code is enough for Prettier to load the plugin of the parser
+        let is_cancelled = AtomicBool::new(false);
+        for language in languages {
+            self.format_file(&json!({}), language, "", "", WARMUP_TIMEOUT, &is_cancelled)
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        // An unknown tag is returned as is by the JS side, without loading Prettier
+        self.format_embedded(&Arc::new(json!({})), "", "", PING_TIMEOUT).map(|_| ())
+    }
+
+    fn format_embedded(
+        &self,
+        options: &Arc<Value>,
+        tag_name: &str,
+        code: &str,
+        timeout: Duration,
+    ) -> Result<String, String> {
+        let options = SharedOptions::from(Arc::clone(options));
+        tokio::task::block_in_place(|| {
+            block_on(async {
+                let format = async {
+                    let status = self
+                        .format_embedded_cb
+                        .call_async(FnArgs::from((
+                            options.clone(),
+                            tag_name.to_string(),
+                            code.to_string(),
+                        )))
+                        .await;
+                    match status {
+                        Ok(promise) => match promise.await {
+                            Ok(formatted_code) => Ok(formatted_code),
+                            Err(err) => Err(format!(
+                                "JS formatEmbeddedCode promise rejected for tag '{tag_name}': {err}"
+                            )),
+                        },
+                        Err(err) => Err(format!(
+                            "Failed to call JS formatEmbeddedCode callback for tag '{tag_name}': {err}"
+                        )),
+                    }
+                };
+
+                tokio::time::timeout(timeout, format).await.unwrap_or_else(|_| {
+                    Err(format!("external formatter timed out after {}ms", timeout.as_millis()))
+                })
+            })
+        })
+    }
+
+    fn format_file(
+        &self,
+        options: &Value,
+        parser_name: &str,
+        file_name: &str,
+        code: &str,
+        timeout: Duration,
+        is_cancelled: &AtomicBool,
+    ) -> Result<Option<String>, BridgeError> {
+        // Converted once, so retries do not clone the options again
+        let options = SharedOptions::from(Arc::new(options.clone()));
+        tokio::task::block_in_place(|| {
+            block_on(async {
+                let format = async {
+                    // Promise rejections are not retried, they are formatting errors
+                    let status = call_with_retries(self.call_retries, || {
+                        self.format_file_cb.call_async(FnArgs::from((
+                            options.clone(),
+                            parser_name.to_string(),
+                            file_name.to_string(),
+                            code.to_string(),
+                        )))
+                    })
+                    .await;
+                    match status {
+                        Ok(promise) => match promise.await {
+                            Ok(formatted_code) => Ok(formatted_code),
+                            Err(err) => Err(BridgeError::Rejected(format!(
+                                "JS formatFile promise rejected for file: '{file_name}', parser: '{parser_name}': {err}"
+                            ))),
+                        },
+                        Err(err) => Err(BridgeError::Failed(format!(
+                            "Failed to call JS formatFile callback for file: '{file_name}', parser: '{parser_name}': {err}"
+                        ))),
+                    }
+                };
+
+                // A hanging JS callback (e.g. a deadlocked plugin) must not freeze the language server
+                tokio::select! {
+                    result = tokio::time::timeout(timeout, format) => result.unwrap_or_else(|_| {
+                        Err(BridgeError::Failed(format!(
+                            "external formatter timed out after {}ms",
+                            timeout.as_millis()
+                        )))
+                    }),
+                    () = wait_for_cancellation(is_cancelled) => {
+                        Err(BridgeError::Failed("external formatter request was cancelled".to_string()))
+                    }
+                }
+            })
+        })
+    }
+
+    fn format_files(
+        &self,
+        requests: &[FormatRequest<'_>],
+        timeout: Duration,
+        is_cancelled: &AtomicBool,
+    ) -> Vec<Result<Option<String>, BridgeError>> {
+        if requests.is_empty() {
+            return vec![];
+        }
+
+        let js_requests = requests
+            .iter()
+            .map(|request| {
+                json!({
+                    "options": request.options,
+                    "parserName": request.parser_name,
+                    "fileName": request.file_name,
+                    "code": request.code,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let results = tokio::task::block_in_place(|| {
+            block_on(async {
+                let format = async {
+                    let status =
+                        self.format_files_cb.call_async(FnArgs::from((js_requests,))).await;
+                    match status {
+                        Ok(promise) => promise.await.map_err(|err| {
+                            BridgeError::Rejected(format!("JS formatFiles promise rejected: {err}"))
+                        }),
+                        Err(err) => Err(BridgeError::Failed(format!(
+                            "Failed to call JS formatFiles callback: {err}"
+                        ))),
+                    }
+                };
+
+                tokio::select! {
+                    result = tokio::time::timeout(timeout, format) => result.unwrap_or_else(|_| {
+                        Err(BridgeError::Failed(format!(
+                            "external formatter timed out after {}ms",
+                            timeout.as_millis()
+                        )))
+                    }),
+                    () = wait_for_cancellation(is_cancelled) => {
+                        Err(BridgeError::Failed("external formatter request was cancelled".to_string()))
+                    }
+                }
+            })
+        });
+
+        match results {
+            Ok(results) if results.len() == requests.len() => results
+                .into_iter()
+                .zip(requests)
+                .map(|(result, request)| match result {
+                    FormatFileResult { code: Some(code), .. } => Ok(Some(code)),
+                    FormatFileResult { code: None, error: None } => Ok(None),
+                    FormatFileResult { error: Some(error), .. } => {
+                        Err(BridgeError::Rejected(format!(
+                            "JS formatFiles failed for file: '{}', parser: '{}': {}",
+                            request.file_name, request.parser_name, error
+                        )))
+                    }
+                })
+                .collect(),
+            Ok(results) => {
+                let err = BridgeError::Failed(format!(
+                    "JS formatFiles returned {} results for {} files",
+                    results.len(),
+                    requests.len()
+                ));
+                requests.iter().map(|_| Err(err.clone())).collect()
+            }
+            // The whole batch failed
+            Err(err) => requests.iter().map(|_| Err(err.clone())).collect(),
+        }
+    }
+}
+
+/// How long [`ExternalFormatterBridge::ping`] waits for the JS side.
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long [`ExternalFormatterBridge::warmup`] waits for each parser to load.
+const WARMUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running JS callback checks whether its request was cancelled.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Resolves once `is_cancelled` is set.
+async fn wait_for_cancellation(is_cancelled: &AtomicBool) {
+    while !is_cancelled.load(Ordering::Relaxed) {
+        tokio::time::sleep(CANCELLATION_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicBool, time::Duration};
+
+    use cow_utils::CowUtils;
+    use serde_json::json;
+
+    use super::{BridgeError, ExternalFormatterBridge, FormatRequest};
+    use crate::lsp::tester::TestBridge;
+
+    #[test]
+    fn test_default_format_files() {
+        let options = json!({});
+        let requests = [
+            FormatRequest { options: &options, parser_name: "css", file_name: "a.css", code: "a" },
+            FormatRequest {
+                options: &options,
+                parser_name: "unknown",
+                file_name: "b.txt",
+                code: "b",
+            },
+            FormatRequest {
+                options: &options,
+                parser_name: "json",
+                file_name: "c.json",
+                code: "c",
+            },
+        ];
+
+        let bridge = TestBridge::default()
+            .with_result("unknown", "b.txt", Err("unsupported parser: unknown"))
+            .with_format(|code| Ok(Some(code.cow_to_uppercase().into_owned())));
+        let results =
+            bridge.format_files(&requests, Duration::from_secs(1), &AtomicBool::new(false));
+        assert_eq!(
+            results,
+            vec![
+                Ok(Some("A".to_string())),
+                Err(BridgeError::Rejected("unsupported parser: unknown".to_string())),
+                Ok(Some("C".to_string()))
+            ]
+        );
+        // Each file is formatted by its own `format_file` call
+        assert_eq!(bridge.take_parser_names(), vec!["css", "unknown", "json"]);
+    }
+}
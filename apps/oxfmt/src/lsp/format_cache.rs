@@ -0,0 +1,257 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use indexmap::IndexMap;
+use tower_lsp_server::ls_types::TextEdit;
+
+use crate::core::{FormatFileStrategy, ResolvedOptions};
+
+/// Bounded LRU cache of the text edits returned by `run_format`,
+/// keyed by the file path and the hash of the source text,
+/// along with anything else affecting the result, e.g. the client formatting options.
+///
+/// An empty `Vec` means the file was already formatted.
+pub struct FormatCache {
+    capacity: usize,
+    /// Ordered from the least to the most recently used entry.
+    entries: Mutex<IndexMap<(PathBuf, u64), Vec<TextEdit>>>,
+}
+
+impl FormatCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::new(IndexMap::with_capacity(capacity)) }
+    }
+
+    pub fn get(&self, path: &Path, source: &impl Hash) -> Option<Vec<TextEdit>> {
+        let key = (path.to_path_buf(), hash_source(source));
+        let mut entries = self.entries.lock().ok()?;
+        let index = entries.get_index_of(&key)?;
+
+        let last = entries.len() - 1;
+        entries.move_index(index, last);
+        entries.get_index(last).map(|(_, edits)| edits.clone())
+    }
+
+    pub fn insert(&self, path: &Path, source: &impl Hash, edits: Vec<TextEdit>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = (path.to_path_buf(), hash_source(source));
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        entries.shift_remove(&key);
+        if entries.len() >= self.capacity {
+            entries.shift_remove_index(0);
+        }
+        entries.insert(key, edits);
+    }
+}
+
+/// Bounded LRU cache of the source text of files read from disk,
+/// keyed by the file path, along with the modification time of the file when it was read.
+///
+/// An entry is only returned while the modification time is unchanged,
+/// so unchanged files are not read again.
+pub struct SourceCache {
+    capacity: usize,
+    /// Ordered from the least to the most recently used entry.
+    entries: Mutex<IndexMap<PathBuf, (SystemTime, Arc<str>)>>,
+}
+
+impl SourceCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::new(IndexMap::with_capacity(capacity)) }
+    }
+
+    pub fn get(&self, path: &Path, modified: SystemTime) -> Option<Arc<str>> {
+        let mut entries = self.entries.lock().ok()?;
+        let index = entries.get_index_of(path)?;
+        if entries[index].0 != modified {
+            entries.shift_remove_index(index);
+            return None;
+        }
+
+        let last = entries.len() - 1;
+        entries.move_index(index, last);
+        entries.get_index(last).map(|(_, (_, source))| Arc::clone(source))
+    }
+
+    pub fn insert(&self, path: &Path, modified: SystemTime, source: Arc<str>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        entries.shift_remove(path);
+        if entries.len() >= self.capacity {
+            entries.shift_remove_index(0);
+        }
+        entries.insert(path.to_path_buf(), (modified, source));
+    }
+}
+
+/// Bounded LRU cache of the options resolved by `ConfigResolver` for each file,
+/// keyed by the strategy, i.e. the file path and how it is formatted,
+/// along with the indentation sent by the client.
+///
+/// The path is always part of the key, since `overrides` and `.editorconfig` globs
+/// may match a single file, even files in the same directory with the same extension can differ.
+pub struct ResolveCache {
+    capacity: usize,
+    /// Ordered from the least to the most recently used entry.
+    entries: Mutex<IndexMap<ResolveKey, ResolvedOptions>>,
+}
+
+/// The strategy and the `(insert_spaces, tab_size)` sent by the client.
+type ResolveKey = (FormatFileStrategy, Option<(bool, u8)>);
+
+impl ResolveCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::new(IndexMap::with_capacity(capacity)) }
+    }
+
+    /// Returns the cached options, or resolves them with `resolve` and caches them.
+    pub fn get_or_resolve(
+        &self,
+        strategy: &FormatFileStrategy,
+        indent: Option<(bool, u8)>,
+        resolve: impl FnOnce() -> ResolvedOptions,
+    ) -> ResolvedOptions {
+        let key = (strategy.clone(), indent);
+        if let Ok(mut entries) = self.entries.lock()
+            && let Some(index) = entries.get_index_of(&key)
+        {
+            let last = entries.len() - 1;
+            entries.move_index(index, last);
+            return entries[last].clone();
+        }
+
+        // Resolved without holding the lock, so other files are not blocked
+        let options = resolve();
+        if self.capacity == 0 {
+            return options;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.shift_remove(&key);
+            if entries.len() >= self.capacity {
+                entries.shift_remove_index(0);
+            }
+            entries.insert(key, options.clone());
+        }
+        options
+    }
+}
+
+/// Hash of the content of a file, e.g. the source text.
+pub fn hash_source(source: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{Position, Range, TextEdit};
+
+    use super::{FormatCache, ResolveCache, SourceCache};
+    use crate::core::{ConfigResolver, FormatFileStrategy, ResolvedOptions};
+
+    fn edit(new_text: &str) -> Vec<TextEdit> {
+        vec![TextEdit::new(Range::new(Position::new(0, 0), Position::new(0, 1)), new_text.into())]
+    }
+
+    #[test]
+    fn test_get_and_insert() {
+        let cache = FormatCache::new(2);
+        let path = Path::new("/a.ts");
+
+        assert_eq!(cache.get(path, &"a"), None);
+        cache.insert(path, &"a", edit("b"));
+        assert_eq!(cache.get(path, &"a"), Some(edit("b")));
+        // Same path, different content
+        assert_eq!(cache.get(path, &"b"), None);
+        // Same content, different path
+        assert_eq!(cache.get(Path::new("/b.ts"), &"a"), None);
+
+        cache.insert(path, &"c", vec![]);
+        assert_eq!(cache.get(path, &"c"), Some(vec![]));
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let cache = FormatCache::new(2);
+        cache.insert(Path::new("/a.ts"), &"a", edit("a"));
+        cache.insert(Path::new("/b.ts"), &"b", edit("b"));
+
+        // `/a.ts` becomes the most recently used entry
+        assert!(cache.get(Path::new("/a.ts"), &"a").is_some());
+        cache.insert(Path::new("/c.ts"), &"c", edit("c"));
+
+        assert!(cache.get(Path::new("/a.ts"), &"a").is_some());
+        assert!(cache.get(Path::new("/b.ts"), &"b").is_none());
+        assert!(cache.get(Path::new("/c.ts"), &"c").is_some());
+    }
+
+    #[test]
+    fn test_source_cache_modified() {
+        let cache = SourceCache::new(2);
+        let path = Path::new("/a.ts");
+        let modified = SystemTime::UNIX_EPOCH;
+
+        assert_eq!(cache.get(path, modified), None);
+        cache.insert(path, modified, "a".into());
+        assert_eq!(cache.get(path, modified).as_deref(), Some("a"));
+
+        // The file was modified since it was read
+        assert_eq!(cache.get(path, modified + Duration::from_secs(1)), None);
+        // The outdated entry is dropped
+        assert_eq!(cache.get(path, modified), None);
+    }
+
+    #[test]
+    fn test_resolve_cache() {
+        let mut config_resolver = ConfigResolver::from_value(json!({ "semi": false }));
+        config_resolver.build_and_validate().unwrap();
+        let cache = ResolveCache::new(2);
+        let resolved = Cell::new(0);
+        let resolve = |strategy: &FormatFileStrategy, indent| {
+            cache.get_or_resolve(strategy, indent, || {
+                resolved.set(resolved.get() + 1);
+                config_resolver.resolve(strategy)
+            })
+        };
+        let a = FormatFileStrategy::try_from(PathBuf::from("/a.ts")).unwrap();
+        let b = FormatFileStrategy::try_from(PathBuf::from("/b.ts")).unwrap();
+
+        let ResolvedOptions::OxcFormatter { format_options, .. } = resolve(&a, None) else {
+            panic!("Expected options for oxc_formatter");
+        };
+        assert!(!format_options.semicolons.is_always());
+        resolve(&a, None);
+        assert_eq!(resolved.get(), 1);
+
+        // Each file and indentation is resolved separately
+        resolve(&b, None);
+        resolve(&a, Some((true, 4)));
+        assert_eq!(resolved.get(), 3);
+
+        // `(a, None)` was the least recently used entry
+        resolve(&a, None);
+        assert_eq!(resolved.get(), 4);
+    }
+}
@@ -1,17 +1,36 @@
+use std::sync::Arc;
+
 use oxc_language_server::run_server;
 
+mod external_formatter_bridge;
+mod format_cache;
 mod options;
 mod server_formatter;
 #[cfg(test)]
 mod tester;
-const FORMAT_CONFIG_FILES: &[&str; 2] = &[".oxfmtrc.json", ".oxfmtrc.jsonc"];
+/// Config files looked up in the workspace root, in order of priority.
+/// JS config files are loaded through the external formatter bridge.
+const FORMAT_CONFIG_FILES: &[&str; 5] =
+    &[".oxfmtrc.json", ".oxfmtrc.jsonc", ".oxfmtrc.js", ".oxfmtrc.cjs", ".oxfmtrc.mjs"];
+/// Prettier config files looked up in the workspace root if none of [`FORMAT_CONFIG_FILES`] is found,
+/// only with `fmt.readPrettierConfig`.
+const PRETTIER_CONFIG_FILES: &[&str; 2] = &[".prettierrc", ".prettierrc.json"];
+
+pub use external_formatter_bridge::{
+    BridgeCapabilities, BridgeError, ExternalFormatterBridge, FormatFileResult, FormatRequest,
+    JsFormatFilesCb, JsLoadConfigCb, NapiExternalFormatterBridge,
+};
+pub use server_formatter::{
+    FormatError, FormatOutcome, FormatSettings, ServerFormatter, ServerFormatterBuilder,
+    ServerFormatterConfig, SharedExternalFormatter,
+};
 
 /// Run the language server
-pub async fn run_lsp() {
+pub async fn run_lsp(external_bridge: Option<Arc<dyn ExternalFormatterBridge>>) {
     run_server(
         "oxfmt".to_string(),
         env!("CARGO_PKG_VERSION").to_string(),
-        vec![Box::new(server_formatter::ServerFormatterBuilder)],
+        vec![Box::new(ServerFormatterBuilder::new(external_bridge))],
     )
     .await;
 }
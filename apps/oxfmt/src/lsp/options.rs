@@ -5,6 +5,52 @@ use serde_json::Value;
 #[serde(rename_all = "camelCase")]
 pub struct FormatOptions {
     pub config_path: Option<String>,
+    /// Config files looked up in the workspace root, in order of priority,
+    /// e.g. `.config/oxfmt.json`. The default config files are used if empty.
+    pub config_file_names: Vec<String>,
+    /// Timeout in milliseconds for a single call to the external formatter.
+    pub external_timeout_ms: Option<u64>,
+    /// Files larger than this size in bytes are not formatted.
+    pub max_file_size_bytes: Option<u64>,
+    /// Format the formatted code again and warn if the output changes.
+    /// Only meant for debugging the formatter.
+    pub debug_verify_idempotent: bool,
+    /// Publish the parse errors blocking formatting as diagnostics.
+    /// Disabled by default, since the linter already reports them.
+    pub publish_parse_errors: bool,
+    /// Use the indentation of the file, if the config does not set it.
+    pub detect_indentation: bool,
+    /// Skip JS/TS files with a shebang naming one of these interpreters, e.g. `gjs`.
+    pub skip_shebang_interpreters: Vec<String>,
+    /// Read `.prettierrc` if no `.oxfmtrc` is found, e.g. for projects migrating from Prettier.
+    pub read_prettier_config: bool,
+    /// Only format when a config file is found, otherwise the formatter is disabled.
+    pub require_config: bool,
+    /// Only format the files matching these patterns, relative to the workspace root.
+    /// Files matching the ignore patterns are still ignored.
+    pub include_patterns: Vec<String>,
+    /// Sort and deduplicate the imports of JS/TS files when formatting.
+    /// The sort options of the config are used if set.
+    pub organize_imports: bool,
+    /// Line width used if neither the config nor `.editorconfig` sets it,
+    /// e.g. the ruler of the editor.
+    pub print_width: Option<u16>,
+    /// Maximum number of consecutive blank lines kept in JS/TS files, instead of collapsing them to one.
+    pub max_preserved_blank_lines: Option<u8>,
+    /// Format the top-level statements around the parse errors of JS/TS files,
+    /// instead of not formatting the file at all, e.g. while a statement is being typed.
+    pub format_valid_regions: bool,
+}
+
+impl FormatOptions {
+    /// Whether the options looking up the config file differ from `other`,
+    /// so the config file, its ignore patterns and the watched files have to be resolved again.
+    pub fn config_lookup_changed(&self, other: &Self) -> bool {
+        self.config_path != other.config_path
+            || self.config_file_names != other.config_file_names
+            || self.read_prettier_config != other.read_prettier_config
+            || self.require_config != other.require_config
+    }
 }
 
 impl<'de> Deserialize<'de> for FormatOptions {
@@ -29,6 +75,58 @@ impl TryFrom<Value> for FormatOptions {
             config_path: object
                 .get("fmt.configPath")
                 .and_then(|config_path| serde_json::from_value::<String>(config_path.clone()).ok()),
+            config_file_names: object
+                .get("fmt.configFileNames")
+                .and_then(|names| serde_json::from_value::<Vec<String>>(names.clone()).ok())
+                .unwrap_or_default(),
+            external_timeout_ms: object
+                .get("fmt.externalTimeoutMs")
+                .and_then(|timeout| serde_json::from_value::<u64>(timeout.clone()).ok()),
+            max_file_size_bytes: object
+                .get("fmt.maxFileSizeBytes")
+                .and_then(|size| serde_json::from_value::<u64>(size.clone()).ok()),
+            debug_verify_idempotent: object
+                .get("fmt.debugVerifyIdempotent")
+                .and_then(|verify| serde_json::from_value::<bool>(verify.clone()).ok())
+                .unwrap_or(false),
+            publish_parse_errors: object
+                .get("fmt.publishParseErrors")
+                .and_then(|publish| serde_json::from_value::<bool>(publish.clone()).ok())
+                .unwrap_or(false),
+            detect_indentation: object
+                .get("fmt.detectIndentation")
+                .and_then(|detect| serde_json::from_value::<bool>(detect.clone()).ok())
+                .unwrap_or(false),
+            skip_shebang_interpreters: object
+                .get("fmt.skipShebangInterpreters")
+                .and_then(|names| serde_json::from_value::<Vec<String>>(names.clone()).ok())
+                .unwrap_or_default(),
+            read_prettier_config: object
+                .get("fmt.readPrettierConfig")
+                .and_then(|read| serde_json::from_value::<bool>(read.clone()).ok())
+                .unwrap_or(false),
+            require_config: object
+                .get("fmt.requireConfig")
+                .and_then(|require| serde_json::from_value::<bool>(require.clone()).ok())
+                .unwrap_or(false),
+            include_patterns: object
+                .get("fmt.includePatterns")
+                .and_then(|patterns| serde_json::from_value::<Vec<String>>(patterns.clone()).ok())
+                .unwrap_or_default(),
+            organize_imports: object
+                .get("fmt.organizeImports")
+                .and_then(|organize| serde_json::from_value::<bool>(organize.clone()).ok())
+                .unwrap_or(false),
+            print_width: object
+                .get("fmt.printWidth")
+                .and_then(|width| serde_json::from_value::<u16>(width.clone()).ok()),
+            max_preserved_blank_lines: object
+                .get("fmt.maxPreservedBlankLines")
+                .and_then(|lines| serde_json::from_value::<u8>(lines.clone()).ok()),
+            format_valid_regions: object
+                .get("fmt.formatValidRegions")
+                .and_then(|format| serde_json::from_value::<bool>(format.clone()).ok())
+                .unwrap_or(false),
         })
     }
 }
@@ -42,11 +140,39 @@ mod test {
     #[test]
     fn test_valid_options_json() {
         let json = json!({
-            "fmt.configPath": "./.oxfmtrc.json"
+            "fmt.configPath": "./.oxfmtrc.json",
+            "fmt.configFileNames": [".config/oxfmt.json"],
+            "fmt.externalTimeoutMs": 1000,
+            "fmt.maxFileSizeBytes": 1024,
+            "fmt.debugVerifyIdempotent": true,
+            "fmt.publishParseErrors": true,
+            "fmt.detectIndentation": true,
+            "fmt.skipShebangInterpreters": ["gjs"],
+            "fmt.readPrettierConfig": true,
+            "fmt.requireConfig": true,
+            "fmt.includePatterns": ["src/**"],
+            "fmt.organizeImports": true,
+            "fmt.printWidth": 120,
+            "fmt.maxPreservedBlankLines": 2,
+            "fmt.formatValidRegions": true
         });
 
         let options = FormatOptions::try_from(json).unwrap();
         assert_eq!(options.config_path.unwrap(), "./.oxfmtrc.json");
+        assert_eq!(options.config_file_names, vec![".config/oxfmt.json".to_string()]);
+        assert_eq!(options.external_timeout_ms, Some(1000));
+        assert_eq!(options.max_file_size_bytes, Some(1024));
+        assert!(options.debug_verify_idempotent);
+        assert!(options.publish_parse_errors);
+        assert!(options.detect_indentation);
+        assert_eq!(options.skip_shebang_interpreters, vec!["gjs".to_string()]);
+        assert!(options.read_prettier_config);
+        assert!(options.require_config);
+        assert_eq!(options.include_patterns, vec!["src/**".to_string()]);
+        assert!(options.organize_imports);
+        assert_eq!(options.print_width, Some(120));
+        assert_eq!(options.max_preserved_blank_lines, Some(2));
+        assert!(options.format_valid_regions);
     }
 
     #[test]
@@ -55,16 +181,58 @@ mod test {
 
         let options = FormatOptions::try_from(json).unwrap();
         assert!(options.config_path.is_none());
+        assert!(options.config_file_names.is_empty());
+        assert!(options.external_timeout_ms.is_none());
+        assert!(options.max_file_size_bytes.is_none());
+        assert!(!options.debug_verify_idempotent);
+        assert!(!options.publish_parse_errors);
+        assert!(!options.detect_indentation);
+        assert!(options.skip_shebang_interpreters.is_empty());
+        assert!(!options.read_prettier_config);
+        assert!(!options.require_config);
+        assert!(options.include_patterns.is_empty());
+        assert!(!options.organize_imports);
+        assert!(options.print_width.is_none());
+        assert!(options.max_preserved_blank_lines.is_none());
+        assert!(!options.format_valid_regions);
     }
 
     #[test]
     fn test_invalid_options_json() {
         let json = json!({
-            "fmt.configPath": true // should be a string
+            "fmt.configPath": true, // should be a string
+            "fmt.configFileNames": ".config/oxfmt.json", // should be an array
+            "fmt.externalTimeoutMs": "1000", // should be a number
+            "fmt.maxFileSizeBytes": -1, // should be a positive number
+            "fmt.debugVerifyIdempotent": "true", // should be a boolean
+            "fmt.publishParseErrors": 1, // should be a boolean
+            "fmt.detectIndentation": 1, // should be a boolean
+            "fmt.skipShebangInterpreters": "gjs", // should be an array
+            "fmt.readPrettierConfig": "true", // should be a boolean
+            "fmt.requireConfig": "true", // should be a boolean
+            "fmt.includePatterns": "src/**", // should be an array
+            "fmt.organizeImports": "true", // should be a boolean
+            "fmt.printWidth": -1, // should be a positive number
+            "fmt.maxPreservedBlankLines": 256, // should fit in a byte
+            "fmt.formatValidRegions": "true" // should be a boolean
         });
 
         let options = FormatOptions::try_from(json).unwrap();
         assert!(options.config_path.is_none());
+        assert!(options.config_file_names.is_empty());
+        assert!(options.external_timeout_ms.is_none());
+        assert!(options.max_file_size_bytes.is_none());
+        assert!(!options.debug_verify_idempotent);
+        assert!(!options.publish_parse_errors);
+        assert!(!options.detect_indentation);
+        assert!(options.skip_shebang_interpreters.is_empty());
+        assert!(!options.read_prettier_config);
+        assert!(!options.require_config);
+        assert!(options.include_patterns.is_empty());
+        assert!(!options.organize_imports);
+        assert!(options.print_width.is_none());
+        assert!(options.max_preserved_blank_lines.is_none());
+        assert!(!options.format_valid_regions);
     }
 
     #[test]
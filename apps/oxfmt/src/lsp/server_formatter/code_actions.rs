@@ -0,0 +1,378 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use oxc_language_server::Tool;
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Uri, WorkspaceEdit,
+};
+
+use super::{
+    FORMAT_SELECTION_CODE_ACTION_KIND, FormatStats, ORGANIZE_IMPORTS_CODE_ACTION_KIND,
+    ServerFormatter, diff::build_text_edits,
+};
+use crate::core::FormatFileStrategy;
+
+/// Data of the code actions returned without edits, to compute them in [`ServerFormatter::resolve_code_action`].
+#[derive(Serialize, Deserialize)]
+struct CodeActionData {
+    uri: Uri,
+    /// The selection of [`FORMAT_SELECTION_CODE_ACTION_KIND`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    range: Option<Range>,
+}
+
+impl ServerFormatter {
+    /// Offers [`FORMAT_SELECTION_CODE_ACTION_KIND`] for a non-empty selection,
+    /// with the same edits as [`Self::run_format_range`].
+    /// Also offers [`ORGANIZE_IMPORTS_CODE_ACTION_KIND`], formatting the file with its imports organized.
+    /// Both are only offered for JS/TS files which are not ignored.
+    ///
+    /// If the client supports `codeAction/resolve`, the code actions are returned without edits,
+    /// which are computed when one is chosen, see [`Self::resolve_code_action`].
+    /// Otherwise they are only offered if their kind is in `only_code_action_kinds`,
+    /// as clients request code actions on every cursor move, and computing the edits formats the whole file.
+    /// They are not offered then if the selection is already formatted,
+    /// or organizing the imports changes nothing more than formatting.
+    pub(super) fn code_actions(
+        &self,
+        uri: &Uri,
+        range: &Range,
+        content: Option<&str>,
+        only_code_action_kinds: Option<&Vec<CodeActionKind>>,
+    ) -> Vec<CodeActionOrCommand> {
+        let is_resolved_later = self.code_action_resolve.load(Ordering::Relaxed);
+        let is_requested = |kind: &CodeActionKind| {
+            (is_resolved_later || only_code_action_kinds.is_some())
+                && is_code_action_kind_requested(kind, only_code_action_kinds)
+        };
+        let mut actions = vec![];
+
+        if range.start != range.end && is_requested(&FORMAT_SELECTION_CODE_ACTION_KIND) {
+            let edits = if is_resolved_later {
+                self.is_js_document(uri, content).then_some(None)
+            } else {
+                self.run_format_range(uri, *range, content, None)
+                    .filter(|edits| !edits.is_empty())
+                    .map(Some)
+            };
+            if let Some(edits) = edits {
+                actions.push(code_action(
+                    "Format selection",
+                    FORMAT_SELECTION_CODE_ACTION_KIND,
+                    CodeActionData { uri: uri.clone(), range: Some(*range) },
+                    edits,
+                ));
+            }
+        }
+
+        if is_requested(&ORGANIZE_IMPORTS_CODE_ACTION_KIND) {
+            let edits = if is_resolved_later {
+                self.is_js_document(uri, content).then_some(None)
+            } else {
+                self.run_organize_imports(uri, content).map(Some)
+            };
+            if let Some(edits) = edits {
+                actions.push(code_action(
+                    "Format and organize imports",
+                    ORGANIZE_IMPORTS_CODE_ACTION_KIND,
+                    CodeActionData { uri: uri.clone(), range: None },
+                    edits,
+                ));
+            }
+        }
+
+        actions
+    }
+
+    /// Computes the edits of a code action returned by [`Self::code_actions`] without them.
+    /// The edits are empty if there is nothing to change.
+    pub(super) fn resolve_code_action(
+        &self,
+        code_action: &CodeAction,
+        content: Option<&str>,
+    ) -> Option<CodeAction> {
+        let kind = code_action.kind.as_ref()?;
+        let CodeActionData { uri, range } =
+            serde_json::from_value(code_action.data.clone()?).ok()?;
+        let edits = if *kind == FORMAT_SELECTION_CODE_ACTION_KIND {
+            self.run_format_range(&uri, range?, content, None)
+        } else if *kind == ORGANIZE_IMPORTS_CODE_ACTION_KIND {
+            self.run_organize_imports(&uri, content)
+        } else {
+            return None;
+        };
+
+        Some(CodeAction {
+            edit: Some(workspace_edit(&uri, edits.unwrap_or_default())),
+            ..code_action.clone()
+        })
+    }
+
+    /// Whether the document is a JS/TS file which is not ignored,
+    /// the only files the code actions are offered for.
+    fn is_js_document(&self, uri: &Uri, content: Option<&str>) -> bool {
+        uri.to_file_path().and_then(|path| self.get_source_text(&path, content).ok()).is_some_and(
+            |(strategy, _)| matches!(strategy, FormatFileStrategy::OxcFormatter { .. }),
+        )
+    }
+
+    /// Formats a JS/TS file with its imports organized, see [`ORGANIZE_IMPORTS_CODE_ACTION_KIND`].
+    /// Returns `None` if the file cannot be formatted,
+    /// or organizing the imports changes nothing more than formatting.
+    fn run_organize_imports(&self, uri: &Uri, content: Option<&str>) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+        if !matches!(strategy, FormatFileStrategy::OxcFormatter { .. }) {
+            return None;
+        }
+
+        let is_cancelled = AtomicBool::new(false);
+        let mut stats = FormatStats::default();
+        let code = self
+            .format_with_stats(&strategy, &source_text, None, true, &is_cancelled, &mut stats)
+            .ok()?;
+        if code == *source_text {
+            return None;
+        }
+
+        // The edits only match those of formatting without organizing the imports if the code does,
+        // which are usually cached already, see `run_format`
+        let edits = build_text_edits(&source_text, &code);
+        if self.run_format(uri, Some(&source_text), None, &is_cancelled)? == edits {
+            return None;
+        }
+        Some(edits)
+    }
+}
+
+/// Returns whether code actions of `kind` are requested, i.e. `kind` or one of its parents is in `only`.
+/// All kinds are requested if `only` is not set.
+fn is_code_action_kind_requested(
+    kind: &CodeActionKind,
+    only: Option<&Vec<CodeActionKind>>,
+) -> bool {
+    only.is_none_or(|only| {
+        only.iter().any(|requested| {
+            kind.as_str() == requested.as_str()
+                || kind
+                    .as_str()
+                    .strip_prefix(requested.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    })
+}
+
+/// Creates a code action applying `edits` to the document.
+/// Without `edits`, they are computed by [`ServerFormatter::resolve_code_action`] from `data`.
+fn code_action(
+    title: &str,
+    kind: CodeActionKind,
+    data: CodeActionData,
+    edits: Option<Vec<TextEdit>>,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.to_string(),
+        kind: Some(kind),
+        edit: edits.map(|edits| workspace_edit(&data.uri, edits)),
+        data: serde_json::to_value(data).ok(),
+        ..Default::default()
+    })
+}
+
+fn workspace_edit(uri: &Uri, edits: Vec<TextEdit>) -> WorkspaceEdit {
+    WorkspaceEdit {
+        changes: Some(std::iter::once((uri.clone(), edits)).collect()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::atomic::AtomicBool};
+
+    use oxc_language_server::{Capabilities, Tool, ToolBuilder};
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{
+        CodeActionKind, CodeActionOrCommand, CodeActionProviderCapability, Position, Range,
+        ServerCapabilities,
+    };
+
+    use crate::{
+        core::FormatFileStrategy,
+        lsp::{
+            ServerFormatterBuilder,
+            server_formatter::{
+                FORMAT_SELECTION_CODE_ACTION_KIND, ORGANIZE_IMPORTS_CODE_ACTION_KIND,
+                diff::build_text_edits,
+            },
+            tester::{Tester, get_file_uri},
+        },
+    };
+
+    fn selection_only() -> Vec<CodeActionKind> {
+        vec![FORMAT_SELECTION_CODE_ACTION_KIND]
+    }
+
+    #[test]
+    fn test_format_selection_code_action() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/range"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let selection = Range::new(Position::new(1, 0), Position::new(1, 16));
+        let only = selection_only();
+
+        let actions = formatter.get_code_actions_or_commands(&uri, &selection, None, Some(&only));
+        let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+            panic!("Expected a single code action, got {actions:?}");
+        };
+        assert_eq!(action.kind, Some(FORMAT_SELECTION_CODE_ACTION_KIND));
+        let changes = action.edit.as_ref().and_then(|edit| edit.changes.as_ref()).unwrap();
+        assert_eq!(changes[&uri], formatter.run_format_range(&uri, selection, None, None).unwrap());
+
+        // Without `codeAction/resolve`, only offered if the kind is requested, as the edits are computed right away
+        assert!(formatter.get_code_actions_or_commands(&uri, &selection, None, None).is_empty());
+
+        // Not offered for an empty selection, other kinds, or formatted code
+        let cursor = Range::new(Position::new(1, 0), Position::new(1, 0));
+        assert!(
+            formatter.get_code_actions_or_commands(&uri, &cursor, None, Some(&only)).is_empty()
+        );
+        let quickfix = vec![CodeActionKind::QUICKFIX];
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &selection, None, Some(&quickfix))
+                .is_empty()
+        );
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &selection, Some("const b = 2;\n"), Some(&only))
+                .is_empty()
+        );
+
+        // Not offered for ignored files
+        let formatter = ServerFormatterBuilder::new(None).build(
+            &Tester::get_root_uri("test/fixtures/lsp/range"),
+            json!({ "fmt.includePatterns": ["other/**"] }),
+        );
+        assert!(
+            formatter.get_code_actions_or_commands(&uri, &selection, None, Some(&only)).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_resolve_code_action() {
+        let builder = ServerFormatterBuilder::new(None);
+        let formatter = builder.build(&Tester::get_root_uri("test/fixtures/lsp/range"), json!({}));
+        let mut capabilities = ServerCapabilities::default();
+        let mut backend_capabilities = Capabilities::default();
+        backend_capabilities.code_action_resolve = true;
+        builder.server_capabilities(&mut capabilities, &backend_capabilities);
+        let Some(CodeActionProviderCapability::Options(options)) =
+            capabilities.code_action_provider
+        else {
+            panic!("Expected code action options");
+        };
+        assert_eq!(options.resolve_provider, Some(true));
+
+        // Offered without edits for any requested kind, even if the selection is formatted
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let selection = Range::new(Position::new(1, 0), Position::new(1, 16));
+        let actions = formatter.get_code_actions_or_commands(&uri, &selection, None, None);
+        let [
+            CodeActionOrCommand::CodeAction(format_selection),
+            CodeActionOrCommand::CodeAction(organize_imports),
+        ] = actions.as_slice()
+        else {
+            panic!("Expected two code actions, got {actions:?}");
+        };
+        assert_eq!(format_selection.kind, Some(FORMAT_SELECTION_CODE_ACTION_KIND));
+        assert_eq!(organize_imports.kind, Some(ORGANIZE_IMPORTS_CODE_ACTION_KIND));
+        assert!(format_selection.edit.is_none());
+        assert!(organize_imports.edit.is_none());
+
+        // The edits are computed when resolved
+        let resolved = formatter.resolve_code_action(format_selection, None).unwrap();
+        let changes = resolved.edit.as_ref().and_then(|edit| edit.changes.as_ref()).unwrap();
+        assert_eq!(changes[&uri], formatter.run_format_range(&uri, selection, None, None).unwrap());
+        let resolved = formatter.resolve_code_action(organize_imports, None).unwrap();
+        let changes = resolved.edit.as_ref().and_then(|edit| edit.changes.as_ref()).unwrap();
+        assert!(changes[&uri].is_empty());
+
+        // Not offered for non-JS files
+        let json_uri = get_file_uri("test/fixtures/lsp/range/data.json");
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&json_uri, &selection, Some("{}\n"), None)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_organize_imports() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let source = "import { b } from \"b\";\nimport \"./z-side-effect\";\nimport \"./a-side-effect\";\nimport { a } from \"a\";\nimport { b } from \"b\";\n\nb(a);\n";
+        let organized = "import { a } from \"a\";\n\nimport \"./z-side-effect\";\nimport \"./a-side-effect\";\nimport { b } from \"b\";\n\nb(a);\n";
+        let format = |options| {
+            ServerFormatterBuilder::new(None)
+                .build(&root_uri, options)
+                .format(&strategy, source, None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        // Duplicates are removed and side-effect imports keep their order
+        assert_eq!(format(json!({ "fmt.organizeImports": true })), organized);
+        // Imports are kept as is by default
+        assert_eq!(format(json!({})), source);
+
+        // Also offered as a code action
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/imports.ts");
+        let cursor = Range::new(Position::new(0, 0), Position::new(0, 0));
+        let only = vec![ORGANIZE_IMPORTS_CODE_ACTION_KIND];
+        let actions =
+            formatter.get_code_actions_or_commands(&uri, &cursor, Some(source), Some(&only));
+        let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+            panic!("Expected a single code action, got {actions:?}");
+        };
+        assert_eq!(action.kind, Some(ORGANIZE_IMPORTS_CODE_ACTION_KIND));
+        let changes = action.edit.as_ref().and_then(|edit| edit.changes.as_ref()).unwrap();
+        assert_eq!(changes[&uri], build_text_edits(source, organized));
+
+        // Not offered for other kinds, organized imports, or non-JS files
+        let quickfix = vec![CodeActionKind::QUICKFIX];
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &cursor, Some(source), Some(&quickfix))
+                .is_empty()
+        );
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &cursor, Some(organized), Some(&only))
+                .is_empty()
+        );
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &cursor, Some("b(a);\n"), Some(&only))
+                .is_empty()
+        );
+        let json_uri = get_file_uri("test/fixtures/lsp/basic/imports.json");
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&json_uri, &cursor, Some("{}\n"), Some(&only))
+                .is_empty()
+        );
+        // Formatting without organizing the imports is not done again if cached
+        let path = uri.to_file_path().unwrap();
+        formatter.format_cache.insert(
+            &path,
+            &(source, None::<(bool, u32)>),
+            build_text_edits(source, organized),
+        );
+        assert!(
+            formatter
+                .get_code_actions_or_commands(&uri, &cursor, Some(source), Some(&only))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,542 @@
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::{debug, warn};
+use oxc_language_server::utils::normalize_path;
+use serde_json::json;
+
+use super::{
+    ServerFormatter,
+    globs::{IGNORE_FILES, normalize_windows_path},
+};
+use crate::{
+    core::{
+        ConfigResolver, JSON_CONFIG_FILE_NAMES, resolve_editorconfig_path, resolve_oxfmtrc_path,
+    },
+    lsp::{
+        FORMAT_CONFIG_FILES, PRETTIER_CONFIG_FILES,
+        external_formatter_bridge::ExternalFormatterBridge, format_cache::hash_source,
+    },
+};
+
+impl ServerFormatter {
+    /// Whether a change to the watched file requires the formatter to be rebuilt.
+    ///
+    /// Only the config file, the nested `.oxfmtrc.json` files, the ignore files and `.editorconfig` files
+    /// affect the formatter, as well as Prettier config files with `read_prettier_config`.
+    /// Changes leaving the content of the file as it was on the previous change of the same workspace are skipped,
+    /// as editors can fire several events for one save, e.g. truncate and write.
+    pub(super) fn needs_rebuild(
+        &self,
+        path: &Path,
+        config_path: Option<&String>,
+        file_names: &[String],
+        read_prettier_config: bool,
+    ) -> bool {
+        let is_config_file = match config_path.filter(|s| !s.is_empty()) {
+            Some(config_path) if is_glob_config_path(config_path) => {
+                config_path_glob(&self.root_path, config_path)
+                    .is_some_and(|glob| glob.matched(path, false).is_ignore())
+            }
+            Some(config_path) => path == normalize_path(self.root_path.join(config_path)),
+            None => config_file_names(file_names, read_prettier_config)
+                .any(|file| path == normalize_path(self.root_path.join(file))),
+        };
+        let is_ignore_file = IGNORE_FILES.iter().any(|file| path == self.root_path.join(file));
+        let is_editorconfig = path.file_name().is_some_and(|name| name == ".editorconfig");
+        // Those in the root path are only used as the config file
+        let is_nested_config = path.starts_with(&self.root_path)
+            && path.parent() != Some(self.root_path.as_path())
+            && path
+                .file_name()
+                .is_some_and(|name| JSON_CONFIG_FILE_NAMES.iter().any(|n| name == *n));
+        if !is_config_file && !is_ignore_file && !is_editorconfig && !is_nested_config {
+            debug!(path:% = path.display(); "Skipping rebuild, not a config or ignore file");
+            return false;
+        }
+
+        // `None` if the file was deleted
+        let hash = std::fs::read(path).ok().map(|content| hash_source(&content));
+        let watched_file_hashes = self.watched_file_hashes.pin();
+        let key = (self.root_path.clone(), path.to_path_buf());
+        if watched_file_hashes.insert(key, hash) == Some(&hash) {
+            debug!(path:% = path.display(); "Skipping rebuild, content did not change");
+            return false;
+        }
+        true
+    }
+}
+
+/// Resolve the config of the workspace and its ignore patterns.
+/// Falls back to the default config if the config file is not found or invalid.
+/// JS config files are loaded through the external formatter bridge, if available.
+/// Returns the config resolver, its ignore patterns,
+/// and the reason why the default config is used instead, if the config could not be resolved.
+pub(super) fn resolve_config(
+    root_path: &Path,
+    oxfmtrc_path: Option<&Path>,
+    external_bridge: Option<&dyn ExternalFormatterBridge>,
+) -> (ConfigResolver, Vec<String>, Option<String>) {
+    let editorconfig_path = resolve_editorconfig_path(root_path);
+
+    let resolved = match oxfmtrc_path {
+        Some(path) if is_prettier_config_path(path) => {
+            ConfigResolver::from_prettier_config_path(root_path, path, editorconfig_path.as_deref())
+        }
+        Some(path) if is_js_config_path(path) => external_bridge
+            .ok_or_else(|| "JS config files require the external formatter".to_string())
+            .and_then(|bridge| bridge.load_config(path))
+            .and_then(|raw_config| {
+                ConfigResolver::from_value_with_editorconfig(
+                    root_path,
+                    raw_config,
+                    editorconfig_path.as_deref(),
+                )
+            }),
+        oxfmtrc_path => {
+            ConfigResolver::from_config_paths(root_path, oxfmtrc_path, editorconfig_path.as_deref())
+        }
+    }
+    .and_then(|mut config_resolver| {
+        let ignore_patterns = config_resolver.build_and_validate()?;
+        Ok((config_resolver, ignore_patterns))
+    });
+
+    match resolved {
+        Ok((config_resolver, ignore_patterns)) => (config_resolver, ignore_patterns, None),
+        Err(err) => {
+            // Keep formatting with the default config, but let the user know their config is broken
+            let config_file = oxfmtrc_path
+                .map_or_else(|| "config".to_string(), |path| path.display().to_string());
+            let config_error =
+                format!("Failed to resolve {config_file}: {err}, fallback to default config");
+            warn!("{config_error}");
+            let mut config_resolver = ConfigResolver::from_value(json!({}));
+            let ignore_patterns =
+                config_resolver.build_and_validate().expect("default config should be valid");
+            (config_resolver, ignore_patterns, Some(config_error))
+        }
+    }
+}
+
+/// Config files looked up in the workspace root, in order of priority.
+/// `file_names` from `fmt.configFileNames` replace [`FORMAT_CONFIG_FILES`], unless empty.
+/// Prettier config files are only included with `fmt.readPrettierConfig`.
+pub(super) fn config_file_names(
+    file_names: &[String],
+    read_prettier_config: bool,
+) -> impl Iterator<Item = &str> {
+    let default_config_files =
+        if file_names.is_empty() { FORMAT_CONFIG_FILES.as_slice() } else { &[] };
+    let prettier_config_files =
+        if read_prettier_config { PRETTIER_CONFIG_FILES.as_slice() } else { &[] };
+    file_names
+        .iter()
+        .map(String::as_str)
+        .chain(default_config_files.iter().chain(prettier_config_files).copied())
+}
+
+/// Whether the config file is a Prettier config file found with `fmt.readPrettierConfig`.
+fn is_prettier_config_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| PRETTIER_CONFIG_FILES.contains(&name))
+}
+
+/// Whether the config file has to be loaded by JS side, e.g. `.oxfmtrc.js`.
+fn is_js_config_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext, "js" | "cjs" | "mjs"))
+}
+
+/// Find the config file of the workspace.
+/// `config_path` is relative to the root path, if not found, searches for the default config files in the root path.
+/// With `read_prettier_config`, Prettier config files are searched last.
+/// If the root path has no config file, the nearest `.oxfmtrc.json` of its parent directories is used.
+///
+/// `config_path` can also be a glob, e.g. `configs/*.oxfmtrc.json`, see [`find_config_path_by_glob`].
+///
+/// # Errors
+/// Returns error if the glob matches multiple config files and none of them is the nearest.
+pub(super) fn find_config_path(
+    root_path: &Path,
+    config_path: Option<&String>,
+    file_names: &[String],
+    read_prettier_config: bool,
+) -> Result<Option<PathBuf>, String> {
+    if let Some(config_path) = config_path.filter(|s| !s.is_empty()) {
+        let config = if is_glob_config_path(config_path) {
+            find_config_path_by_glob(root_path, config_path)?
+        } else {
+            let config = normalize_path(root_path.join(config_path));
+            config.try_exists().is_ok_and(|exists| exists).then_some(config)
+        };
+        if config.is_some() {
+            return Ok(config);
+        }
+
+        warn!(
+            "Config file not found: {}, searching for `{}` in the root path",
+            normalize_path(root_path.join(config_path)).to_string_lossy(),
+            config_file_names(file_names, read_prettier_config).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let config = config_file_names(file_names, read_prettier_config).find_map(|file| {
+        let config = normalize_path(root_path.join(file));
+        config.try_exists().is_ok_and(|exists| exists).then_some(config)
+    });
+    // Like the CLI, the nearest `.oxfmtrc.json` of the parent directories is used if the root has none
+    Ok(config.or_else(|| root_path.parent().and_then(|parent| resolve_oxfmtrc_path(parent, None))))
+}
+
+/// Whether `fmt.configPath` is a glob, e.g. `configs/*.oxfmtrc.json`, rather than a single file.
+fn is_glob_config_path(config_path: &str) -> bool {
+    config_path.contains(['*', '?', '[', '{'])
+}
+
+/// Build the matcher of the glob `config_path`, relative to the root path,
+/// same syntax as the ignore patterns.
+fn config_path_glob(root_path: &Path, config_path: &str) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(normalize_windows_path(root_path));
+    builder.add_line(None, config_path).ok()?;
+    builder.build().ok()
+}
+
+/// Find the config file matching the glob `config_path` in the workspace.
+/// A workspace is formatted with a single config, so the match nearest to the root path is used,
+/// e.g. `configs/*.oxfmtrc.json` selected by environment, with only one of them present.
+///
+/// # Errors
+/// Returns error if the glob is invalid, or multiple config files are the nearest match,
+/// since there is no telling which one is meant.
+fn find_config_path_by_glob(
+    root_path: &Path,
+    config_path: &str,
+) -> Result<Option<PathBuf>, String> {
+    let glob = config_path_glob(root_path, config_path)
+        .ok_or_else(|| format!("Invalid `configPath` glob: {config_path}"))?;
+
+    let mut matches = ignore::WalkBuilder::new(root_path)
+        .filter_entry(|entry| {
+            !matches!(
+                entry.file_name().to_str(),
+                Some(".git" | ".jj" | ".sl" | ".svn" | ".hg" | "node_modules")
+            )
+        })
+        .hidden(false)
+        .ignore(false)
+        .parents(false)
+        .git_global(false)
+        .git_ignore(false)
+        .git_exclude(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|file_type| !file_type.is_dir()))
+        .map(ignore::DirEntry::into_path)
+        .filter(|path| glob.matched(path, false).is_ignore())
+        .collect::<Vec<_>>();
+
+    let Some(nearest_depth) = matches.iter().map(|path| path.components().count()).min() else {
+        return Ok(None);
+    };
+    matches.retain(|path| path.components().count() == nearest_depth);
+    matches.sort_unstable();
+    match matches.as_slice() {
+        [config] => Ok(Some(normalize_path(config))),
+        _ => Err(format!(
+            "`configPath` glob `{config_path}` matches multiple config files: {}, fallback to default config",
+            matches.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, atomic::AtomicBool},
+        time::SystemTime,
+    };
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{Position, Range, TextEdit};
+
+    use super::find_config_path;
+    use crate::{
+        core::FormatFileStrategy,
+        lsp::{
+            ServerFormatterBuilder,
+            tester::{BridgeCall, TestBridge, Tester, get_file_path, get_file_uri},
+        },
+    };
+
+    #[test]
+    fn test_nested_config() {
+        let source = "const a = \"a\"\nif (a) {\nb()\n}\n";
+        let format = |root_dir: &str, file: &str| {
+            let strategy =
+                FormatFileStrategy::try_from(get_file_path(&format!("{root_dir}/{file}"))).unwrap();
+            ServerFormatterBuilder::new(None)
+                .build(&Tester::get_root_uri(root_dir), json!({}))
+                .format(&strategy, source, None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        let root_dir = "test/fixtures/lsp/nested_config";
+        assert_eq!(format(root_dir, "a.ts"), "const a = 'a'\nif (a) {\n  b()\n}\n");
+        // `singleQuote` is inherited from the root config
+        assert_eq!(format(root_dir, "app/a.ts"), "const a = 'a';\nif (a) {\n    b();\n}\n");
+        // The closest config wins
+        assert_eq!(
+            format(root_dir, "app/deep/a.ts"),
+            "const a = 'a';\nif (a) {\n        b();\n}\n"
+        );
+        // `root: true` stops merging the configs of parent directories
+        assert_eq!(format(root_dir, "isolated/a.ts"), "const a = \"a\";\nif (a) {\n   b();\n}\n");
+
+        // The configs of the parent directories of the workspace are merged too
+        let root_dir = "test/fixtures/lsp/nested_config/app";
+        assert_eq!(format(root_dir, "a.ts"), "const a = 'a';\nif (a) {\n    b();\n}\n");
+        assert_eq!(format(root_dir, "deep/a.ts"), "const a = 'a';\nif (a) {\n        b();\n}\n");
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let tester = Tester::new("test/fixtures/lsp/overrides", json!({}));
+        let indent_of = |relative_file_path: &str| {
+            let edits = tester.format(relative_file_path, None).expect("file should be formatted");
+            assert_eq!(edits.len(), 1);
+            edits[0].new_text.clone()
+        };
+
+        assert_eq!(indent_of("code.ts"), "  ");
+        assert_eq!(indent_of("data.json"), "    ");
+        // The last matching override wins
+        assert_eq!(indent_of("nested/data.json"), "        ");
+    }
+
+    #[test]
+    fn test_nested_editorconfig() {
+        let tester = Tester::new("test/fixtures/lsp/editorconfig_nested", json!({}));
+        let indent_of = |relative_file_path: &str| {
+            let edits = tester.format(relative_file_path, None).expect("file should be formatted");
+            assert_eq!(edits.len(), 1);
+            edits[0].new_text.clone()
+        };
+
+        assert_eq!(indent_of("root.ts"), "  ");
+        // The closest `.editorconfig` wins
+        assert_eq!(indent_of("nested/nested.ts"), "    ");
+        assert_eq!(indent_of("nested/deeper/deeper.ts"), "        ");
+        // `root = true` stops the lookup, so the defaults are used
+        assert_eq!(indent_of("nested/isolated/isolated.ts"), "  ");
+    }
+
+    #[test]
+    fn test_config_error_setup_warning() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/invalid_config"), json!({}));
+        let warnings = formatter.get_setup_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(".oxfmtrc.json"));
+        // Still formats with the default config
+        let tester = Tester::new("test/fixtures/lsp/invalid_config", json!({}));
+        assert!(tester.format("invalid_config.ts", Some("const a   =   1;\n")).is_some());
+
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        assert!(formatter.get_setup_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_js_config() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/js_config");
+        let uri = get_file_uri("test/fixtures/lsp/js_config/js_config.ts");
+
+        let bridge = Arc::new(TestBridge::default().with_config(json!({ "semi": false })));
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _)).build(&root_uri, json!({}));
+        assert!(formatter.get_setup_warnings().is_empty());
+        assert!(bridge.take_calls().iter().any(
+            |call| matches!(call, BridgeCall::LoadConfig(path) if path.ends_with(".oxfmtrc.js"))
+        ));
+        let edits = formatter
+            .run_format(&uri, None, None, &AtomicBool::new(false))
+            .expect("semicolon should be removed");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "");
+
+        // JS config files can not be loaded without the external formatter
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        let warnings = formatter.get_setup_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(".oxfmtrc.js"));
+    }
+
+    #[test]
+    fn test_configuration_change_keeps_config() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/config_glob");
+        let uri = get_file_uri("test/fixtures/lsp/config_glob/test.ts");
+        let old_options = json!({ "fmt.configPath": "configs/dev.oxfmtrc.json" });
+        let builder = ServerFormatterBuilder::new(None);
+        let formatter = builder.build(&root_uri, old_options.clone());
+
+        let changes = formatter.handle_configuration_change(
+            &builder,
+            &root_uri,
+            &old_options,
+            json!({ "fmt.configPath": "configs/dev.oxfmtrc.json", "fmt.maxFileSizeBytes": 1024 }),
+        );
+        assert!(changes.watch_patterns.is_none());
+        let formatter = changes.tool.expect("formatter should be rebuilt");
+        // `semi: false` of the config is still applied
+        let edits = formatter.run_format(&uri, None, None, &AtomicBool::new(false)).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "");
+        // The new options are applied too
+        assert_eq!(
+            formatter.run_format(&uri, Some(&"a;".repeat(1024)), None, &AtomicBool::new(false)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_root_config_detection() {
+        Tester::new(
+            "test/fixtures/lsp/root_config",
+            json!({
+                "fmt.experimental": true
+            }),
+        )
+        .format_and_snapshot_single_file("semicolons-as-needed.ts");
+    }
+
+    #[test]
+    fn test_require_config() {
+        let tester = Tester::new("test/fixtures/lsp/basic", json!({ "fmt.requireConfig": true }));
+        assert!(tester.format("basic.ts", Some("const a=1\n")).is_none());
+
+        let tester =
+            Tester::new("test/fixtures/lsp/root_config", json!({ "fmt.requireConfig": true }));
+        assert!(tester.format("semicolons-as-needed.ts", None).is_some());
+    }
+
+    #[test]
+    fn test_read_prettier_config() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/prettierrc");
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let format = |options| {
+            ServerFormatterBuilder::new(None)
+                .build(&root_uri, options)
+                .format(&strategy, "const a = \"a\";\n", None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        // `.prettierrc` is only read when enabled
+        assert_eq!(format(json!({})), "const a = \"a\";\n");
+        // Unsupported options like `plugins` are ignored
+        assert_eq!(format(json!({ "fmt.readPrettierConfig": true })), "const a = 'a'\n");
+
+        // `.prettierrc` counts as a config file
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&root_uri, json!({ "fmt.readPrettierConfig": true, "fmt.requireConfig": true }));
+        assert!(formatter.config_error.is_none());
+        assert!(!formatter.is_disabled);
+    }
+
+    #[test]
+    fn test_config_file_names() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/config_file_names");
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let format = |options| {
+            ServerFormatterBuilder::new(None)
+                .build(&root_uri, options)
+                .format(&strategy, "const a = 1;\n", None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        assert_eq!(format(json!({})), "const a = 1;\n");
+        assert_eq!(
+            format(json!({ "fmt.configFileNames": ["oxfmt.json", ".config/oxfmt.json"] })),
+            "const a = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_config_path() {
+        Tester::new(
+            "test/fixtures/lsp/custom_config_path",
+            json!({
+                "fmt.experimental": true,
+                "fmt.configPath": "./format.json",
+            }),
+        )
+        .format_and_snapshot_single_file("semicolons-as-needed.ts");
+    }
+
+    #[test]
+    fn test_glob_config_path() {
+        let root_dir = "test/fixtures/lsp/config_glob";
+        let root_path = get_file_path(root_dir);
+        let find = |config_path: &str| {
+            find_config_path(&root_path, Some(&config_path.to_string()), &[], false)
+        };
+
+        // The match nearest to the root wins
+        assert_eq!(
+            find("configs/**/*.oxfmtrc.json"),
+            Ok(Some(root_path.join("configs/dev.oxfmtrc.json")))
+        );
+        assert_eq!(
+            find("**/prod.oxfmtrc.json"),
+            Ok(Some(root_path.join("configs/nested/prod.oxfmtrc.json")))
+        );
+        assert_eq!(find("missing/*.json"), Ok(None));
+        assert!(find("*/*.oxfmtrc.json").is_err_and(|err| err.contains("matches multiple")));
+
+        let tester = Tester::new(root_dir, json!({ "fmt.configPath": "configs/*.oxfmtrc.json" }));
+        assert_eq!(
+            tester.format("test.ts", None),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(0, 11), Position::new(0, 12)),
+                String::new()
+            )])
+        );
+
+        // Ambiguous matches are reported, and the default config is used
+        let root_uri = Tester::get_root_uri(root_dir);
+        let options = json!({ "fmt.configPath": "*/*.oxfmtrc.json" });
+        let builder = ServerFormatterBuilder::new(None);
+        let formatter = builder.build(&root_uri, options.clone());
+        assert!(formatter.get_setup_warnings()[0].contains("matches multiple config files"));
+        assert!(
+            formatter.format_to_diff(&get_file_uri(&format!("{root_dir}/test.ts")), None, 0)
+                == Some(String::new())
+        );
+
+        // Any matching file triggers a rebuild
+        let config = root_path.join("other/ci.oxfmtrc.json");
+        std::fs::File::options()
+            .write(true)
+            .open(&config)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .unwrap();
+        let changes = formatter.handle_watched_file_change(
+            &builder,
+            &get_file_uri(&format!("{root_dir}/other/ci.oxfmtrc.json")),
+            &root_uri,
+            options.clone(),
+        );
+        assert!(changes.tool.is_some());
+        let changes = formatter.handle_watched_file_change(
+            &builder,
+            &get_file_uri(&format!("{root_dir}/test.ts")),
+            &root_uri,
+            options,
+        );
+        assert!(changes.tool.is_none());
+    }
+}
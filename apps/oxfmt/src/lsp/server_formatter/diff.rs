@@ -0,0 +1,596 @@
+use std::sync::atomic::AtomicBool;
+
+use oxc_data_structures::rope::{Rope, get_line_column};
+use similar::{DiffTag, TextDiff};
+use tower_lsp_server::ls_types::{Position, Range, TextEdit, Uri};
+use unicode_segmentation::GraphemeCursor;
+
+use super::ServerFormatter;
+
+impl ServerFormatter {
+    /// Same as [`Tool::run_format`], but only returns the edits touching `changed_line_ranges`,
+    /// e.g. for a "format only my changes" command, with the ranges computed from `git diff` by the caller.
+    /// Only the lines of the ranges are compared, the characters are ignored.
+    /// The whole file is formatted, then each changed hunk is either kept or dropped as a whole.
+    pub fn run_format_changed(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+        changed_line_ranges: &[Range],
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+        let code = self.format(&strategy, &source_text, None, &AtomicBool::new(false)).ok()?;
+        if code == *source_text {
+            return Some(vec![]);
+        }
+
+        // Always split into line hunks, so code close to the changed lines is not reformatted
+        Some(
+            build_line_diff_text_edits(&source_text, &code)
+                .into_iter()
+                .filter(|edit| {
+                    changed_line_ranges.iter().any(|range| {
+                        edit.range.start.line <= range.end.line
+                            && range.start.line <= edit.range.end.line
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Format the file and return the changes as a unified diff,
+    /// with `context_lines` unchanged lines around each hunk.
+    /// The result can be shown in a terminal or applied with `patch`.
+    ///
+    /// - If the file is not formattable or ignored, [`None`] is returned
+    /// - If the file is formattable, but no changes are made, an empty string is returned
+    pub fn format_to_diff(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+        context_lines: usize,
+    ) -> Option<String> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+        let code = self.format(&strategy, &source_text, None, &AtomicBool::new(false)).ok()?;
+
+        if code == source_text {
+            return Some(String::new());
+        }
+
+        let file_name = path.to_string_lossy();
+        Some(
+            TextDiff::from_lines(source_text.as_ref(), &code)
+                .unified_diff()
+                .context_radius(context_lines)
+                .header(&file_name, &file_name)
+                .to_string(),
+        )
+    }
+}
+
+/// If the minimal text edit spans more lines than this,
+/// it is split into multiple edits by diffing line by line.
+const MAX_SINGLE_EDIT_LINES: usize = 50;
+
+/// Returns the text edits to transform `source_text` into `formatted_text`.
+/// If both are the same, an empty vector is returned.
+///
+/// Changes close to each other are returned as a single edit.
+/// Otherwise, each changed hunk gets its own edit,
+/// so a small change deep in a large file does not replace everything in between.
+pub(super) fn build_text_edits(source_text: &str, formatted_text: &str) -> Vec<TextEdit> {
+    if source_text == formatted_text {
+        return vec![];
+    }
+
+    let (start, end, replacement) = compute_minimal_text_edit(source_text, formatted_text);
+
+    let replaced_text = &source_text[start as usize..end as usize];
+    let changed_lines = replaced_text.matches('\n').count().max(replacement.matches('\n').count());
+    if changed_lines <= MAX_SINGLE_EDIT_LINES {
+        let mut cursor = PositionCursor::new(source_text);
+        return vec![cursor.text_edit(start, end, replacement)];
+    }
+
+    build_line_diff_text_edits(source_text, formatted_text)
+}
+
+/// Returns the text with `edits` applied to `source_text`, the inverse of [`build_text_edits`],
+/// e.g. to get the formatted code back from the edits kept in the format cache.
+/// The edits must be sorted and must not overlap.
+pub(super) fn apply_text_edits(source_text: &str, edits: &[TextEdit]) -> String {
+    let mut cursor = PositionCursor::new(source_text);
+    let mut text = String::with_capacity(source_text.len());
+    let mut last_end = 0;
+    for edit in edits {
+        let start = cursor.offset(edit.range.start) as usize;
+        let end = cursor.offset(edit.range.end) as usize;
+        text.push_str(&source_text[last_end..start]);
+        text.push_str(&edit.new_text);
+        last_end = end;
+    }
+    text.push_str(&source_text[last_end..]);
+    text
+}
+
+/// Returns a text edit for each changed hunk, by diffing `source_text` and `formatted_text` line by line.
+/// Each edit is reduced to its minimal text edit.
+///
+/// Edits are created while walking the diff, so only the replacement of each hunk is copied,
+/// never the whole text, e.g. to a [`Rope`].
+#[expect(clippy::cast_possible_truncation)]
+pub(super) fn build_line_diff_text_edits(source_text: &str, formatted_text: &str) -> Vec<TextEdit> {
+    let diff = TextDiff::from_lines(source_text, formatted_text);
+    let (source_lines, formatted_lines) = (diff.old_slices(), diff.new_slices());
+    let lines_len = |lines: &[&str]| lines.iter().map(|line| line.len()).sum::<usize>();
+
+    let mut cursor = PositionCursor::new(source_text);
+    let mut edits = vec![];
+    let mut push_edit = |source_range: std::ops::Range<usize>,
+                         formatted_range: std::ops::Range<usize>| {
+        let source_slice = &source_text[source_range.clone()];
+        let formatted_slice = &formatted_text[formatted_range];
+        if source_slice == formatted_slice {
+            return;
+        }
+        let (start, end, replacement) = compute_minimal_text_edit(source_slice, formatted_slice);
+        let offset = source_range.start as u32;
+        edits.push(cursor.text_edit(offset + start, offset + end, replacement));
+    };
+
+    // Byte ranges of the current hunk.
+    // Adjacent changes (e.g. a deletion followed by an insertion) are merged into a single hunk.
+    let mut hunk: Option<(std::ops::Range<usize>, std::ops::Range<usize>)> = None;
+    let (mut source_offset, mut formatted_offset) = (0, 0);
+    for op in diff.ops() {
+        let source_end = source_offset + lines_len(&source_lines[op.old_range()]);
+        let formatted_end = formatted_offset + lines_len(&formatted_lines[op.new_range()]);
+
+        if op.tag() == DiffTag::Equal {
+            if let Some((source_range, formatted_range)) = hunk.take() {
+                push_edit(source_range, formatted_range);
+            }
+        } else {
+            let (source_range, formatted_range) = hunk
+                .get_or_insert((source_offset..source_offset, formatted_offset..formatted_offset));
+            source_range.end = source_end;
+            formatted_range.end = formatted_end;
+        }
+
+        (source_offset, formatted_offset) = (source_end, formatted_end);
+    }
+    if let Some((source_range, formatted_range)) = hunk {
+        push_edit(source_range, formatted_range);
+    }
+
+    edits
+}
+
+/// Converts byte offsets of `source_text` to LSP [`Position`]s in a single pass,
+/// without building a [`Rope`] of the whole text.
+/// Offsets must be passed in increasing order. Line breaks are the same as [`Rope`].
+pub(super) struct PositionCursor<'a> {
+    source_text: &'a str,
+    offset: usize,
+    line: u32,
+    line_start: usize,
+}
+
+impl<'a> PositionCursor<'a> {
+    pub(super) fn new(source_text: &'a str) -> Self {
+        Self { source_text, offset: 0, line: 0, line_start: 0 }
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    pub(super) fn position(&mut self, offset: u32) -> Position {
+        let offset = offset as usize;
+        debug_assert!(offset >= self.offset, "offsets must be passed in increasing order");
+
+        for (index, c) in self.source_text[self.offset..offset].char_indices() {
+            let index = self.offset + index;
+            if self.is_line_break(index, c) {
+                self.line += 1;
+                self.line_start = index + c.len_utf8();
+            }
+        }
+        self.offset = offset;
+
+        let character = self.source_text[self.line_start..offset].encode_utf16().count();
+        Position::new(self.line, character as u32)
+    }
+
+    /// Converts an LSP [`Position`] back to a byte offset of the text, the inverse of [`Self::position`].
+    /// Positions must be passed in increasing order, and never be past the end of their line.
+    #[expect(clippy::cast_possible_truncation)]
+    pub(super) fn offset(&mut self, position: Position) -> u32 {
+        let mut character =
+            self.source_text[self.line_start..self.offset].encode_utf16().count() as u32;
+        let mut offset = self.source_text.len();
+        for (index, c) in self.source_text[self.offset..].char_indices() {
+            let index = self.offset + index;
+            if self.line == position.line && character >= position.character {
+                offset = index;
+                break;
+            }
+            if self.is_line_break(index, c) {
+                self.line += 1;
+                self.line_start = index + c.len_utf8();
+                character = 0;
+            } else {
+                character += c.len_utf16() as u32;
+            }
+        }
+        self.offset = offset;
+        offset as u32
+    }
+
+    /// `\r\n` is a single line break, which ends after `\n`.
+    fn is_line_break(&self, index: usize, c: char) -> bool {
+        match c {
+            '\r' => !self.source_text[index + 1..].starts_with('\n'),
+            '\n' | '\u{000B}' | '\u{000C}' | '\u{0085}' | '\u{2028}' | '\u{2029}' => true,
+            _ => false,
+        }
+    }
+
+    fn text_edit(&mut self, start: u32, end: u32, replacement: &str) -> TextEdit {
+        let start = self.position(start);
+        let end = self.position(end);
+        TextEdit::new(Range::new(start, end), replacement.to_string())
+    }
+}
+
+pub(super) fn create_text_edit(
+    rope: &Rope,
+    source_text: &str,
+    start: u32,
+    end: u32,
+    replacement: &str,
+) -> TextEdit {
+    let (start_line, start_character) = get_line_column(rope, start, source_text);
+    let (end_line, end_character) = get_line_column(rope, end, source_text);
+
+    TextEdit::new(
+        Range::new(
+            Position::new(start_line, start_character),
+            Position::new(end_line, end_character),
+        ),
+        replacement.to_string(),
+    )
+}
+
+/// Returns `true` if the ranges overlap or touch each other.
+pub(super) fn is_range_intersecting(a: &Range, b: &Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// Returns the minimal text edit (start, end, replacement) to transform `source_text` into `formatted_text`.
+/// The edit is aligned to grapheme clusters, so it never starts or ends inside e.g. a ZWJ emoji sequence.
+#[expect(clippy::cast_possible_truncation)]
+pub(super) fn compute_minimal_text_edit<'a>(
+    source_text: &str,
+    formatted_text: &'a str,
+) -> (u32, u32, &'a str) {
+    debug_assert!(source_text != formatted_text);
+
+    let src_bytes = source_text.as_bytes();
+    let fmt_bytes = formatted_text.as_bytes();
+    let src_len = src_bytes.len();
+    let fmt_len = fmt_bytes.len();
+
+    // Find common prefix (byte offset), then move it back to a grapheme boundary of both texts
+    let mut prefix_byte = src_bytes.iter().zip(fmt_bytes).take_while(|(a, b)| a == b).count();
+    while !is_grapheme_boundary(source_text, prefix_byte)
+        || !is_grapheme_boundary(formatted_text, prefix_byte)
+    {
+        prefix_byte -= 1;
+    }
+
+    // Find common suffix (byte offset from end).
+    // The suffix must not overlap the prefix, e.g. `"aa"` -> `"aaa"`,
+    // so it is bounded by the shorter of the two remaining parts.
+    let max_suffix_byte = src_len.min(fmt_len) - prefix_byte;
+    let mut suffix_byte = src_bytes
+        .iter()
+        .rev()
+        .zip(fmt_bytes.iter().rev())
+        .take(max_suffix_byte)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !is_grapheme_boundary(source_text, src_len - suffix_byte)
+        || !is_grapheme_boundary(formatted_text, fmt_len - suffix_byte)
+    {
+        suffix_byte -= 1;
+    }
+
+    let start = prefix_byte as u32;
+    let end = (src_len - suffix_byte) as u32;
+    let replacement = &formatted_text[prefix_byte..fmt_len - suffix_byte];
+
+    (start, end, replacement)
+}
+
+/// Whether `offset` is between two grapheme clusters of `text`, or at its start or end.
+fn is_grapheme_boundary(text: &str, offset: usize) -> bool {
+    text.is_char_boundary(offset)
+        && GraphemeCursor::new(offset, text.len(), true).is_boundary(text, 0).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use oxc_data_structures::rope::{Rope, get_line_column};
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{Position, Range};
+
+    use super::{PositionCursor, apply_text_edits, build_text_edits, compute_minimal_text_edit};
+    use crate::lsp::{
+        ServerFormatterBuilder,
+        tester::{Tester, get_file_path, get_file_uri},
+    };
+
+    #[test]
+    #[should_panic(expected = "assertion failed")]
+    fn test_no_change() {
+        let src = "abc";
+        let formatted = "abc";
+        compute_minimal_text_edit(src, formatted);
+    }
+
+    #[test]
+    fn test_single_char_change() {
+        let src = "abc";
+        let formatted = "axc";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        // Only 'b' replaced by 'x'
+        assert_eq!((start, end, replacement), (1, 2, "x"));
+    }
+
+    #[test]
+    fn test_insert_char() {
+        let src = "abc";
+        let formatted = "abxc";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        // Insert 'x' after 'b'
+        assert_eq!((start, end, replacement), (2, 2, "x"));
+    }
+
+    #[test]
+    fn test_delete_char() {
+        let src = "abc";
+        let formatted = "ac";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        // Delete 'b'
+        assert_eq!((start, end, replacement), (1, 2, ""));
+    }
+
+    #[test]
+    fn test_replace_multiple_chars() {
+        let src = "abcdef";
+        let formatted = "abXYef";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        // Replace "cd" with "XY"
+        assert_eq!((start, end, replacement), (2, 4, "XY"));
+    }
+
+    #[test]
+    fn test_replace_multiple_chars_between_similars_complex() {
+        let src = "aYabYb";
+        let formatted = "aXabXb";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        assert_eq!((start, end, replacement), (1, 5, "XabX"));
+    }
+
+    #[test]
+    fn test_unicode() {
+        let src = "a😀b";
+        let formatted = "a😃b";
+        let (start, end, replacement) = compute_minimal_text_edit(src, formatted);
+        // Replace 😀 with 😃
+        assert_eq!((start, end, replacement), (1, 5, "😃"));
+    }
+
+    #[test]
+    fn test_append() {
+        let src = "a".repeat(100);
+        let mut formatted = src.clone();
+        formatted.push('b'); // Add a character at the end
+
+        let (start, end, replacement) = compute_minimal_text_edit(&src, &formatted);
+        assert_eq!((start, end, replacement), (100, 100, "b"));
+    }
+
+    #[test]
+    fn test_prepend() {
+        let src = "a".repeat(100);
+        let mut formatted = String::from("b");
+        formatted.push_str(&src); // Add a character at the start
+
+        let (start, end, replacement) = compute_minimal_text_edit(&src, &formatted);
+        assert_eq!((start, end, replacement), (0, 0, "b"));
+    }
+
+    #[test]
+    fn test_overlapping_prefix_and_suffix() {
+        let (start, end, replacement) = compute_minimal_text_edit("aa", "aaa");
+        assert_eq!((start, end, replacement), (2, 2, "a"));
+
+        let (start, end, replacement) = compute_minimal_text_edit("aaa", "aa");
+        assert_eq!((start, end, replacement), (2, 3, ""));
+    }
+
+    #[test]
+    fn test_overlapping_repeated_pattern() {
+        let (start, end, replacement) = compute_minimal_text_edit("abab", "ababab");
+        assert_eq!((start, end, replacement), (4, 4, "ab"));
+
+        let (start, end, replacement) = compute_minimal_text_edit("ababab", "abab");
+        assert_eq!((start, end, replacement), (4, 6, ""));
+    }
+
+    #[test]
+    fn test_repeated_char_insertions() {
+        for n in 0..5 {
+            for m in 0..5 {
+                if n == m {
+                    continue;
+                }
+                let src = " ".repeat(n);
+                let formatted = " ".repeat(m);
+                let (start, end, replacement) = compute_minimal_text_edit(&src, &formatted);
+                let mut result = src.clone();
+                result.replace_range(start as usize..end as usize, replacement);
+                assert_eq!(result, formatted);
+                assert_eq!(start as usize, n.min(m));
+            }
+        }
+    }
+
+    #[test]
+    fn test_suffix_does_not_split_multi_byte_char() {
+        // 'é' (C3 A9) and '©' (C2 A9) share their last byte
+        let (start, end, replacement) = compute_minimal_text_edit("é", "©");
+        assert_eq!((start, end, replacement), (0, 2, "©"));
+    }
+
+    #[test]
+    fn test_edit_does_not_split_grapheme_cluster() {
+        // 👨‍👩‍👧 and 👨‍👩‍👦 are ZWJ sequences which only differ in their last code point
+        let (start, end, replacement) = compute_minimal_text_edit("a👨‍👩‍👧b", "a👨‍👩‍👦b");
+        assert_eq!((start, end, replacement), (1, 19, "👨‍👩‍👦"));
+
+        // Differ in a code point in the middle of the cluster
+        let (start, end, replacement) = compute_minimal_text_edit("👨‍👩‍👧", "👨‍👦‍👧");
+        assert_eq!((start, end, replacement), (0, 18, "👨‍👦‍👧"));
+
+        // 😀 (F0 9F 98 80) and 😁 (F0 9F 98 81) only differ in their last byte
+        let (start, end, replacement) = compute_minimal_text_edit("a😀", "b😁");
+        assert_eq!((start, end, replacement), (0, 5, "b😁"));
+        let (start, end, replacement) = compute_minimal_text_edit("a;😀", "a😁");
+        assert_eq!((start, end, replacement), (1, 6, "😁"));
+
+        // The common prefix `ae` ends inside the cluster `é` of the formatted text only
+        let (start, end, replacement) = compute_minimal_text_edit("ae", "ae\u{301}");
+        assert_eq!((start, end, replacement), (1, 2, "e\u{301}"));
+        let (start, end, replacement) = compute_minimal_text_edit("e\u{301}b", "eb");
+        assert_eq!((start, end, replacement), (0, 3, "e"));
+    }
+
+    #[test]
+    fn test_build_text_edits_single_hunk() {
+        let src = "a\nb\n";
+        let formatted = "a;\nb;\n";
+        let edits = build_text_edits(src, formatted);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new(Position::new(0, 1), Position::new(1, 1)));
+        assert_eq!(edits[0].new_text, ";\nb;");
+    }
+
+    #[test]
+    fn test_build_text_edits_multiple_hunks() {
+        let src = (0..100).map(|i| format!("line{i}\n")).collect::<Vec<_>>().concat();
+        let formatted = (0..100)
+            .map(|i| if i == 0 || i == 99 { format!("line{i};\n") } else { format!("line{i}\n") })
+            .collect::<String>();
+
+        let edits = build_text_edits(&src, &formatted);
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range, Range::new(Position::new(0, 5), Position::new(0, 5)));
+        assert_eq!(edits[0].new_text, ";");
+        assert_eq!(edits[1].range, Range::new(Position::new(99, 6), Position::new(99, 6)));
+        assert_eq!(edits[1].new_text, ";");
+    }
+
+    #[test]
+    #[expect(clippy::cast_possible_truncation)]
+    fn test_position_cursor() {
+        let source_text = "a\r\nb😀\rc\u{2028}d\n\ne";
+        let rope = Rope::from(source_text);
+        let mut cursor = PositionCursor::new(source_text);
+        for offset in source_text.char_indices().map(|(i, _)| i).chain([source_text.len()]) {
+            let (line, character) = get_line_column(&rope, offset as u32, source_text);
+            assert_eq!(cursor.position(offset as u32), Position::new(line, character), "{offset}");
+        }
+    }
+
+    #[test]
+    #[expect(clippy::cast_possible_truncation)]
+    fn test_position_cursor_offset() {
+        let source_text = "a\r\nb😀\rc\u{2028}d\n\ne";
+        let mut positions = PositionCursor::new(source_text);
+        let mut offsets = PositionCursor::new(source_text);
+        for offset in source_text.char_indices().map(|(i, _)| i).chain([source_text.len()]) {
+            let position = positions.position(offset as u32);
+            assert_eq!(offsets.offset(position), offset as u32, "{position:?}");
+        }
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        // Many lines apart, so each hunk gets its own edit
+        let lines = (0..100).map(|i| format!("line{i}\n")).collect::<Vec<_>>();
+        let multiple_hunks = lines.concat();
+        let formatted_hunks = lines[1..99].concat() + "line99;\n";
+        for (src, formatted) in [
+            ("a\nb\n", "a;\nb;\n"),
+            ("abc", "abc"),
+            ("a\r\nb😀\rc", "a;\r\n\r\nb 😀\rc;"),
+            (&multiple_hunks, &formatted_hunks),
+        ] {
+            assert_eq!(apply_text_edits(src, &build_text_edits(src, formatted)), formatted);
+        }
+    }
+
+    #[test]
+    fn test_build_text_edits_no_change() {
+        assert!(build_text_edits("abc", "abc").is_empty());
+    }
+
+    #[test]
+    fn test_format_to_diff() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        let header = format!(
+            "--- {path}\n+++ {path}\n",
+            path = get_file_path("test/fixtures/lsp/basic/basic.ts").display()
+        );
+        let content = "const a = 1;\nconst b   =   2;\nconst c = 3;\n";
+
+        assert_eq!(
+            formatter.format_to_diff(&uri, Some(content), 0),
+            Some(format!("{header}@@ -2 +2 @@\n-const b   =   2;\n+const b = 2;\n"))
+        );
+        assert_eq!(
+            formatter.format_to_diff(&uri, Some(content), 1),
+            Some(format!(
+                "{header}@@ -1,3 +1,3 @@\n const a = 1;\n-const b   =   2;\n+const b = 2;\n const c = 3;\n"
+            ))
+        );
+        // Already formatted
+        assert_eq!(formatter.format_to_diff(&uri, Some("const a = 1;\n"), 3), Some(String::new()));
+        // Parse errors
+        assert_eq!(formatter.format_to_diff(&uri, Some("const a = ;\n"), 3), None);
+    }
+
+    #[test]
+    fn test_run_format_changed() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        let content = Some("const a   =   1;\nconst b = 2;\nconst c   =   3;\n");
+        let line = |line| Range::new(Position::new(line, 0), Position::new(line, 0));
+
+        // Only the hunk of the changed line is formatted, even if other hunks are close
+        let edits = formatter.run_format_changed(&uri, content, &[line(2)]).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start.line, 2);
+
+        let edits = formatter.run_format_changed(&uri, content, &[line(0), line(2)]).unwrap();
+        assert_eq!(edits.len(), 2);
+
+        assert_eq!(formatter.run_format_changed(&uri, content, &[line(1)]), Some(vec![]));
+        assert_eq!(formatter.run_format_changed(&uri, content, &[]), Some(vec![]));
+    }
+}
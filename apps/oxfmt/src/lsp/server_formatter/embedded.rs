@@ -0,0 +1,308 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use log::debug;
+use oxc_ast::ast::{Expression, Program, TaggedTemplateExpression};
+use oxc_ast_visit::{Visit, walk};
+use oxc_data_structures::rope::Rope;
+use oxc_formatter::EmbeddedFormatter;
+use oxc_language_server::utils::position_to_offset;
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, Span};
+use tower_lsp_server::ls_types::{FormattingOptions, Range, TextEdit, Uri};
+
+use super::{
+    ServerFormatter,
+    diff::{compute_minimal_text_edit, create_text_edit},
+};
+use crate::core::{FormatFileStrategy, ResolvedOptions};
+
+impl ServerFormatter {
+    /// Formats only the code embedded in the tagged template containing `range`, e.g. CSS in `` css`...` ``,
+    /// with the external formatter. The edit replaces the contents between the backticks,
+    /// as formatting the whole file would.
+    ///
+    /// Falls back to [`Self::run_format_range`] if the range is not inside a single tagged template,
+    /// or the template can not be formatted as embedded code, e.g. it has substitutions or an unsupported tag.
+    pub fn run_format_embedded_range(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+
+        let FormatFileStrategy::OxcFormatter { source_type, .. } = &strategy else {
+            return None;
+        };
+        let source_type = *source_type;
+        if !self.formats_embedded_code(&strategy) {
+            return self.format_range(uri, range, content, options, is_cancelled);
+        }
+
+        let allocator = self.allocator_pool.get();
+        let ret = Parser::new(&allocator, &source_text, source_type)
+            .with_options(self.parse_options)
+            .parse();
+
+        if !ret.errors.is_empty() {
+            return None;
+        }
+
+        let rope = Rope::from(&*source_text);
+        let range_start = u32::try_from(position_to_offset(&source_text, range.start)).ok()?;
+        let range_end = u32::try_from(position_to_offset(&source_text, range.end)).ok()?;
+
+        // Templates can only be nested in substitutions, so the last one containing the range is the innermost
+        let source_templates = get_tagged_templates(&ret.program, &source_text);
+        let Some(index) = source_templates.iter().rposition(|(span, tag_name)| {
+            span.start <= range_start && range_end <= span.end && tag_name.is_some()
+        }) else {
+            return self.format_range(uri, range, content, options, is_cancelled);
+        };
+
+        // Same as `run_format_range()`, the whole file is formatted as by `run_format()`,
+        // and the N-th template of the source is the N-th template of the formatted code
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+        let code = self.format_cached(&path, &strategy, &source_text, indent, is_cancelled)?;
+        let formatted_allocator = self.allocator_pool.get();
+        let formatted_ret = Parser::new(&formatted_allocator, &code, source_type)
+            .with_options(self.parse_options)
+            .parse();
+        let formatted_templates = get_tagged_templates(&formatted_ret.program, &code);
+
+        if !formatted_ret.errors.is_empty() || source_templates.len() != formatted_templates.len() {
+            return self.format_range(uri, range, content, options, is_cancelled);
+        }
+
+        let content_span = source_templates[index].0;
+        let source_slice = content_span.source_text(&source_text);
+        let formatted_slice = formatted_templates[index].0.source_text(&code);
+
+        if source_slice == formatted_slice {
+            return Some(vec![]);
+        }
+
+        let (start, end, replacement) = compute_minimal_text_edit(source_slice, formatted_slice);
+        Some(vec![create_text_edit(
+            &rope,
+            &source_text,
+            content_span.start + start,
+            content_span.start + end,
+            replacement,
+        )])
+    }
+
+    /// Whether the code embedded in the tagged templates of the JS/TS file is formatted,
+    /// see [`Self::embedded_formatter`].
+    fn formats_embedded_code(&self, strategy: &FormatFileStrategy) -> bool {
+        self.external.capabilities.supports_embedded
+            && self.external_bridge().is_some()
+            && matches!(
+                self.resolve_options(strategy, None),
+                ResolvedOptions::OxcFormatter { format_options, .. }
+                    if !format_options.embedded_language_formatting.is_off()
+            )
+    }
+
+    /// Create the formatter for code embedded in tagged template literals, e.g. CSS in `` css`...` ``.
+    /// Returns `None` if the external formatter is not available or does not support embedded code.
+    pub(super) fn embedded_formatter(
+        &self,
+        external_options: serde_json::Value,
+    ) -> Option<EmbeddedFormatter> {
+        if !self.external.capabilities.supports_embedded {
+            return None;
+        }
+        let external_bridge = Arc::clone(self.external_bridge()?);
+        let timeout = self.settings.external_timeout;
+        let external_options = Arc::new(external_options);
+
+        Some(EmbeddedFormatter::new(Arc::new(move |tag_name: &str, code: &str| {
+            external_bridge.format_embedded(&external_options, tag_name, code, timeout).inspect_err(
+                |err| debug!("Failed to format embedded code with tag '{tag_name}': {err}"),
+            )
+        })))
+    }
+}
+
+/// Returns the span between the backticks of each tagged template, in source order,
+/// with the tag name if its contents can be formatted as embedded code.
+fn get_tagged_templates<'s>(
+    program: &Program<'_>,
+    source_text: &'s str,
+) -> Vec<(Span, Option<&'s str>)> {
+    struct TaggedTemplateCollector<'s> {
+        source_text: &'s str,
+        templates: Vec<(Span, Option<&'s str>)>,
+    }
+
+    impl<'a> Visit<'a> for TaggedTemplateCollector<'_> {
+        fn visit_tagged_template_expression(&mut self, expr: &TaggedTemplateExpression<'a>) {
+            let span = expr.quasi.span;
+            let tag_name = match &expr.tag {
+                Expression::Identifier(_) | Expression::StaticMemberExpression(_) => {
+                    Some(expr.tag.span().source_text(self.source_text))
+                }
+                _ => None,
+            }
+            .map(EmbeddedFormatter::resolve_tag_alias)
+            .filter(|tag_name| {
+                expr.quasi.is_no_substitution_template()
+                    && EmbeddedFormatter::is_supported_tag(tag_name)
+            });
+            self.templates.push((Span::new(span.start + 1, span.end - 1), tag_name));
+            walk::walk_tagged_template_expression(self, expr);
+        }
+    }
+
+    let mut collector = TaggedTemplateCollector { source_text, templates: vec![] };
+    collector.visit_program(program);
+    collector.templates
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::AtomicBool};
+
+    use cow_utils::CowUtils;
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{Position, Range, TextEdit};
+
+    use crate::{
+        core::{ConfigResolver, FormatFileStrategy},
+        lsp::{
+            BridgeCapabilities, ServerFormatter, ServerFormatterBuilder, ServerFormatterConfig,
+            SharedExternalFormatter,
+            tester::{TestBridge, Tester, get_file_path, get_file_uri},
+        },
+    };
+
+    #[test]
+    fn test_embedded_formatted_by_external_formatter() {
+        fn format(config: serde_json::Value) -> (Vec<TextEdit>, Vec<String>) {
+            let bridge = Arc::new(
+                TestBridge::default()
+                    .with_format_embedded(|code| code.cow_replace(':', ": ").into_owned()),
+            );
+            let mut config_resolver = ConfigResolver::from_value(config);
+            config_resolver.build_and_validate().expect("config should be valid");
+            let formatter = ServerFormatter::new(
+                config_resolver,
+                ServerFormatterConfig {
+                    external: SharedExternalFormatter {
+                        bridge: Some(Arc::clone(&bridge) as _),
+                        capabilities: BridgeCapabilities {
+                            supports_embedded: true,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            );
+            let edits = formatter
+                .run_format(
+                    &get_file_uri("test/fixtures/lsp/basic/basic.ts"),
+                    Some("const a = css`color:red;`;\nconst b = styled.div`color:red;`;\nconst c = foo`color:red;`;\n"),
+                    None,
+                    &AtomicBool::new(false),
+                )
+                .expect("file should be formatted");
+            (edits, bridge.take_tag_names())
+        }
+
+        let (edits, tag_names) = format(json!({ "embeddedLanguageFormatting": "auto" }));
+        assert!(edits.iter().all(|edit| edit.new_text.contains("color: red;")));
+        // Unsupported tags are not passed to the external formatter
+        assert_eq!(tag_names, vec!["css".to_string(), "styled.div".to_string()]);
+
+        // Embedded formatting is turned off by default
+        let (_, tag_names) = format(json!({}));
+        assert!(tag_names.is_empty());
+    }
+
+    /// Breaks the lines between the tags of embedded HTML.
+    fn html_bridge() -> TestBridge {
+        TestBridge::default()
+            .with_format_embedded(|code| code.cow_replace("><", ">\n<").into_owned())
+    }
+
+    #[test]
+    fn test_lit_html_templates() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/lit");
+        let path = get_file_path("test/fixtures/lsp/lit/my-element.ts");
+        let source = std::fs::read_to_string(&path).unwrap();
+        let strategy = FormatFileStrategy::try_from(path).unwrap();
+
+        let bridge = Arc::new(html_bridge());
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _)).build(&root_uri, json!({}));
+        let code = formatter.format(&strategy, &source, None, &AtomicBool::new(false)).unwrap();
+        // `svg` is formatted as HTML too
+        assert_eq!(bridge.take_tag_names(), vec!["html".to_string(), "html".to_string()]);
+        assert!(
+            code.contains("return html`\n      <div>\n      <p>Hello</p>\n      </div>\n    `;")
+        );
+        assert!(code.contains("return svg`\n      <circle r=\"1\"/>\n    `;"));
+
+        // Left untouched without the external formatter
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        assert_eq!(formatter.format(&strategy, &source, None, &AtomicBool::new(false)), Ok(source));
+    }
+
+    #[test]
+    fn test_format_embedded_range() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/lit");
+        let uri = get_file_uri("test/fixtures/lsp/lit/my-element.ts");
+        let bridge = Arc::new(html_bridge());
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _)).build(&root_uri, json!({}));
+
+        // Only the contents of the template around the range are replaced
+        let range = Range::new(Position::new(4, 20), Position::new(4, 25));
+        assert_eq!(
+            formatter.run_format_embedded_range(&uri, range, None, None, &AtomicBool::new(false)),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(4, 16), Position::new(4, 39)),
+                "\n      <div>\n      <p>Hello</p>\n      </div>\n    ".into()
+            )])
+        );
+        // The whole file is formatted, as `run_format()` would
+        assert_eq!(
+            bridge.take_embedded_codes(),
+            vec!["<div><p>Hello</p></div>".to_string(), "<circle r=\"1\"/>".to_string()]
+        );
+
+        // Ranges spanning multiple templates or code outside of them are formatted as usual
+        let range = Range::new(Position::new(4, 20), Position::new(8, 20));
+        let edits =
+            formatter.run_format_embedded_range(&uri, range, None, None, &AtomicBool::new(false));
+        assert_eq!(edits, formatter.run_format_range(&uri, range, None, None));
+        assert!(edits.is_some_and(|edits| !edits.is_empty()));
+        let range = Range::new(Position::new(0, 0), Position::new(0, 10));
+        assert_eq!(
+            formatter.run_format_embedded_range(&uri, range, None, None, &AtomicBool::new(false)),
+            Some(vec![])
+        );
+
+        // Templates with substitutions are not embedded code
+        bridge.take_calls();
+        let content = "const a = html`<div>${b}</div>`;\n";
+        let range = Range::new(Position::new(0, 16), Position::new(0, 17));
+        assert_eq!(
+            formatter.run_format_embedded_range(
+                &uri,
+                range,
+                Some(content),
+                None,
+                &AtomicBool::new(false)
+            ),
+            Some(vec![])
+        );
+        assert!(bridge.take_embedded_codes().is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use cow_utils::CowUtils;
+use ignore::{
+    Match,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+
+use super::{BOM, ServerFormatter, ServerFormatterBuilder};
+use crate::core::{dedup_ignore_patterns, utils};
+
+impl ServerFormatterBuilder {
+    pub(super) fn create_ignore_globs(
+        root_path: &Path,
+        ignore_patterns: &[String],
+    ) -> Result<Gitignore, String> {
+        // All ignore files are in the root, so their lines can be merged with the config patterns,
+        // then a pattern listed in e.g. both `.gitignore` and the config is only matched once.
+        let mut lines = vec![];
+        for ignore_path in load_ignore_paths(root_path) {
+            let content = utils::read_to_string(&ignore_path)
+                .map_err(|_| format!("Failed to add ignore file: {}", ignore_path.display()))?;
+            let content = content.strip_prefix(BOM).unwrap_or(&content);
+            lines.extend(content.lines().map(|line| (Some(ignore_path.clone()), line.to_string())));
+        }
+        lines.extend(ignore_patterns.iter().map(|pattern| (None, pattern.clone())));
+
+        let mut builder = GitignoreBuilder::new(normalize_windows_path(root_path));
+        for (ignore_path, line) in dedup_ignore_patterns(lines, |(_, line)| line) {
+            builder.add_line(ignore_path.clone(), &line).map_err(|e| match ignore_path {
+                Some(ignore_path) => {
+                    format!("Failed to add ignore file: {}: {e}", ignore_path.display())
+                }
+                None => format!("Invalid ignore pattern: {line}: {e}"),
+            })?;
+        }
+
+        builder.build().map_err(|_| "Failed to build ignore globs".to_string())
+    }
+
+    /// Build the allowlist of `fmt.includePatterns`, same syntax as the ignore patterns.
+    /// Returns `None` if no pattern is configured, then every file is included.
+    pub(super) fn create_include_globs(
+        root_path: &Path,
+        include_patterns: &[String],
+    ) -> Result<Option<Gitignore>, String> {
+        if include_patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = GitignoreBuilder::new(normalize_windows_path(root_path));
+        for pattern in include_patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| format!("Invalid include pattern: {pattern}: {e}"))?;
+        }
+
+        builder.build().map(Some).map_err(|_| "Failed to build include globs".to_string())
+    }
+}
+
+impl ServerFormatter {
+    /// Whether the file or directory should not be formatted.
+    ///
+    /// A file is formatted only if it matches the include patterns, when configured,
+    /// and does not match the ignore patterns. Ignore patterns always take precedence.
+    pub(super) fn is_ignored(&self, path: &Path) -> bool {
+        let path = normalize_windows_path(path);
+        let path = path.as_ref();
+
+        if let Some(include_glob) = &self.include_glob
+            && !path.is_dir()
+            && !(path.starts_with(include_glob.path())
+                && include_glob.matched_path_or_any_parents(path, false).is_ignore())
+        {
+            return true;
+        }
+
+        if let Some(glob) = &self.gitignore_glob {
+            if !path.starts_with(glob.path()) {
+                return false;
+            }
+
+            // Patterns matching the path itself take precedence over its parent directories,
+            // e.g. `!src/generated.ts`, so they are never cached.
+            match glob.matched(path, path.is_dir()) {
+                Match::None => {}
+                matched => return matched.is_ignore(),
+            }
+
+            path.parent()
+                .filter(|dir| dir.starts_with(glob.path()))
+                .is_some_and(|dir| self.is_dir_ignored(glob, dir))
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if the directory or one of its ancestors is ignored.
+    /// Same as [`Gitignore::matched_path_or_any_parents`], but cached per directory.
+    fn is_dir_ignored(&self, glob: &Gitignore, dir: &Path) -> bool {
+        let ignored_dirs = self.ignored_dirs.pin();
+        if let Some(ignored) = ignored_dirs.get(dir) {
+            return *ignored;
+        }
+
+        let ignored = glob.matched_path_or_any_parents(dir, true).is_ignore();
+        ignored_dirs.insert(dir.to_path_buf(), ignored);
+        ignored
+    }
+}
+
+/// On Windows, the file paths of the URIs sent by the client and the workspace root
+/// can disagree on the drive letter casing, the verbatim `\\?\` prefix or the separators,
+/// then [`Path::starts_with`] fails for a file inside the workspace.
+/// Returns the path with the prefix stripped, an uppercase drive letter and `\` separators.
+///
+/// Other platforms return the path as is.
+pub(super) fn normalize_windows_path(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    match path.to_str() {
+        Some(path) => Cow::Owned(PathBuf::from(normalize_windows_path_str(path))),
+        None => Cow::Borrowed(path),
+    }
+}
+
+pub(super) fn normalize_windows_path_str(path: &str) -> String {
+    let path = path.cow_replace('/', "\\");
+    let mut path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(rest) => format!(r"\\{rest}"),
+        None => path.strip_prefix(r"\\?\").unwrap_or(&path).to_string(),
+    };
+    if path.as_bytes().get(1) == Some(&b':')
+        && let Some(drive_letter) = path.get_mut(..1)
+    {
+        drive_letter.make_ascii_uppercase();
+    }
+    path
+}
+
+pub(super) const IGNORE_FILES: [&str; 3] = [".gitignore", ".prettierignore", ".oxfmtignore"];
+
+// Almost the same as `oxfmt::walk::load_ignore_paths`, but does not handle custom ignore files.
+// The order matters, patterns of later files take precedence,
+// so `.oxfmtignore` can re-include files ignored by `.gitignore` or `.prettierignore`.
+pub(super) fn load_ignore_paths(cwd: &Path) -> Vec<PathBuf> {
+    IGNORE_FILES
+        .iter()
+        .filter_map(|file_name| {
+            let path = cwd.join(file_name);
+            if path.exists() { Some(path) } else { None }
+        })
+        .collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+
+    use super::normalize_windows_path_str;
+    use crate::lsp::{
+        ServerFormatterBuilder,
+        tester::{Tester, get_file_path, get_file_uri},
+    };
+
+    #[test]
+    fn test_ignore_files() {
+        Tester::new(
+            "test/fixtures/lsp/ignore-file",
+            json!({
+                "fmt.experimental": true
+            }),
+        )
+        .format_and_snapshot_multiple_file(&["ignored.ts", "not-ignored.js"]);
+    }
+
+    #[test]
+    fn test_oxfmtignore() {
+        Tester::new("test/fixtures/lsp/oxfmtignore", json!({})).format_and_snapshot_multiple_file(
+            &["excluded.js", "ignored.generated.ts", "kept.generated.ts"],
+        );
+    }
+
+    #[test]
+    fn test_ignore_directory_pattern() {
+        // `build/` only matches directories, so the files are ignored through their ancestors
+        let tester = Tester::new("test/fixtures/lsp/ignore-dir", json!({}));
+        assert!(tester.format("build/x/y/deep.ts", None).is_none());
+        assert!(tester.format("src/build/nested.ts", None).is_none());
+        assert!(tester.format("src/kept.ts", None).is_some());
+    }
+
+    #[test]
+    fn test_watch_ignore_files() {
+        let root_dir = "test/fixtures/lsp/oxfmtignore";
+        let root_uri = Tester::get_root_uri(root_dir);
+        let builder = ServerFormatterBuilder::new(None);
+        let formatter = builder.build(&root_uri, json!({}));
+
+        // Only the ignore files found in the root are watched
+        let patterns = formatter.get_watcher_patterns(json!({}));
+        assert_eq!(patterns[5..], [".gitignore".to_string(), ".oxfmtignore".to_string()]);
+        let patterns = formatter.get_watcher_patterns(json!({ "fmt.configPath": "format.json" }));
+        assert_eq!(patterns, ["format.json", ".gitignore", ".oxfmtignore"]);
+
+        // Touch `.gitignore` after the formatter was built
+        let gitignore = get_file_path(root_dir).join(".gitignore");
+        std::fs::File::options()
+            .write(true)
+            .open(&gitignore)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .unwrap();
+        let changes = formatter.handle_watched_file_change(
+            &builder,
+            &get_file_uri(&format!("{root_dir}/.gitignore")),
+            &root_uri,
+            json!({}),
+        );
+        assert!(changes.tool.is_some());
+    }
+
+    #[test]
+    fn test_is_ignored_cache() {
+        let root_dir = "test/fixtures/lsp/oxfmtignore";
+        let formatter =
+            ServerFormatterBuilder::new(None).build(&Tester::get_root_uri(root_dir), json!({}));
+        let root_path = get_file_path(root_dir);
+
+        // The second round is answered from the cache
+        for _ in 0..2 {
+            assert!(formatter.is_ignored(&root_path.join("excluded.js")));
+            assert!(formatter.is_ignored(&root_path.join("ignored.generated.ts")));
+            assert!(!formatter.is_ignored(&root_path.join("kept.generated.ts")));
+            assert!(!formatter.is_ignored(&root_path.join("not-existing.ts")));
+        }
+        assert_eq!(formatter.ignored_dirs.pin().len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_windows_path_str() {
+        assert_eq!(normalize_windows_path_str(r"c:\repo\a.ts"), r"C:\repo\a.ts");
+        assert_eq!(normalize_windows_path_str("c:/repo/a.ts"), r"C:\repo\a.ts");
+        assert_eq!(normalize_windows_path_str(r"\\?\c:\repo\a.ts"), r"C:\repo\a.ts");
+        assert_eq!(
+            normalize_windows_path_str(r"\\?\UNC\server\share\a.ts"),
+            r"\\server\share\a.ts"
+        );
+        assert_eq!(normalize_windows_path_str(r"\\server\share\a.ts"), r"\\server\share\a.ts");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_is_ignored_with_different_drive_letter_case() {
+        let root_dir = "test/fixtures/lsp/oxfmtignore";
+        let formatter =
+            ServerFormatterBuilder::new(None).build(&Tester::get_root_uri(root_dir), json!({}));
+        let path = get_file_path(root_dir).join("excluded.js");
+
+        let path = path.to_str().unwrap();
+        let lowercase = PathBuf::from(format!("{}{}", path[..1].to_ascii_lowercase(), &path[1..]));
+        let uppercase = PathBuf::from(format!("{}{}", path[..1].to_ascii_uppercase(), &path[1..]));
+        assert!(formatter.is_ignored(&lowercase));
+        assert!(formatter.is_ignored(&uppercase));
+        assert!(formatter.is_ignored(&PathBuf::from(format!(r"\\?\{}", uppercase.display()))));
+    }
+
+    #[test]
+    fn test_include_patterns() {
+        let root_dir = "test/fixtures/lsp/oxfmtignore";
+        let formatter = ServerFormatterBuilder::new(None).build(
+            &Tester::get_root_uri(root_dir),
+            json!({ "fmt.includePatterns": ["excluded.js", "*.generated.ts"] }),
+        );
+        let root_path = get_file_path(root_dir);
+
+        assert!(!formatter.is_ignored(&root_path.join("kept.generated.ts")));
+        // Not included
+        assert!(formatter.is_ignored(&root_path.join("not-included.js")));
+        // Included, but ignored
+        assert!(formatter.is_ignored(&root_path.join("excluded.js")));
+        assert!(formatter.is_ignored(&root_path.join("ignored.generated.ts")));
+        // Directories are still walked
+        assert!(!formatter.is_ignored(&root_path));
+    }
+
+    #[test]
+    fn test_ignore_pattern() {
+        Tester::new(
+            "test/fixtures/lsp/ignore-pattern",
+            json!({
+                "fmt.experimental": true
+            }),
+        )
+        .format_and_snapshot_multiple_file(&["ignored.ts", "not-ignored.js"]);
+    }
+}
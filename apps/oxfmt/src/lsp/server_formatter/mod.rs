@@ -0,0 +1,3431 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use cow_utils::CowUtils;
+use ignore::gitignore::Gitignore;
+use log::{debug, warn};
+use oxc_allocator::AllocatorPool;
+use oxc_ast::ast::{Program, Statement};
+use oxc_data_structures::rope::{Rope, get_line_column};
+use oxc_diagnostics::OxcDiagnostic;
+use oxc_formatter::{Formatter, get_parse_options, get_supported_source_type};
+use oxc_parser::{ParseOptions, Parser};
+use oxc_span::SourceType;
+use rustc_hash::FxHashSet;
+use serde_json::json;
+use tower_lsp_server::{
+    jsonrpc::ErrorCode,
+    ls_types::{
+        CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand,
+        CodeActionProviderCapability, Diagnostic, DiagnosticSeverity,
+        DocumentOnTypeFormattingOptions, ExecuteCommandOptions, FormattingOptions, Pattern,
+        Position, Range, ServerCapabilities, TextDocumentSyncCapability, TextEdit, Uri,
+        WorkDoneProgressOptions, WorkspaceEdit,
+    },
+};
+
+use crate::{
+    core::{
+        BUILTIN_EXTERNAL_PARSER_NAMES, ConfigResolver, FinalNewline, FormatFileStrategy,
+        ResolvedOptions, final_newline_count, format_json, has_significant_trailing_newlines,
+        utils,
+    },
+    lsp::{
+        external_formatter_bridge::{BridgeCapabilities, BridgeError, ExternalFormatterBridge},
+        format_cache::{FormatCache, ResolveCache, SourceCache},
+        options::FormatOptions as LSPFormatOptions,
+    },
+};
+
+use oxc_language_server::{
+    Capabilities, ConcurrentHashMap, {Tool, ToolBuilder, ToolRestartChanges},
+};
+
+mod code_actions;
+mod config;
+mod diff;
+mod embedded;
+mod globs;
+mod on_type;
+mod range;
+mod requests;
+mod workspace;
+
+use config::{config_file_names, find_config_path, resolve_config};
+use diff::{PositionCursor, apply_text_edits, build_text_edits};
+use globs::load_ignore_paths;
+
+/// Default timeout for a single call to the external formatter.
+const DEFAULT_EXTERNAL_TIMEOUT_MS: u64 = 5000;
+
+/// Default maximum size of a file to format, larger files are usually generated, e.g. bundled code.
+const DEFAULT_MAX_FILE_SIZE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// UTF-8 byte order mark.
+const BOM: char = '\u{feff}';
+
+/// Maximum number of formatting results kept in [`ServerFormatter::format_cache`].
+const FORMAT_CACHE_CAPACITY: usize = 256;
+
+/// Maximum number of files kept in [`ServerFormatter::source_cache`].
+const SOURCE_CACHE_CAPACITY: usize = 256;
+
+/// Maximum number of files kept in [`ServerFormatter::resolve_cache`].
+const RESOLVE_CACHE_CAPACITY: usize = 256;
+
+/// Command to format all files in the workspace.
+pub const FORMAT_WORKSPACE_COMMAND_ID: &str = "oxfmt.formatWorkspace";
+
+/// Code action formatting the selected range, for editors preferring code actions over range formatting.
+pub const FORMAT_SELECTION_CODE_ACTION_KIND: CodeActionKind =
+    CodeActionKind::new("oxfmt.formatSelection");
+
+/// Code action formatting the file with its imports sorted and deduplicated, see [`remove_duplicate_imports`].
+pub const ORGANIZE_IMPORTS_CODE_ACTION_KIND: CodeActionKind =
+    CodeActionKind::new("oxfmt.organizeImports");
+
+/// Custom request returning the languages the formatter handles,
+/// so editor extensions can decide whether to register oxfmt for a document.
+pub const SUPPORTED_LANGUAGES_REQUEST: &str = "oxfmt/supportedLanguages";
+
+/// Custom request returning how the config of a document is resolved,
+/// so editor extensions can show the effective config to users.
+pub const RESOLVE_CONFIG_REQUEST: &str = "oxfmt/resolveConfig";
+
+/// Custom request returning whether the external formatter responds and the languages it loaded,
+/// so users can tell whether their Prettier plugins are loaded at all.
+pub const BRIDGE_STATUS_REQUEST: &str = "oxfmt/bridgeStatus";
+
+/// Custom request returning the whole formatted text of a document, `null` if it is not formatted,
+/// so editor extensions can show what would change in a diff view.
+pub const PREVIEW_FORMATTED_REQUEST: &str = "oxfmt/previewFormatted";
+
+/// File extensions used to format untitled documents of each language id sent by clients.
+/// See <https://code.visualstudio.com/docs/languages/identifiers>
+const UNTITLED_LANGUAGE_EXTENSIONS: &[(&str, &str)] = &[
+    ("javascript", "js"),
+    ("javascriptreact", "jsx"),
+    ("typescript", "ts"),
+    ("typescriptreact", "tsx"),
+    ("json", "json"),
+    ("jsonc", "jsonc"),
+    ("json5", "json5"),
+    ("toml", "toml"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("less", "less"),
+    ("html", "html"),
+    ("vue", "vue"),
+    ("graphql", "graphql"),
+    ("handlebars", "hbs"),
+    ("markdown", "md"),
+    ("mdx", "mdx"),
+    ("yaml", "yaml"),
+];
+
+/// File extensions formatted by oxc itself, without the external formatter.
+const NATIVE_EXTENSIONS: &[&str] = &["json", "jsonc", "json5", "toml"];
+
+/// The external formatter is disabled for the rest of the session
+/// after failing this many times in a row, e.g. when the JS side crashed.
+const MAX_CONSECUTIVE_EXTERNAL_FAILURES: usize = 10;
+
+pub struct ServerFormatterBuilder {
+    /// Shared by all built formatters.
+    external: SharedExternalFormatter,
+    /// Hash of the content of the watched files when they last changed, shared by all built formatters,
+    /// see [`ServerFormatter::needs_rebuild`].
+    /// Keyed by the workspace root and the file, as each workspace rebuilds its own formatter.
+    watched_file_hashes: Arc<ConcurrentHashMap<(PathBuf, PathBuf), Option<u64>>>,
+    /// Whether the client supports `codeAction/resolve`, set with the server capabilities.
+    /// Shared by all built formatters, as the workspaces can be started before.
+    code_action_resolve: Arc<AtomicBool>,
+}
+
+impl ServerFormatterBuilder {
+    /// Create a new builder.
+    /// The external formatter is initialized once here and shared by all workspaces.
+    /// If it is `None` or fails to initialize, only files supported natively are formatted.
+    pub fn new(external_bridge: Option<Arc<dyn ExternalFormatterBridge>>) -> Self {
+        let external = match init_external_formatter(external_bridge) {
+            Some((bridge, languages, capabilities)) => SharedExternalFormatter {
+                bridge: Some(bridge),
+                languages,
+                capabilities,
+                failures: Arc::default(),
+            },
+            None => SharedExternalFormatter::default(),
+        };
+        Self { external, watched_file_hashes: Arc::default(), code_action_resolve: Arc::default() }
+    }
+
+    /// # Panics
+    /// Panics if the root URI cannot be converted to a file path.
+    pub fn build(&self, root_uri: &Uri, options: serde_json::Value) -> ServerFormatter {
+        let options = match serde_json::from_value::<LSPFormatOptions>(options) {
+            Ok(opts) => opts,
+            Err(err) => {
+                warn!(
+                    "Failed to deserialize LSPFormatOptions from JSON: {err}, falling back to default options"
+                );
+                LSPFormatOptions::default()
+            }
+        };
+
+        let root_path = root_uri.to_file_path().unwrap();
+        let (oxfmtrc_path, config_path_error) = match find_config_path(
+            &root_path,
+            options.config_path.as_ref(),
+            &options.config_file_names,
+            options.read_prettier_config,
+        ) {
+            Ok(oxfmtrc_path) => (oxfmtrc_path, None),
+            Err(err) => {
+                warn!("{err}");
+                (None, Some(err))
+            }
+        };
+        let is_disabled = options.require_config && oxfmtrc_path.is_none();
+        if oxfmtrc_path.is_none() {
+            let searched = options.config_path.clone().unwrap_or_else(|| {
+                config_file_names(&options.config_file_names, options.read_prettier_config)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+            warn!(
+                "Config file not found: {searched}, {}",
+                if is_disabled { "formatter is disabled" } else { "fallback to default config" }
+            );
+        }
+        // JS config files are only loaded by bridges supporting it
+        let config_bridge = self
+            .external
+            .bridge
+            .as_deref()
+            .filter(|_| self.external.capabilities.supports_config_load);
+        let (config_resolver, ignore_patterns, config_error) =
+            resolve_config(&root_path, oxfmtrc_path.as_deref(), config_bridge);
+        let config_error = config_error.or(config_path_error);
+
+        let gitignore_glob = match Self::create_ignore_globs(&root_path, &ignore_patterns) {
+            Ok(glob) => Some(glob),
+            Err(err) => {
+                warn!("Failed to create gitignore globs: {err}, proceeding without ignore globs");
+                None
+            }
+        };
+        let include_glob = match Self::create_include_globs(&root_path, &options.include_patterns) {
+            Ok(glob) => glob,
+            Err(err) => {
+                warn!("Failed to create include globs: {err}, proceeding without include globs");
+                None
+            }
+        };
+
+        ServerFormatter::new(
+            config_resolver,
+            ServerFormatterConfig {
+                root_path: root_path.to_path_buf(),
+                config_error,
+                config_path: oxfmtrc_path,
+                gitignore_glob,
+                include_glob,
+                external: self.external.clone(),
+                watched_file_hashes: Arc::clone(&self.watched_file_hashes),
+                code_action_resolve: Arc::clone(&self.code_action_resolve),
+                settings: FormatSettings::from(options),
+                is_disabled,
+            },
+        )
+    }
+}
+
+impl ToolBuilder for ServerFormatterBuilder {
+    fn server_capabilities(
+        &self,
+        capabilities: &mut ServerCapabilities,
+        backend_capabilities: &Capabilities,
+    ) {
+        capabilities.document_formatting_provider =
+            Some(tower_lsp_server::ls_types::OneOf::Left(true));
+        capabilities.document_range_formatting_provider =
+            Some(tower_lsp_server::ls_types::OneOf::Left(true));
+        capabilities.document_on_type_formatting_provider = Some(DocumentOnTypeFormattingOptions {
+            first_trigger_character: "}".to_string(),
+            more_trigger_character: Some(vec![";".to_string(), "\n".to_string(), ">".to_string()]),
+        });
+        // Format on save for clients that do not send `textDocument/formatting` themselves
+        if let Some(TextDocumentSyncCapability::Options(sync_options)) =
+            &mut capabilities.text_document_sync
+        {
+            sync_options.will_save_wait_until = Some(true);
+        }
+        // Format on paste for clients supporting the proposed `textDocument/documentPaste` request
+        let mut experimental = capabilities.experimental.take().unwrap_or_else(|| json!({}));
+        if let Some(experimental) = experimental.as_object_mut() {
+            experimental.insert("documentPasteProvider".to_string(), json!(true));
+            // `ls_types` has no `rangesSupport` of `DocumentRangeFormattingOptions` yet (LSP 3.18),
+            // so `textDocument/rangesFormatting` is advertised as experimental with the same shape
+            experimental.insert(
+                "documentRangeFormattingProvider".to_string(),
+                json!({ "rangesSupport": true }),
+            );
+        }
+        capabilities.experimental = Some(experimental);
+
+        // Keep the code action kinds and options of other tools, e.g. the linter
+        let code_action_options = match &capabilities.code_action_provider {
+            Some(CodeActionProviderCapability::Options(options)) => options.clone(),
+            _ => CodeActionOptions::default(),
+        };
+        let mut code_action_kinds = code_action_options.code_action_kinds.unwrap_or_default();
+        for kind in [FORMAT_SELECTION_CODE_ACTION_KIND, ORGANIZE_IMPORTS_CODE_ACTION_KIND] {
+            if !code_action_kinds.contains(&kind) {
+                code_action_kinds.push(kind);
+            }
+        }
+        // The edits of the code actions are computed when one is chosen, if the client supports it
+        self.code_action_resolve.store(backend_capabilities.code_action_resolve, Ordering::Relaxed);
+        let resolve_provider = code_action_options.resolve_provider.unwrap_or_default()
+            || backend_capabilities.code_action_resolve;
+        capabilities.code_action_provider =
+            Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(code_action_kinds),
+                resolve_provider: resolve_provider.then_some(true),
+                ..code_action_options
+            }));
+
+        let mut commands = capabilities
+            .execute_command_provider
+            .as_ref()
+            .map_or(vec![], |opts| opts.commands.clone());
+        if !commands.contains(&FORMAT_WORKSPACE_COMMAND_ID.to_string()) {
+            commands.push(FORMAT_WORKSPACE_COMMAND_ID.to_string());
+        }
+        capabilities.execute_command_provider = Some(ExecuteCommandOptions {
+            commands,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: capabilities
+                    .execute_command_provider
+                    .as_ref()
+                    .and_then(|provider| provider.work_done_progress_options.work_done_progress),
+            },
+        });
+    }
+    fn build_boxed(&self, root_uri: &Uri, options: serde_json::Value) -> Box<dyn Tool> {
+        Box::new(self.build(root_uri, options))
+    }
+
+    fn custom_request_methods(&self) -> &'static [&'static str] {
+        &[SUPPORTED_LANGUAGES_REQUEST, BRIDGE_STATUS_REQUEST]
+    }
+
+    fn custom_text_document_request_methods(&self) -> &'static [&'static str] {
+        &[RESOLVE_CONFIG_REQUEST, PREVIEW_FORMATTED_REQUEST]
+    }
+}
+
+/// Timings and sizes of a single format, see [`ServerFormatter::run_format_with_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FormatStats {
+    /// Time spent parsing, only measured for JS/TS files.
+    pub parse_micros: u64,
+    /// Time spent formatting, including the round trip to the external formatter.
+    pub format_micros: u64,
+    /// Length of the source text in bytes.
+    pub bytes_in: usize,
+    /// Length of the formatted code in bytes.
+    pub bytes_out: usize,
+    /// Number of text edits returned.
+    pub edit_count: usize,
+}
+
+/// Result of [`ServerFormatter::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckResult {
+    /// The file is already formatted.
+    Formatted,
+    /// Formatting the file would change it.
+    NeedsFormatting,
+    /// The file is ignored by the ignore files, the config, or the formatter is disabled.
+    Ignored,
+    /// The file could not be formatted, e.g. it has syntax errors.
+    ParseError,
+    /// The file is not supported, e.g. unknown extension or no external formatter for it,
+    /// or the external formatter failed to format it.
+    Skipped,
+}
+
+/// Result of [`ServerFormatter::run_format_result`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatOutcome {
+    /// The edits formatting the file.
+    Edits(Vec<TextEdit>),
+    /// The file is already formatted.
+    Unchanged,
+}
+
+/// Why [`ServerFormatter::run_format_result`] did not format a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The URI is not a file path.
+    InvalidUri,
+    /// The formatter is disabled, no config file found.
+    Disabled,
+    /// The file is ignored by the ignore files or the config.
+    Ignored,
+    /// The file type is not supported, e.g. unknown extension.
+    Unsupported,
+    /// The file needs the external formatter, but it is not available or does not support the parser.
+    ExternalFormatterUnavailable,
+    /// The file is larger than `fmt.maxFileSizeBytes`.
+    TooLarge,
+    /// The file could not be read.
+    ReadFailed(String),
+    /// The file is run by an interpreter of `fmt.skipShebangInterpreters`.
+    SkippedInterpreter(String),
+    /// The file could not be parsed, e.g. it has syntax errors.
+    ParseError,
+    /// The external formatter failed or timed out.
+    ExternalFormatterFailed(String),
+    /// The request was cancelled, e.g. the content changed in the meantime.
+    Cancelled,
+}
+
+/// The external formatter, initialized once by [`ServerFormatterBuilder::new`]
+/// and shared by all formatters it builds.
+///
+/// The default has no external formatter, so only files supported natively are formatted.
+#[derive(Clone, Default)]
+pub struct SharedExternalFormatter {
+    pub bridge: Option<Arc<dyn ExternalFormatterBridge>>,
+    /// Parser names the external formatter supports, empty if it is not available.
+    pub languages: FxHashSet<String>,
+    /// Optional features of the external formatter, e.g. whether it formats embedded code.
+    pub capabilities: BridgeCapabilities,
+    /// Consecutive failures of the external formatter, see [`MAX_CONSECUTIVE_EXTERNAL_FAILURES`].
+    /// Clones share the same count.
+    pub failures: Arc<AtomicUsize>,
+}
+
+/// Settings sent by the client, see [`LSPFormatOptions`].
+/// The options looking up the config file and the include patterns are not part of it.
+pub struct FormatSettings {
+    pub external_timeout: Duration,
+    /// Files larger than this are not formatted, so editors do not freeze on e.g. build artifacts.
+    pub max_file_size: u64,
+    /// Whether to format the formatted code again and warn if the output changes.
+    pub verify_idempotent: bool,
+    /// Whether to publish the parse errors of JS/TS files as diagnostics.
+    pub publish_parse_errors: bool,
+    /// Whether to use the indentation of the source text, if the config does not set it.
+    pub detect_indentation: bool,
+    /// JS/TS files with a shebang naming one of these interpreters are not formatted,
+    /// e.g. `gjs` for scripts of another runtime using the `.js` extension.
+    pub skip_shebang_interpreters: Vec<String>,
+    /// Whether to sort and deduplicate the imports of JS/TS files when formatting.
+    pub organize_imports: bool,
+    /// Line width sent by the client, used if the config does not set it.
+    pub print_width: Option<u16>,
+    /// Maximum number of consecutive blank lines kept in JS/TS files, sent by the client.
+    pub max_preserved_blank_lines: Option<u8>,
+    /// Whether to format the top-level statements around the parse errors of JS/TS files.
+    pub format_valid_regions: bool,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self::from(LSPFormatOptions::default())
+    }
+}
+
+impl From<LSPFormatOptions> for FormatSettings {
+    fn from(options: LSPFormatOptions) -> Self {
+        Self {
+            external_timeout: Duration::from_millis(
+                options.external_timeout_ms.unwrap_or(DEFAULT_EXTERNAL_TIMEOUT_MS),
+            ),
+            max_file_size: options.max_file_size_bytes.unwrap_or(DEFAULT_MAX_FILE_SIZE_BYTES),
+            verify_idempotent: options.debug_verify_idempotent,
+            publish_parse_errors: options.publish_parse_errors,
+            detect_indentation: options.detect_indentation,
+            skip_shebang_interpreters: options.skip_shebang_interpreters,
+            organize_imports: options.organize_imports,
+            print_width: options.print_width,
+            max_preserved_blank_lines: options.max_preserved_blank_lines,
+            format_valid_regions: options.format_valid_regions,
+        }
+    }
+}
+
+/// Everything a [`ServerFormatter`] is built with besides its resolved config, see [`ServerFormatter::new`].
+///
+/// The default has no workspace nor external formatter, and the default [`FormatSettings`].
+#[derive(Default)]
+pub struct ServerFormatterConfig {
+    pub root_path: PathBuf,
+    /// Why the config could not be resolved, if the default config is used instead.
+    pub config_error: Option<String>,
+    pub config_path: Option<PathBuf>,
+    pub gitignore_glob: Option<Gitignore>,
+    pub include_glob: Option<Gitignore>,
+    pub external: SharedExternalFormatter,
+    /// Shared by all formatters built by the same builder.
+    pub watched_file_hashes: Arc<ConcurrentHashMap<(PathBuf, PathBuf), Option<u64>>>,
+    /// Shared by all formatters built by the same builder.
+    pub code_action_resolve: Arc<AtomicBool>,
+    pub settings: FormatSettings,
+    pub is_disabled: bool,
+}
+
+pub struct ServerFormatter {
+    /// Root directory of the workspace, walked by [`FORMAT_WORKSPACE_COMMAND_ID`].
+    root_path: PathBuf,
+    /// Shared with the formatters rebuilt for options not affecting the config,
+    /// see [`Self::with_options`].
+    config_resolver: Arc<ConfigResolver>,
+    /// Why the config could not be resolved, if the default config is used instead.
+    config_error: Option<String>,
+    /// The config file of the workspace, `None` if not found.
+    config_path: Option<PathBuf>,
+    gitignore_glob: Option<Gitignore>,
+    /// Allowlist of `fmt.includePatterns`, files not matching it are ignored.
+    /// Only the files are checked, so the directories containing them are still walked.
+    include_glob: Option<Gitignore>,
+    /// Whether a directory or one of its ancestors is ignored, keyed by the directory path.
+    /// The formatter is rebuilt on every watched file change, so this never goes stale.
+    ignored_dirs: ConcurrentHashMap<PathBuf, bool>,
+    /// Text edits of recently formatted documents, so unchanged documents are not formatted again.
+    /// Same as `ignored_dirs`, it is dropped with the formatter on every watched file change.
+    format_cache: FormatCache,
+    /// Source text of files read from disk, so files not modified since are not read again.
+    source_cache: SourceCache,
+    /// Options resolved for recently formatted files, so the config is not resolved again.
+    /// Same as `ignored_dirs`, it is dropped with the formatter when the config changes.
+    resolve_cache: ResolveCache,
+    /// Allocators reused by every format request, instead of creating a new arena each time.
+    /// Each allocator is reset when returned to the pool, after the formatted code is copied out of it.
+    allocator_pool: AllocatorPool,
+    /// Options of every parse of JS/TS files, created once instead of for each format request.
+    parse_options: ParseOptions,
+    /// Use it through [`Self::external_bridge`], which stops using it after too many failures.
+    external: SharedExternalFormatter,
+    settings: FormatSettings,
+    /// Hash of the content of the watched files when they last changed, see [`Self::needs_rebuild`].
+    watched_file_hashes: Arc<ConcurrentHashMap<(PathBuf, PathBuf), Option<u64>>>,
+    /// Whether the code actions are returned without edits, see [`Self::code_actions`].
+    code_action_resolve: Arc<AtomicBool>,
+    /// Whether the config file is required by `fmt.requireConfig` but not found.
+    /// A disabled formatter does not format any file.
+    is_disabled: bool,
+}
+
+impl Tool for ServerFormatter {
+    fn name(&self) -> &'static str {
+        "formatter"
+    }
+    /// # Panics
+    /// Panics if the root URI cannot be converted to a file path.
+    fn handle_configuration_change(
+        &self,
+        builder: &dyn ToolBuilder,
+        root_uri: &Uri,
+        old_options_json: &serde_json::Value,
+        new_options_json: serde_json::Value,
+    ) -> ToolRestartChanges {
+        let old_option = match serde_json::from_value::<LSPFormatOptions>(old_options_json.clone())
+        {
+            Ok(opts) => opts,
+            Err(e) => {
+                warn!(
+                    "Failed to deserialize LSPFormatOptions from JSON: {e}. Falling back to default options."
+                );
+                LSPFormatOptions::default()
+            }
+        };
+
+        let new_option = match serde_json::from_value::<LSPFormatOptions>(new_options_json.clone())
+        {
+            Ok(opts) => opts,
+            Err(e) => {
+                warn!(
+                    "Failed to deserialize LSPFormatOptions from JSON: {e}. Falling back to default options."
+                );
+                LSPFormatOptions::default()
+            }
+        };
+
+        if old_option == new_option {
+            return ToolRestartChanges { tool: None, watch_patterns: None };
+        }
+
+        // The config file and the watched files are the same,
+        // only the formatter has to pick up the new options
+        if !old_option.config_lookup_changed(&new_option) {
+            return ToolRestartChanges {
+                tool: Some(Box::new(self.with_options(new_option))),
+                watch_patterns: None,
+            };
+        }
+
+        let new_formatter = builder.build_boxed(root_uri, new_options_json.clone());
+        let watch_patterns = new_formatter.get_watcher_patterns(new_options_json);
+        ToolRestartChanges { tool: Some(new_formatter), watch_patterns: Some(watch_patterns) }
+    }
+
+    fn get_setup_warnings(&self) -> Vec<String> {
+        self.config_error.iter().cloned().collect()
+    }
+
+    fn run_diagnostic(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Result<Vec<(Uri, Vec<Diagnostic>)>, String> {
+        Ok(self.get_parse_error_diagnostics(uri, content))
+    }
+
+    fn run_diagnostic_on_change(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Result<Vec<(Uri, Vec<Diagnostic>)>, String> {
+        Ok(self.get_parse_error_diagnostics(uri, content))
+    }
+
+    fn run_diagnostic_on_save(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Result<Vec<(Uri, Vec<Diagnostic>)>, String> {
+        Ok(self.get_parse_error_diagnostics(uri, content))
+    }
+
+    /// See [`Self::supported_languages`] for [`SUPPORTED_LANGUAGES_REQUEST`],
+    /// and [`Self::bridge_status`] for [`BRIDGE_STATUS_REQUEST`].
+    fn handle_custom_request(&self, method: &str) -> Option<serde_json::Value> {
+        match method {
+            SUPPORTED_LANGUAGES_REQUEST => Some(self.supported_languages()),
+            BRIDGE_STATUS_REQUEST => Some(self.bridge_status()),
+            _ => None,
+        }
+    }
+
+    /// Returns the config files, the matched `overrides` and the effective options of the document
+    /// for [`RESOLVE_CONFIG_REQUEST`].
+    /// `configPath` is `null` if the config file is not found or invalid, then `configError` tells why.
+    /// See [`Self::resolve_config_status`] for [`RESOLVE_CONFIG_REQUEST`],
+    /// and [`Self::preview_formatted`] for [`PREVIEW_FORMATTED_REQUEST`].
+    fn handle_custom_text_document_request(
+        &self,
+        method: &str,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Option<serde_json::Value> {
+        match method {
+            RESOLVE_CONFIG_REQUEST => Some(self.resolve_config_status(uri)),
+            PREVIEW_FORMATTED_REQUEST => Some(self.preview_formatted(uri, content)),
+            _ => None,
+        }
+    }
+
+    fn is_responsible_for_command(&self, command: &str) -> bool {
+        command == FORMAT_WORKSPACE_COMMAND_ID
+    }
+
+    /// Formats all files in the workspace with [`FORMAT_WORKSPACE_COMMAND_ID`].
+    /// The command does not take any arguments.
+    fn execute_command(
+        &self,
+        command: &str,
+        _arguments: Vec<serde_json::Value>,
+        open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        progress: &(dyn Fn(usize, usize) + Sync),
+        is_cancelled: &AtomicBool,
+    ) -> Result<Option<WorkspaceEdit>, ErrorCode> {
+        if command != FORMAT_WORKSPACE_COMMAND_ID {
+            return Ok(None);
+        }
+
+        Ok(self.format_workspace(open_document, progress, is_cancelled))
+    }
+
+    fn get_watcher_patterns(&self, options: serde_json::Value) -> Vec<Pattern> {
+        let options = match serde_json::from_value::<LSPFormatOptions>(options) {
+            Ok(opts) => opts,
+            Err(e) => {
+                warn!(
+                    "Failed to deserialize LSPFormatOptions from JSON: {e}. Falling back to default options."
+                );
+                LSPFormatOptions::default()
+            }
+        };
+
+        let mut patterns = match options.config_path.as_ref().filter(|s| !s.is_empty()) {
+            Some(config_path) => vec![config_path.clone()],
+            None => config_file_names(&options.config_file_names, options.read_prettier_config)
+                .map(ToString::to_string)
+                .collect(),
+        };
+        // Ignore files in use, so newly ignored files are not formatted until a restart
+        patterns.extend(load_ignore_paths(&self.root_path).iter().filter_map(|path| {
+            path.file_name().and_then(|name| name.to_str()).map(ToString::to_string)
+        }));
+        patterns
+    }
+
+    fn handle_watched_file_change(
+        &self,
+        builder: &dyn ToolBuilder,
+        changed_uri: &Uri,
+        root_uri: &Uri,
+        options: serde_json::Value,
+    ) -> ToolRestartChanges {
+        let lsp_options =
+            serde_json::from_value::<LSPFormatOptions>(options.clone()).unwrap_or_default();
+        let needs_rebuild = changed_uri.to_file_path().is_none_or(|path| {
+            self.needs_rebuild(
+                &path,
+                lsp_options.config_path.as_ref(),
+                &lsp_options.config_file_names,
+                lsp_options.read_prettier_config,
+            )
+        });
+        if !needs_rebuild {
+            return ToolRestartChanges { tool: None, watch_patterns: None };
+        }
+
+        let new_formatter = builder.build_boxed(root_uri, options);
+
+        ToolRestartChanges {
+            tool: Some(new_formatter),
+            // TODO: update watch patterns if config_path changed
+            watch_patterns: None,
+        }
+    }
+
+    fn run_format(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        match self.format_document(uri, content, options, is_cancelled) {
+            Ok(FormatOutcome::Edits(edits)) => Some(edits),
+            Ok(FormatOutcome::Unchanged) => Some(vec![]),
+            Err(_) => None,
+        }
+    }
+
+    /// Formats an untitled document as if it was a file in the workspace root,
+    /// with the extension of its language, see [`UNTITLED_LANGUAGE_EXTENSIONS`].
+    /// Ignore patterns are not applied, since the document is not a file yet.
+    fn run_format_untitled(
+        &self,
+        uri: &Uri,
+        language_id: &str,
+        content: &str,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        if self.is_disabled {
+            debug!(uri = uri.as_str(); "Formatter is disabled, no config file found");
+            return None;
+        }
+
+        let Some((_, extension)) =
+            UNTITLED_LANGUAGE_EXTENSIONS.iter().find(|(id, _)| *id == language_id)
+        else {
+            debug!(uri = uri.as_str(), language_id; "Unsupported language of untitled document");
+            return None;
+        };
+        let path = self.root_path.join(format!("untitled.{extension}"));
+        let strategy = FormatFileStrategy::try_from(path.clone()).ok()?;
+        if !self.can_format(&strategy, &path)
+            || self.exceeds_max_file_size(&path, content.len() as u64)
+        {
+            return None;
+        }
+
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+        let code = self.format(&strategy, content, indent, is_cancelled).ok()?;
+        if is_cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(build_text_edits(content, &code))
+    }
+
+    fn get_code_actions_or_commands(
+        &self,
+        uri: &Uri,
+        range: &Range,
+        content: Option<&str>,
+        only_code_action_kinds: Option<&Vec<CodeActionKind>>,
+    ) -> Vec<CodeActionOrCommand> {
+        self.code_actions(uri, range, content, only_code_action_kinds)
+    }
+
+    fn resolve_code_action(
+        &self,
+        code_action: &CodeAction,
+        content: Option<&str>,
+    ) -> Option<CodeAction> {
+        self.resolve_code_action(code_action, content)
+    }
+
+    fn run_format_range(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        self.format_range(uri, range, content, options, &AtomicBool::new(false))
+    }
+
+    fn run_format_ranges(
+        &self,
+        uri: &Uri,
+        ranges: &[Range],
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        self.format_ranges(uri, ranges, content, options)
+    }
+
+    /// Formats the statements touched by the pasted code, same as [`Self::run_format_range`].
+    ///
+    /// Files formatted by the external formatter can only be formatted as a whole,
+    /// so no edits are returned for them.
+    fn run_format_paste(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, _) = self.get_source_text(&path, content).ok()?;
+        if !matches!(strategy, FormatFileStrategy::OxcFormatter { .. }) {
+            return Some(vec![]);
+        }
+        self.run_format_range(uri, range, content, None)
+    }
+
+    fn run_on_type_format(
+        &self,
+        uri: &Uri,
+        position: Position,
+        ch: &str,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        self.format_on_type(uri, position, ch, content, options, is_cancelled)
+    }
+}
+
+impl ServerFormatter {
+    pub fn new(config_resolver: ConfigResolver, config: ServerFormatterConfig) -> Self {
+        Self::with_config(Arc::new(config_resolver), config)
+    }
+
+    fn with_config(config_resolver: Arc<ConfigResolver>, config: ServerFormatterConfig) -> Self {
+        let ServerFormatterConfig {
+            root_path,
+            config_error,
+            config_path,
+            gitignore_glob,
+            include_glob,
+            external,
+            watched_file_hashes,
+            code_action_resolve,
+            settings,
+            is_disabled,
+        } = config;
+
+        Self {
+            root_path,
+            config_resolver,
+            config_error,
+            config_path,
+            gitignore_glob,
+            include_glob,
+            ignored_dirs: ConcurrentHashMap::default(),
+            format_cache: FormatCache::new(FORMAT_CACHE_CAPACITY),
+            source_cache: SourceCache::new(SOURCE_CACHE_CAPACITY),
+            resolve_cache: ResolveCache::new(RESOLVE_CACHE_CAPACITY),
+            // More allocators are created on demand, when formatting on multiple threads
+            allocator_pool: AllocatorPool::new(1),
+            parse_options: get_parse_options(),
+            external,
+            settings,
+            watched_file_hashes,
+            code_action_resolve,
+            is_disabled,
+        }
+    }
+
+    /// Build a formatter with `options`, reusing the resolved config and the ignore matcher of this one.
+    /// Only valid if the options looking up the config file did not change,
+    /// see [`LSPFormatOptions::config_lookup_changed`].
+    fn with_options(&self, options: LSPFormatOptions) -> Self {
+        let include_glob = match ServerFormatterBuilder::create_include_globs(
+            &self.root_path,
+            &options.include_patterns,
+        ) {
+            Ok(glob) => glob,
+            Err(err) => {
+                warn!("Failed to create include globs: {err}, proceeding without include globs");
+                None
+            }
+        };
+
+        let config = ServerFormatterConfig {
+            root_path: self.root_path.clone(),
+            config_error: self.config_error.clone(),
+            config_path: self.config_path.clone(),
+            gitignore_glob: self.gitignore_glob.clone(),
+            include_glob,
+            external: self.external.clone(),
+            watched_file_hashes: Arc::clone(&self.watched_file_hashes),
+            code_action_resolve: Arc::clone(&self.code_action_resolve),
+            settings: FormatSettings::from(options),
+            is_disabled: self.is_disabled,
+        };
+
+        Self {
+            parse_options: self.parse_options,
+            ..Self::with_config(Arc::clone(&self.config_resolver), config)
+        }
+    }
+
+    /// Returns the parse errors of a JS/TS file as diagnostics, so users know why it is not formatted.
+    /// Returns nothing unless `fmt.publishParseErrors` is enabled.
+    ///
+    /// The file is always returned with its diagnostics, even if there are none,
+    /// so the diagnostics of fixed errors are cleared.
+    fn get_parse_error_diagnostics(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Vec<(Uri, Vec<Diagnostic>)> {
+        if !self.settings.publish_parse_errors {
+            return vec![];
+        }
+        let Some(path) = uri.to_file_path() else {
+            return vec![];
+        };
+        let Ok((FormatFileStrategy::OxcFormatter { source_type, .. }, source_text)) =
+            self.get_source_text(&path, content)
+        else {
+            return vec![];
+        };
+
+        let allocator = self.allocator_pool.get();
+        let ret = Parser::new(&allocator, &source_text, source_type)
+            .with_options(self.parse_options)
+            .parse();
+
+        let rope = Rope::from(&*source_text);
+        let diagnostics = ret
+            .errors
+            .iter()
+            .map(|error| parse_error_to_diagnostic(error, &rope, &source_text))
+            .collect();
+        vec![(uri.clone(), diagnostics)]
+    }
+
+    /// Same as [`Tool::run_format`], but tells apart why the file is not formatted,
+    /// e.g. ignored files from syntax errors, and already formatted files from files without edits.
+    ///
+    /// # Errors
+    /// Returns the reason why the file is not formatted, see [`FormatError`].
+    pub fn run_format_result(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Result<FormatOutcome, FormatError> {
+        self.format_document(uri, content, None, &AtomicBool::new(false))
+    }
+
+    /// Formats the document for [`Tool::run_format`] and [`Self::run_format_result`].
+    /// The edits are cached until the content or the indentation sent by the client changes.
+    /// Every request is logged with its outcome, see [`FormatRequestLog`].
+    fn format_document(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Result<FormatOutcome, FormatError> {
+        let config_path = self.config_path.as_deref().filter(|_| self.config_error.is_none());
+        let mut request = FormatRequestLog { uri, config_path, strategy: None };
+        let result = self.format_document_logged(&mut request, content, options, is_cancelled);
+        request.log(&result);
+        result
+    }
+
+    fn format_document_logged(
+        &self,
+        request: &mut FormatRequestLog,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Result<FormatOutcome, FormatError> {
+        let path = request.uri.to_file_path().ok_or(FormatError::InvalidUri)?;
+        let (strategy, source_text) = self.get_source_text(&path, content)?;
+        request.strategy = Some(strategy_name(&strategy));
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+
+        // Content read from disk is only reused while the file is not modified, see `get_source_text()`
+        let cache_key = (source_text.as_ref(), indent);
+        let edits = if let Some(edits) = self.format_cache.get(&path, &cache_key) {
+            edits
+        } else {
+            let code = match self.format(&strategy, &source_text, indent, is_cancelled) {
+                Err(FormatError::ParseError) if self.settings.format_valid_regions => {
+                    let edits = self.build_valid_region_edits(
+                        &strategy,
+                        &source_text,
+                        indent,
+                        is_cancelled,
+                    )?;
+                    if is_cancelled.load(Ordering::Relaxed) {
+                        return Err(FormatError::Cancelled);
+                    }
+                    self.format_cache.insert(&path, &cache_key, edits.clone());
+                    return Ok(if edits.is_empty() {
+                        FormatOutcome::Unchanged
+                    } else {
+                        FormatOutcome::Edits(edits)
+                    });
+                }
+                result => result?,
+            };
+
+            // The content may have changed in the meantime, the edits would be outdated
+            if is_cancelled.load(Ordering::Relaxed) {
+                return Err(FormatError::Cancelled);
+            }
+
+            if self.settings.verify_idempotent
+                && code != *source_text
+                && !self.is_idempotent(&strategy, &code, indent, is_cancelled)
+            {
+                warn!(
+                    path:% = path.display();
+                    "Formatting is not idempotent, formatting again changes the output"
+                );
+            }
+
+            let edits = build_text_edits(&source_text, &code);
+            self.format_cache.insert(&path, &cache_key, edits.clone());
+            edits
+        };
+
+        Ok(if edits.is_empty() { FormatOutcome::Unchanged } else { FormatOutcome::Edits(edits) })
+    }
+
+    /// Formats the whole file for range and on-type formatting, as [`Tool::run_format`] does,
+    /// reusing the edits of [`Self::format_cache`] so a file is not formatted again until it changes.
+    ///
+    /// The cached edits may only format the valid regions of a file with parse errors,
+    /// callers should check that the source parses first, see [`Self::build_valid_region_edits`].
+    fn format_cached(
+        &self,
+        path: &Path,
+        strategy: &FormatFileStrategy,
+        source_text: &str,
+        indent: Option<(bool, u32)>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<String> {
+        let cache_key = (source_text, indent);
+        if let Some(edits) = self.format_cache.get(path, &cache_key) {
+            return Some(apply_text_edits(source_text, &edits));
+        }
+
+        let code = self.format(strategy, source_text, indent, is_cancelled).ok()?;
+        if is_cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.format_cache.insert(path, &cache_key, build_text_edits(source_text, &code));
+        Some(code)
+    }
+
+    /// Returns the edits formatting the valid regions of a JS/TS file with parse errors,
+    /// see [`Self::find_valid_regions`]. The lines around the errors are kept as is.
+    /// Returns [`FormatError::ParseError`] if there is no valid region.
+    fn build_valid_region_edits(
+        &self,
+        strategy: &FormatFileStrategy,
+        source_text: &str,
+        indent: Option<(bool, u32)>,
+        is_cancelled: &AtomicBool,
+    ) -> Result<Vec<TextEdit>, FormatError> {
+        let FormatFileStrategy::OxcFormatter { source_type, .. } = strategy else {
+            return Err(FormatError::ParseError);
+        };
+        let (prefix_end, suffix_start) =
+            self.find_valid_regions(source_text, *source_type, is_cancelled);
+        if prefix_end.is_none() && suffix_start.is_none() {
+            return Err(FormatError::ParseError);
+        }
+
+        let mut edits = vec![];
+        if let Some(end) = prefix_end {
+            // The blank lines before the errors are kept, so is the final newline added by formatting
+            let region = source_text[..end].trim_end();
+            let code = self.format(strategy, region, indent, is_cancelled)?;
+            edits.extend(build_text_edits(region, code.trim_end()));
+        }
+        if let Some(start) = suffix_start {
+            let region = &source_text[start..];
+            let code = self.format(strategy, region, indent, is_cancelled)?;
+            // The region starts at the beginning of a line, so only the lines are shifted
+            #[expect(clippy::cast_possible_truncation)]
+            let line_offset = PositionCursor::new(source_text).position(start as u32).line;
+            edits.extend(build_text_edits(region, &code).into_iter().map(|mut edit| {
+                edit.range.start.line += line_offset;
+                edit.range.end.line += line_offset;
+                edit
+            }));
+        }
+        Ok(edits)
+    }
+
+    /// Finds the valid regions of a JS/TS file with parse errors, for `fmt.formatValidRegions`:
+    /// the top-level statements before the first error, and those after the last error.
+    /// Returns the end of the first region and the start of the second one, if any.
+    ///
+    /// The regions are split at lines starting a top-level statement, see [`is_statement_boundary`],
+    /// and must parse without errors on their own.
+    /// At most [`MAX_VALID_REGION_ATTEMPTS`] splits are tried for each region, since each attempt parses the region.
+    fn find_valid_regions(
+        &self,
+        source_text: &str,
+        source_type: SourceType,
+        is_cancelled: &AtomicBool,
+    ) -> (Option<usize>, Option<usize>) {
+        let parses_cleanly = |text: &str| {
+            let allocator = self.allocator_pool.get();
+            let ret =
+                Parser::new(&allocator, text, source_type).with_options(self.parse_options).parse();
+            ret.errors.is_empty()
+        };
+
+        let (error_start, error_end) = {
+            let allocator = self.allocator_pool.get();
+            let ret = Parser::new(&allocator, source_text, source_type)
+                .with_options(self.parse_options)
+                .parse();
+            let spans = ret
+                .errors
+                .iter()
+                .flat_map(|error| error.labels.as_deref().unwrap_or_default())
+                .map(|label| (label.offset(), label.offset() + label.len()));
+            spans.fold((None, None), |(start, end): (Option<usize>, Option<usize>), (s, e)| {
+                (Some(start.map_or(s, |start| start.min(s))), Some(end.map_or(e, |end| end.max(e))))
+            })
+        };
+        let (Some(error_start), Some(error_end)) = (error_start, error_end) else {
+            return (None, None);
+        };
+
+        let boundaries = source_text
+            .match_indices('\n')
+            .map(|(index, _)| index + 1)
+            .filter(|&start| is_statement_boundary(&source_text[start..]))
+            .collect::<Vec<_>>();
+
+        let prefix_end = boundaries
+            .iter()
+            .rev()
+            .filter(|&&end| end <= error_start)
+            .take(MAX_VALID_REGION_ATTEMPTS)
+            .take_while(|_| !is_cancelled.load(Ordering::Relaxed))
+            .find(|&&end| {
+                !source_text[..end].trim().is_empty() && parses_cleanly(&source_text[..end])
+            })
+            .copied();
+        // The region starts after the line of the last error
+        let error_line_end =
+            source_text[error_end..].find('\n').map_or(source_text.len(), |i| error_end + i);
+        let suffix_start = boundaries
+            .iter()
+            .filter(|&&start| start > error_line_end)
+            .take(MAX_VALID_REGION_ATTEMPTS)
+            .take_while(|_| !is_cancelled.load(Ordering::Relaxed))
+            .find(|&&start| parses_cleanly(&source_text[start..]))
+            .copied();
+
+        (prefix_end, suffix_start)
+    }
+
+    /// Same as [`Tool::run_format`], but also returns how long formatting took,
+    /// so slow files can be diagnosed by tools embedding the formatter.
+    /// The format cache is skipped, so the file is always formatted.
+    pub fn run_format_with_stats(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> (Option<Vec<TextEdit>>, FormatStats) {
+        let mut stats = FormatStats::default();
+        let Some(path) = uri.to_file_path() else {
+            return (None, stats);
+        };
+        let Ok((strategy, source_text)) = self.get_source_text(&path, content) else {
+            return (None, stats);
+        };
+        stats.bytes_in = source_text.len();
+
+        let Ok(code) = self.format_with_stats(
+            &strategy,
+            &source_text,
+            None,
+            self.settings.organize_imports,
+            &AtomicBool::new(false),
+            &mut stats,
+        ) else {
+            return (None, stats);
+        };
+        stats.bytes_out = code.len();
+
+        let edits = build_text_edits(&source_text, &code);
+        stats.edit_count = edits.len();
+        (Some(edits), stats)
+    }
+
+    /// Checks whether the file is formatted, without returning any edits.
+    /// Unlike [`Tool::run_format`], ignored, unsupported and unparsable files are told apart,
+    /// e.g. for CI-style tools reporting unformatted files.
+    pub fn check(&self, uri: &Uri, content: Option<&str>) -> CheckResult {
+        let Some(path) = uri.to_file_path() else {
+            return CheckResult::Skipped;
+        };
+        if self.is_disabled || self.is_ignored(&path) {
+            return CheckResult::Ignored;
+        }
+        let Ok((strategy, source_text)) = self.get_source_text(&path, content) else {
+            return CheckResult::Skipped;
+        };
+
+        match self.format(&strategy, &source_text, None, &AtomicBool::new(false)) {
+            Err(FormatError::ParseError) => CheckResult::ParseError,
+            // e.g. the external formatter is not available or rejected the file
+            Err(_) => CheckResult::Skipped,
+            Ok(code) if code == source_text => CheckResult::Formatted,
+            Ok(_) => CheckResult::NeedsFormatting,
+        }
+    }
+
+    /// Formats `source` without a file backing it, e.g. a scratch buffer.
+    /// `file_hint` is an extension like `ts` or a file name like `package.json`,
+    /// picking the formatter as if the file was at the workspace root.
+    /// `parser` picks the formatter instead of the hint, see [`FormatFileStrategy::from_parser_name`],
+    /// the hint is then only used to resolve the config overrides.
+    /// The ignore patterns are not checked, but the resolved config options are still applied.
+    ///
+    /// Returns `None` if the hint or the parser is not supported or the source can not be formatted.
+    pub fn format_source(
+        &self,
+        source: &str,
+        file_hint: &str,
+        parser: Option<&str>,
+    ) -> Option<String> {
+        let file_name = if file_hint.contains('.') {
+            file_hint.to_string()
+        } else {
+            format!("source.{file_hint}")
+        };
+        let path = self.root_path.join(file_name);
+        let strategy = match parser {
+            Some(parser) => FormatFileStrategy::from_parser_name(path, parser)
+                .inspect_err(|err| debug!("{err}"))
+                .ok()?,
+            None => FormatFileStrategy::try_from(path).ok()?,
+        };
+
+        if let FormatFileStrategy::ExternalFormatter { parser_name, .. } = &strategy
+            && (self.external_bridge().is_none() || !self.external.languages.contains(*parser_name))
+        {
+            return None;
+        }
+
+        self.format(&strategy, source, None, &AtomicBool::new(false)).ok()
+    }
+
+    /// Whether the file can be formatted, i.e. the external formatter handles it if needed.
+    /// `package.json` can still be sorted without the external formatter, see `format()`.
+    fn can_format(&self, strategy: &FormatFileStrategy, path: &Path) -> bool {
+        if let FormatFileStrategy::ExternalFormatter { parser_name, .. } = strategy
+            && (self.external_bridge().is_none() || !self.external.languages.contains(*parser_name))
+        {
+            debug!(path:% = path.display(), parser = *parser_name; "No external formatter available");
+            return false;
+        }
+        true
+    }
+
+    /// Returns the format strategy and the source text of the given path, if it should be formatted.
+    /// If `content` is `None`, the source text is read from the file system.
+    fn get_source_text<'a>(
+        &self,
+        path: &Path,
+        content: Option<&'a str>,
+    ) -> Result<(FormatFileStrategy, Cow<'a, str>), FormatError> {
+        if self.is_disabled {
+            debug!(path:% = path.display(); "Formatter is disabled, no config file found");
+            return Err(FormatError::Disabled);
+        }
+
+        if self.is_ignored(path) {
+            debug!(path:% = path.display(); "File is ignored");
+            return Err(FormatError::Ignored);
+        }
+
+        let strategy = FormatFileStrategy::try_from(path.to_path_buf())
+            .map_err(|()| FormatError::Unsupported)?;
+
+        // Check before reading the file, whether the external formatter can handle it at all.
+        if !self.can_format(&strategy, path) {
+            return Err(FormatError::ExternalFormatterUnavailable);
+        }
+
+        let source_text = match content {
+            Some(content) => {
+                if self.exceeds_max_file_size(path, content.len() as u64) {
+                    return Err(FormatError::TooLarge);
+                }
+                Cow::Borrowed(content)
+            }
+            None => Cow::Owned(self.read_source_text(path)?),
+        };
+
+        // Checked before parsing, so scripts of other runtimes are not reported as parse errors
+        if matches!(strategy, FormatFileStrategy::OxcFormatter { .. })
+            && let Some(interpreter) = shebang_interpreter(&source_text)
+            && self.settings.skip_shebang_interpreters.iter().any(|skipped| skipped == interpreter)
+        {
+            debug!(path:% = path.display(), interpreter; "File is run by a skipped interpreter");
+            return Err(FormatError::SkippedInterpreter(interpreter.to_string()));
+        }
+
+        Ok((strategy, source_text))
+    }
+
+    /// Reads the source text of the file from disk, or from the cache if not modified since.
+    /// Returns an error if the file is too large or cannot be read.
+    fn read_source_text(&self, path: &Path) -> Result<String, FormatError> {
+        // Check the size before reading the whole file into memory
+        let metadata = std::fs::metadata(path).ok();
+        if let Some(metadata) = &metadata
+            && self.exceeds_max_file_size(path, metadata.len())
+        {
+            return Err(FormatError::TooLarge);
+        }
+
+        // Skip reading the file again, if it was not modified since the last read
+        let modified = metadata.and_then(|metadata| metadata.modified().ok());
+        if let Some(modified) = modified
+            && let Some(source) = self.source_cache.get(path, modified)
+        {
+            return Ok(source.to_string());
+        }
+
+        let file_content = std::fs::read_to_string(path)
+            .map_err(|err| FormatError::ReadFailed(err.to_string()))?;
+        #[cfg(all(test, windows))]
+        #[expect(clippy::disallowed_methods)] // no `cow_replace` in tests are fine
+        // On Windows, convert CRLF to LF for consistent formatting results
+        let file_content = file_content.replace("\r\n", "\n");
+
+        if let Some(modified) = modified {
+            self.source_cache.insert(path, modified, file_content.as_str().into());
+        }
+
+        Ok(file_content)
+    }
+
+    /// Whether the file of `size` bytes is too large to format, see `fmt.maxFileSizeBytes`.
+    fn exceeds_max_file_size(&self, path: &Path, size: u64) -> bool {
+        if size <= self.settings.max_file_size {
+            return false;
+        }
+        debug!(
+            "File is too large to format ({size} > {} bytes): {}",
+            self.settings.max_file_size,
+            path.display()
+        );
+        true
+    }
+
+    /// Formats the source text with the formatter of the given strategy.
+    /// Returns an error if the source text can not be formatted, e.g. it has syntax errors.
+    /// `indent` is `(insert_spaces, tab_size)` sent by the client,
+    /// only used if the config does not set the indentation.
+    fn format(
+        &self,
+        strategy: &FormatFileStrategy,
+        source_text: &str,
+        indent: Option<(bool, u32)>,
+        is_cancelled: &AtomicBool,
+    ) -> Result<String, FormatError> {
+        self.format_with_stats(
+            strategy,
+            source_text,
+            indent,
+            self.settings.organize_imports,
+            is_cancelled,
+            &mut FormatStats::default(),
+        )
+    }
+
+    /// Same as [`Self::format`], recording the parse and format timings in `stats`.
+    /// `organize_imports` sorts and deduplicates the imports of JS/TS files,
+    /// the sort options of the config are used if set.
+    fn format_with_stats(
+        &self,
+        strategy: &FormatFileStrategy,
+        source_text: &str,
+        indent: Option<(bool, u32)>,
+        organize_imports: bool,
+        is_cancelled: &AtomicBool,
+        stats: &mut FormatStats,
+    ) -> Result<String, FormatError> {
+        // Kept for formatting again with a native strategy, see `native_fallback_strategy()`
+        let (original_source_text, original_indent) = (source_text, indent);
+        // Formatters may strip or keep the BOM, so it is always stripped before formatting
+        // and added back to the result, then the edits never touch it.
+        let (has_bom, source_text) = match source_text.strip_prefix(BOM) {
+            Some(source_text) => (true, source_text),
+            None => (false, source_text),
+        };
+
+        // The indentation of the source takes precedence over the editor settings
+        let indent = match self
+            .settings
+            .detect_indentation
+            .then(|| detect_indentation(source_text))
+            .flatten()
+        {
+            Some(DetectedIndent::Tabs) => {
+                Some((false, indent.map_or(DEFAULT_TAB_SIZE, |(_, tab_size)| tab_size)))
+            }
+            Some(DetectedIndent::Spaces(width)) => Some((true, width)),
+            None => indent,
+        };
+        // Out of range widths are ignored by the resolver
+        let indent = indent.map(|(insert_spaces, tab_size)| {
+            (insert_spaces, u8::try_from(tab_size).unwrap_or(u8::MAX))
+        });
+        let resolved_options = self.resolve_options(strategy, indent);
+        let start = Instant::now();
+        let (code, insert_final_newline, trim_trailing_newlines, final_newline) = match (
+            strategy,
+            resolved_options,
+        ) {
+            (
+                FormatFileStrategy::OxcFormatter { source_type, .. },
+                ResolvedOptions::OxcFormatter {
+                    mut format_options,
+                    external_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                },
+            ) => {
+                let source_type = *source_type;
+                let deduplicated_source_text: String;
+                let allocator = self.allocator_pool.get();
+                let mut ret = Parser::new(&allocator, source_text, source_type)
+                    .with_options(self.parse_options)
+                    .parse();
+
+                if !ret.errors.is_empty() {
+                    return Err(FormatError::ParseError);
+                }
+
+                if organize_imports {
+                    format_options.experimental_sort_imports.get_or_insert_default();
+                    if let Some(source_text) = remove_duplicate_imports(source_text, &ret.program) {
+                        deduplicated_source_text = source_text;
+                        ret = Parser::new(&allocator, &deduplicated_source_text, source_type)
+                            .with_options(self.parse_options)
+                            .parse();
+                        if !ret.errors.is_empty() {
+                            return Err(FormatError::ParseError);
+                        }
+                    }
+                }
+                stats.parse_micros = elapsed_micros(start);
+
+                let embedded_formatter = if format_options.embedded_language_formatting.is_off() {
+                    None
+                } else {
+                    self.embedded_formatter(external_options)
+                };
+                let formatted = Formatter::new(&allocator, format_options)
+                    .format_impl(&ret.program, embedded_formatter);
+                (
+                    formatted.print().map_err(|_| FormatError::ParseError)?.into_code(),
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                )
+            }
+            (
+                FormatFileStrategy::OxfmtToml { .. },
+                ResolvedOptions::OxfmtToml {
+                    toml_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                },
+            ) => (
+                oxc_toml::format(source_text, toml_options),
+                insert_final_newline,
+                trim_trailing_newlines,
+                final_newline,
+            ),
+            (
+                FormatFileStrategy::OxcFormatterJson { .. },
+                ResolvedOptions::OxcFormatterJson {
+                    format_options,
+                    sort_keys,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                },
+            ) => {
+                let allocator = self.allocator_pool.get();
+                let code = format_json(&allocator, source_text, format_options, sort_keys)
+                    .map_err(|_| FormatError::ParseError)?;
+                (code, insert_final_newline, trim_trailing_newlines, final_newline)
+            }
+            (
+                FormatFileStrategy::ExternalFormatter { path, parser_name },
+                ResolvedOptions::ExternalFormatter {
+                    external_options,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                },
+            ) => {
+                let Some(code) = self.format_by_external_formatter(
+                    source_text,
+                    path,
+                    parser_name,
+                    &external_options,
+                    is_cancelled,
+                )?
+                else {
+                    // No parser or plugin can handle the file, try to format it natively
+                    let fallback = native_fallback_strategy(path, parser_name)
+                        .ok_or(FormatError::Unsupported)?;
+                    return self.format_with_stats(
+                        &fallback,
+                        original_source_text,
+                        original_indent,
+                        organize_imports,
+                        is_cancelled,
+                        stats,
+                    );
+                };
+                (code, insert_final_newline, trim_trailing_newlines, final_newline)
+            }
+            (
+                FormatFileStrategy::ExternalFormatterPackageJson { path, parser_name },
+                ResolvedOptions::ExternalFormatterPackageJson {
+                    external_options,
+                    sort_package_json,
+                    insert_final_newline,
+                    trim_trailing_newlines,
+                    final_newline,
+                },
+            ) => {
+                // Without the external formatter, `package.json` is only sorted and reindented,
+                // unless sorting is disabled by the config
+                let sort_only = self.external_bridge().is_none();
+                if sort_only && sort_package_json.is_none() {
+                    return Err(FormatError::ExternalFormatterUnavailable);
+                }
+
+                let source_text: Cow<'_, str> = if let Some(sort_options) = sort_package_json {
+                    let options = sort_package_json::SortOptions {
+                        sort_scripts: sort_options.sort_scripts,
+                        pretty: sort_only || sort_options.pretty,
+                    };
+                    match sort_package_json::sort_package_json_with_options(source_text, &options) {
+                        Ok(sorted) => Cow::Owned(sorted),
+                        Err(err) => {
+                            debug!(path:% = path.display(), err:%; "Failed to sort package.json");
+                            return Err(FormatError::ParseError);
+                        }
+                    }
+                } else {
+                    Cow::Borrowed(source_text)
+                };
+
+                let code = if sort_only {
+                    // The sorted output is printed from scratch, reindent it like other JSON files,
+                    // so the config applies and formatting it again does not change it
+                    let json_strategy = FormatFileStrategy::OxcFormatterJson {
+                        path: path.clone(),
+                        parser_name: "json",
+                    };
+                    let ResolvedOptions::OxcFormatterJson { format_options, .. } =
+                        self.resolve_options(&json_strategy, indent)
+                    else {
+                        return Err(options_mismatch(&json_strategy));
+                    };
+                    let allocator = self.allocator_pool.get();
+                    format_json(&allocator, &source_text, format_options, false)
+                        .map_err(|_| FormatError::ParseError)?
+                } else {
+                    self.format_by_external_formatter(
+                        &source_text,
+                        path,
+                        parser_name,
+                        &external_options,
+                        is_cancelled,
+                    )?
+                    .ok_or(FormatError::Unsupported)?
+                };
+                (code, insert_final_newline, trim_trailing_newlines, final_newline)
+            }
+            _ => return Err(options_mismatch(strategy)),
+        };
+        stats.format_micros = elapsed_micros(start).saturating_sub(stats.parse_micros);
+
+        // The trailing line breaks are part of the content, which the formatters keep as in the source text
+        let (insert_final_newline, trim_trailing_newlines) =
+            if has_significant_trailing_newlines(strategy, &code) {
+                (true, false)
+            } else {
+                (insert_final_newline, trim_trailing_newlines)
+            };
+        let mut code = apply_final_newline(
+            code,
+            source_text,
+            insert_final_newline,
+            trim_trailing_newlines,
+            final_newline,
+        );
+        if has_bom {
+            code.insert(0, BOM);
+        }
+        Ok(code)
+    }
+
+    /// Resolves the options of the file, with `indent` and the print width sent by the client
+    /// if the config does not set them, and the maximum number of blank lines sent by the client.
+    fn resolve_options(
+        &self,
+        strategy: &FormatFileStrategy,
+        indent: Option<(bool, u8)>,
+    ) -> ResolvedOptions {
+        self.resolve_cache.get_or_resolve(strategy, indent, || {
+            let mut resolved_options = if indent.is_none() && self.settings.print_width.is_none() {
+                self.config_resolver.resolve(strategy)
+            } else {
+                let indent = indent.map(|(insert_spaces, tab_width)| (!insert_spaces, tab_width));
+                self.config_resolver.resolve_with_fallbacks(
+                    strategy,
+                    indent,
+                    self.settings.print_width,
+                )
+            };
+            if let Some(max_preserved_blank_lines) = self.settings.max_preserved_blank_lines
+                && let ResolvedOptions::OxcFormatter { format_options, .. } = &mut resolved_options
+            {
+                format_options.max_preserved_blank_lines = max_preserved_blank_lines.into();
+            }
+            resolved_options
+        })
+    }
+
+    /// Formats a non-JS/TS file with the external formatter (Prettier).
+    /// Returns an error if the external formatter is not available, fails or times out,
+    /// and `Ok(None)` if no parser or plugin of the external formatter can handle the file.
+    fn format_by_external_formatter(
+        &self,
+        source_text: &str,
+        path: &Path,
+        parser_name: &str,
+        external_options: &serde_json::Value,
+        is_cancelled: &AtomicBool,
+    ) -> Result<Option<String>, FormatError> {
+        let external_bridge =
+            self.external_bridge().ok_or(FormatError::ExternalFormatterUnavailable)?;
+        // Some plugins might depend on `filepath`, so pass the actual file name as well.
+        // See also `SourceFormatter::format_by_external_formatter`.
+        let file_name = utils::file_name_lossy(path);
+        if let Cow::Owned(name) = &file_name {
+            warn!("File name is not valid UTF-8, using `{name}`: {}", path.display());
+        }
+
+        match external_bridge.format_file(
+            external_options,
+            parser_name,
+            &file_name,
+            source_text,
+            self.settings.external_timeout,
+            is_cancelled,
+        ) {
+            Ok(code) => {
+                // An unsupported file is not a failure, the external formatter did respond
+                if code.is_none() {
+                    debug!(path:% = path.display(); "External formatter cannot handle file");
+                }
+                self.external.failures.store(0, Ordering::Relaxed);
+                Ok(code)
+            }
+            Err(BridgeError::Rejected(err)) => {
+                debug!(path:% = path.display(), err:%; "External formatter rejected file");
+                // e.g. a syntax error, the external formatter did respond
+                self.external.failures.store(0, Ordering::Relaxed);
+                Err(FormatError::ExternalFormatterFailed(err))
+            }
+            Err(BridgeError::Failed(err)) => {
+                debug!(path:% = path.display(), err:%; "Failed to format file with external formatter");
+                // Cancelled requests are not the external formatter's fault
+                if !is_cancelled.load(Ordering::Relaxed) {
+                    let failures = self.external.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                    if failures == MAX_CONSECUTIVE_EXTERNAL_FAILURES {
+                        warn!(
+                            "External formatter failed {failures} times in a row, proceeding without it"
+                        );
+                    }
+                }
+                Err(FormatError::ExternalFormatterFailed(err))
+            }
+        }
+    }
+
+    /// The external formatter, unless it is not available
+    /// or failed [`MAX_CONSECUTIVE_EXTERNAL_FAILURES`] times in a row.
+    fn external_bridge(&self) -> Option<&Arc<dyn ExternalFormatterBridge>> {
+        if self.external.failures.load(Ordering::Relaxed) >= MAX_CONSECUTIVE_EXTERNAL_FAILURES {
+            return None;
+        }
+        self.external.bridge.as_ref()
+    }
+
+    /// Whether formatting the already formatted `code` again returns the same output.
+    /// Otherwise, the file would keep changing on every format.
+    fn is_idempotent(
+        &self,
+        strategy: &FormatFileStrategy,
+        code: &str,
+        indent: Option<(bool, u32)>,
+        is_cancelled: &AtomicBool,
+    ) -> bool {
+        self.format(strategy, code, indent, is_cancelled)
+            .is_ok_and(|reformatted| reformatted == code)
+    }
+}
+
+/// Returns the strategy to format a file natively,
+/// when the external formatter reports that it cannot handle it.
+/// Returns `None` if there is no native formatter for the file, then it is skipped.
+fn native_fallback_strategy(path: &Path, parser_name: &str) -> Option<FormatFileStrategy> {
+    if let Some(source_type) = get_supported_source_type(path) {
+        return Some(FormatFileStrategy::oxc_formatter(path.to_path_buf(), source_type));
+    }
+    // `json-stringify` only differs from `json` in quoting and empty lines
+    (parser_name == "json-stringify").then(|| FormatFileStrategy::OxcFormatterJson {
+        path: path.to_path_buf(),
+        parser_name: "json",
+    })
+}
+
+/// Context of a format request, logged once with the outcome of the request.
+/// The fields are logged as key-values, so the logs can be filtered per file or per outcome,
+/// e.g. why a file is not formatted: `ignored`, `unsupported` or `too_large`.
+struct FormatRequestLog<'a> {
+    uri: &'a Uri,
+    /// The config file of the workspace, `None` if not found or invalid.
+    config_path: Option<&'a Path>,
+    /// `None` until the file is known to be formatted, e.g. not ignored.
+    strategy: Option<&'static str>,
+}
+
+impl FormatRequestLog<'_> {
+    fn log(&self, result: &Result<FormatOutcome, FormatError>) {
+        let (outcome, edits) = match result {
+            Ok(FormatOutcome::Edits(edits)) => ("formatted", edits.len()),
+            Ok(FormatOutcome::Unchanged) => ("unchanged", 0),
+            Err(err) => (format_error_name(err), 0),
+        };
+        debug!(
+            uri = self.uri.as_str(),
+            strategy = self.strategy.unwrap_or("none"),
+            config:% = self.config_path.map_or_else(|| "none".into(), |path| path.display().to_string()),
+            outcome,
+            edits;
+            "Format request"
+        );
+    }
+}
+
+/// Logs options resolved for another kind of file than the strategy, which is a bug of the config resolver.
+/// The language server keeps running, so the file is skipped instead of panicking.
+fn options_mismatch(strategy: &FormatFileStrategy) -> FormatError {
+    warn!(
+        path:% = strategy.path().display();
+        "Resolved options do not match the format strategy, skipping file"
+    );
+    FormatError::Unsupported
+}
+
+/// Name of the strategy as logged by [`FormatRequestLog`].
+fn strategy_name(strategy: &FormatFileStrategy) -> &'static str {
+    match strategy {
+        FormatFileStrategy::OxcFormatter { .. } => "oxc_formatter",
+        FormatFileStrategy::OxfmtToml { .. } => "oxfmt_toml",
+        FormatFileStrategy::OxcFormatterJson { .. } => "oxc_formatter_json",
+        FormatFileStrategy::ExternalFormatter { .. } => "external_formatter",
+        FormatFileStrategy::ExternalFormatterPackageJson { .. } => {
+            "external_formatter_package_json"
+        }
+    }
+}
+
+/// Name of the error as logged by [`FormatRequestLog`].
+fn format_error_name(err: &FormatError) -> &'static str {
+    match err {
+        FormatError::InvalidUri => "invalid_uri",
+        FormatError::Disabled => "disabled",
+        FormatError::Ignored => "ignored",
+        FormatError::Unsupported => "unsupported",
+        FormatError::ExternalFormatterUnavailable => "external_formatter_unavailable",
+        FormatError::TooLarge => "too_large",
+        FormatError::ReadFailed(_) => "read_failed",
+        FormatError::SkippedInterpreter(_) => "skipped_interpreter",
+        FormatError::ParseError => "parse_error",
+        FormatError::ExternalFormatterFailed(_) => "external_formatter_failed",
+        FormatError::Cancelled => "cancelled",
+    }
+}
+
+/// Returns the name of the interpreter in the shebang of the source text,
+/// e.g. `gjs` for `#!/usr/bin/gjs` and `#!/usr/bin/env -S gjs -m`.
+fn shebang_interpreter(source_text: &str) -> Option<&str> {
+    let source_text = source_text.strip_prefix(BOM).unwrap_or(source_text);
+    let line = source_text.strip_prefix("#!")?.lines().next()?;
+    let mut args = line.split_whitespace();
+    let program = args.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program);
+    }
+    // Skip the options and variables of `env`, e.g. `-S` and `NODE_ENV=production`
+    args.find(|arg| !arg.starts_with('-') && !arg.contains('='))
+}
+
+fn elapsed_micros(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Converts a parse error to a LSP diagnostic, located at its primary label.
+#[expect(clippy::cast_possible_truncation)]
+fn parse_error_to_diagnostic(error: &OxcDiagnostic, rope: &Rope, source_text: &str) -> Diagnostic {
+    let labels = error.labels.as_deref().unwrap_or_default();
+    let range = labels.iter().find(|label| label.primary()).or(labels.first()).map_or_else(
+        Range::default,
+        |label| {
+            let start = label.offset() as u32;
+            let end = start + label.len() as u32;
+            let (start_line, start_character) = get_line_column(rope, start, source_text);
+            let (end_line, end_character) = get_line_column(rope, end, source_text);
+            Range::new(
+                Position::new(start_line, start_character),
+                Position::new(end_line, end_character),
+            )
+        },
+    );
+
+    let message = match &error.help {
+        Some(help) => format!("{}\nhelp: {help}", error.message),
+        None => error.message.to_string(),
+    };
+
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        message,
+        source: Some("oxfmt".into()),
+        ..Default::default()
+    }
+}
+
+/// Maximum number of splits tried for each valid region of `fmt.formatValidRegions`,
+/// see [`ServerFormatter::find_valid_regions`].
+const MAX_VALID_REGION_ATTEMPTS: usize = 16;
+
+/// Words continuing the statement of the previous line, even at the start of a line.
+const CONTINUATION_KEYWORDS: [&str; 5] = ["as", "else", "in", "instanceof", "satisfies"];
+
+/// Whether `line` (the source text from the start of a line) starts a top-level statement.
+///
+/// Only lines starting with an identifier or a keyword without indentation are considered,
+/// e.g. lines starting with `(`, `[` or an operator may continue the statement of the previous line.
+fn is_statement_boundary(line: &str) -> bool {
+    let Some(first) = line.chars().next() else {
+        return false;
+    };
+    if !(first.is_alphabetic() || matches!(first, '_' | '$' | '@')) {
+        return false;
+    }
+    let word_end = line.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'));
+    let word = &line[..word_end.unwrap_or(line.len())];
+    !CONTINUATION_KEYWORDS.contains(&word)
+}
+
+/// Returns `source_text` without the top-level imports identical to a previous one, e.g. pasted twice.
+/// Returns `None` if there are no duplicates.
+///
+/// Removing them does not change the evaluation order, since a module is only evaluated once,
+/// at its first import. The line of a removed import is removed too, if nothing else is on it.
+fn remove_duplicate_imports(source_text: &str, program: &Program<'_>) -> Option<String> {
+    let mut seen = FxHashSet::default();
+    let mut duplicates = vec![];
+    for statement in &program.body {
+        let Statement::ImportDeclaration(import) = statement else {
+            continue;
+        };
+        if !seen.insert(import.span.source_text(source_text)) {
+            duplicates.push(import.span);
+        }
+    }
+    if duplicates.is_empty() {
+        return None;
+    }
+
+    let mut result = String::with_capacity(source_text.len());
+    let mut last_end = 0;
+    for span in duplicates {
+        let start = span.start as usize;
+        let mut end = span.end as usize;
+        let rest = &source_text[end..];
+        let line_end = rest.find('\n').map_or(rest.len(), |index| index + 1);
+        if rest[..line_end].trim().is_empty() {
+            end += line_end;
+        }
+        result.push_str(&source_text[last_end..start]);
+        last_end = end;
+    }
+    result.push_str(&source_text[last_end..]);
+    Some(result)
+}
+
+/// Initialize the external formatter once for the whole language server.
+/// Returns the bridge, the parser names it supports:
+/// the ones built into Prettier, plus the languages of the loaded plugins,
+/// and its optional features.
+/// Returns `None` if it is not available or fails to initialize.
+fn init_external_formatter(
+    external_bridge: Option<Arc<dyn ExternalFormatterBridge>>,
+) -> Option<(Arc<dyn ExternalFormatterBridge>, FxHashSet<String>, BridgeCapabilities)> {
+    let external_bridge = external_bridge?;
+
+    // Files are formatted one by one, no need for multiple threads
+    match external_bridge.init(1) {
+        Ok(plugin_languages) => {
+            // Loading a plugin on first use would delay the first format of its files
+            let start = Instant::now();
+            match external_bridge.warmup(&plugin_languages) {
+                Ok(()) => debug!(
+                    "Warmed up external formatter for {} languages in {}ms",
+                    plugin_languages.len(),
+                    start.elapsed().as_millis()
+                ),
+                Err(err) => warn!("Failed to warm up external formatter: {err}"),
+            }
+            let languages = BUILTIN_EXTERNAL_PARSER_NAMES
+                .iter()
+                .map(|parser_name| (*parser_name).to_string())
+                .chain(plugin_languages)
+                .collect();
+            let capabilities = external_bridge.capabilities();
+            debug!("External formatter capabilities: {capabilities:?}");
+            Some((external_bridge, languages, capabilities))
+        }
+        Err(err) => {
+            warn!("Failed to initialize external formatter: {err}, proceeding without it");
+            None
+        }
+    }
+}
+
+/// Trims the trailing newline each formatter adds, if `insert_final_newline` is disabled.
+/// Otherwise, ensures the code ends with newlines of the `final_newline` kind,
+/// exactly one unless `trim_trailing_newlines` is disabled, see [`final_newline_count`].
+///
+/// With [`FinalNewline::Auto`], the formatters print LF,
+/// so all line endings are converted to the first line ending of the source text,
+/// e.g. CRLF files stay CRLF instead of fighting with the editor.
+/// See also `SourceFormatter::format`.
+fn apply_final_newline(
+    code: String,
+    source_text: &str,
+    insert_final_newline: bool,
+    trim_trailing_newlines: bool,
+    final_newline: FinalNewline,
+) -> String {
+    let line_ending = match final_newline {
+        FinalNewline::Lf => "\n",
+        FinalNewline::CrLf => "\r\n",
+        FinalNewline::Cr => "\r",
+        FinalNewline::Auto => detect_line_ending(source_text),
+    };
+    let mut code = if final_newline == FinalNewline::Auto && line_ending != "\n" {
+        code.cow_replace("\r\n", "\n").cow_replace('\n', line_ending).into_owned()
+    } else {
+        code
+    };
+
+    let newlines = final_newline_count(source_text, insert_final_newline, trim_trailing_newlines);
+    if newlines == 0 {
+        let trimmed_len = code.trim_end().len();
+        code.truncate(trimmed_len);
+        return code;
+    }
+
+    let trimmed_len = code.trim_end_matches(['\r', '\n']).len();
+    // Empty files stay empty
+    if trimmed_len > 0 {
+        code.truncate(trimmed_len);
+        code.push_str(&line_ending.repeat(newlines));
+    }
+    code
+}
+
+/// Maximum number of indented lines sampled by [`detect_indentation`].
+const DETECT_INDENTATION_LINES: usize = 100;
+
+/// Tab size used with [`DetectedIndent::Tabs`] if the editor does not send one, the same as the default indent width.
+const DEFAULT_TAB_SIZE: u32 = 2;
+
+/// Indentation used by a source text, see [`detect_indentation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DetectedIndent {
+    Tabs,
+    Spaces(u32),
+}
+
+/// Detects the indentation from the first [`DETECT_INDENTATION_LINES`] indented lines.
+/// Tabs win if more lines start with a tab than with spaces.
+/// Otherwise, the width is the most common increase of indentation between two lines.
+/// Returns `None` if nothing is indented.
+fn detect_indentation(source_text: &str) -> Option<DetectedIndent> {
+    let (mut tab_lines, mut space_lines) = (0, 0);
+    // Number of times the indentation increases by `index` spaces
+    let mut increases = [0usize; 9];
+    let mut previous_spaces = 0;
+
+    for line in source_text.lines() {
+        let content = line.trim_start_matches([' ', '\t']);
+        // Blank lines and the ` * ` of block comments do not tell the indentation
+        if content.is_empty() || content.starts_with('*') {
+            continue;
+        }
+
+        let indent = &line[..line.len() - content.len()];
+        if indent.starts_with('\t') {
+            tab_lines += 1;
+            previous_spaces = 0;
+        } else if !indent.contains('\t') {
+            let spaces = indent.len();
+            if spaces > 0 {
+                space_lines += 1;
+            }
+            if let Some(increase) = spaces.checked_sub(previous_spaces)
+                && let Some(count) = increases.get_mut(increase)
+            {
+                *count += 1;
+            }
+            previous_spaces = spaces;
+        }
+
+        if tab_lines + space_lines >= DETECT_INDENTATION_LINES {
+            break;
+        }
+    }
+
+    if tab_lines > space_lines {
+        return Some(DetectedIndent::Tabs);
+    }
+    // Index `0` is not an increase
+    let (width, count) = increases.iter().enumerate().skip(1).max_by_key(|(width, count)| {
+        // Prefer the smaller width on ties
+        (**count, std::cmp::Reverse(*width))
+    })?;
+    #[expect(clippy::cast_possible_truncation)]
+    (*count > 0).then_some(DetectedIndent::Spaces(width as u32))
+}
+
+/// Returns the first line ending of the text, LF if there is none.
+/// Same as `endOfLine: "auto"` of Prettier, mixed line endings are normalized to the first one.
+fn detect_line_ending(text: &str) -> &'static str {
+    match text.find(['\r', '\n']) {
+        Some(index) if text[index..].starts_with("\r\n") => "\r\n",
+        Some(index) if text.as_bytes()[index] == b'\r' => "\r",
+        _ => "\n",
+    }
+}
+
+#[cfg(test)]
+mod tests_builder {
+    use std::sync::Arc;
+
+    use crate::lsp::{
+        server_formatter::{FORMAT_WORKSPACE_COMMAND_ID, ServerFormatterBuilder},
+        tester::{BridgeCall, TestBridge},
+    };
+    use oxc_language_server::{Capabilities, ToolBuilder};
+
+    #[test]
+    fn test_server_capabilities() {
+        use tower_lsp_server::ls_types::{
+            DocumentOnTypeFormattingOptions, OneOf, ServerCapabilities, TextDocumentSyncCapability,
+            TextDocumentSyncOptions,
+        };
+
+        let builder = ServerFormatterBuilder::new(None);
+        let mut capabilities = ServerCapabilities::default();
+
+        builder.server_capabilities(&mut capabilities, &Capabilities::default());
+
+        assert_eq!(capabilities.document_formatting_provider, Some(OneOf::Left(true)));
+        assert_eq!(capabilities.document_range_formatting_provider, Some(OneOf::Left(true)));
+        assert_eq!(
+            capabilities.document_on_type_formatting_provider,
+            Some(DocumentOnTypeFormattingOptions {
+                first_trigger_character: "}".to_string(),
+                more_trigger_character: Some(vec![
+                    ";".to_string(),
+                    "\n".to_string(),
+                    ">".to_string(),
+                ]),
+            })
+        );
+        assert_eq!(
+            capabilities.execute_command_provider.map(|provider| provider.commands),
+            Some(vec![FORMAT_WORKSPACE_COMMAND_ID.to_string()])
+        );
+
+        let mut capabilities = ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Options(
+                TextDocumentSyncOptions::default(),
+            )),
+            ..Default::default()
+        };
+        builder.server_capabilities(&mut capabilities, &Capabilities::default());
+
+        let Some(TextDocumentSyncCapability::Options(sync_options)) =
+            &capabilities.text_document_sync
+        else {
+            panic!("text document sync options should be kept");
+        };
+        assert_eq!(sync_options.will_save_wait_until, Some(true));
+        assert_eq!(
+            capabilities.experimental,
+            Some(serde_json::json!({
+                "documentPasteProvider": true,
+                "documentRangeFormattingProvider": { "rangesSupport": true },
+            }))
+        );
+    }
+
+    #[test]
+    fn test_external_languages() {
+        let builder = ServerFormatterBuilder::new(None);
+        assert!(builder.external.languages.is_empty());
+
+        let builder = ServerFormatterBuilder::new(Some(Arc::new(
+            TestBridge::default().with_init_result(Ok(vec!["svelte".to_string()])),
+        )));
+        assert!(builder.external.bridge.is_some());
+        assert!(builder.external.languages.contains("svelte"));
+        assert!(builder.external.languages.contains("css"));
+        assert!(builder.external.languages.contains("json-stringify"));
+
+        let builder = ServerFormatterBuilder::new(Some(Arc::new(
+            TestBridge::default().with_init_result(Err("failed to load plugins".to_string())),
+        )));
+        assert!(builder.external.bridge.is_none());
+        assert!(builder.external.languages.is_empty());
+    }
+
+    #[test]
+    fn test_warmup() {
+        let bridge =
+            Arc::new(TestBridge::default().with_init_result(Ok(vec!["svelte".to_string()])));
+        ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _));
+        // Only the languages of the loaded plugins are warmed up
+        assert_eq!(bridge.take_calls(), vec![BridgeCall::Warmup(vec!["svelte".to_string()])]);
+
+        // A failed warmup does not disable the external formatter
+        let bridge = TestBridge::default()
+            .with_init_result(Ok(vec!["svelte".to_string()]))
+            .with_warmup_result(Err("failed to load plugin".to_string()));
+        let builder = ServerFormatterBuilder::new(Some(Arc::new(bridge)));
+        assert!(builder.external.bridge.is_some());
+        assert!(builder.external.languages.contains("svelte"));
+    }
+}
+
+#[cfg(test)]
+mod test_watchers {
+    // formatter file watcher-system does not depend on the actual file system,
+    // so we can use a fake directory for testing.
+    const FAKE_DIR: &str = "fixtures/formatter/watchers";
+
+    mod init_watchers {
+        use crate::lsp::{server_formatter::test_watchers::FAKE_DIR, tester::Tester};
+        use serde_json::json;
+
+        #[test]
+        fn test_default_options() {
+            let patterns = Tester::new(FAKE_DIR, json!({})).get_watcher_patterns();
+            assert_eq!(patterns.len(), 5);
+            assert_eq!(patterns[0], ".oxfmtrc.json");
+            assert_eq!(patterns[1], ".oxfmtrc.jsonc");
+            assert_eq!(patterns[2], ".oxfmtrc.js");
+            assert_eq!(patterns[3], ".oxfmtrc.cjs");
+            assert_eq!(patterns[4], ".oxfmtrc.mjs");
+        }
+
+        #[test]
+        fn test_read_prettier_config() {
+            let patterns = Tester::new(FAKE_DIR, json!({ "fmt.readPrettierConfig": true }))
+                .get_watcher_patterns();
+            assert_eq!(patterns.len(), 7);
+            assert_eq!(patterns[5], ".prettierrc");
+            assert_eq!(patterns[6], ".prettierrc.json");
+        }
+
+        #[test]
+        fn test_config_file_names() {
+            let patterns = Tester::new(
+                FAKE_DIR,
+                json!({ "fmt.configFileNames": [".config/oxfmt.json", "oxfmt.json"] }),
+            )
+            .get_watcher_patterns();
+            assert_eq!(patterns, vec![".config/oxfmt.json", "oxfmt.json"]);
+
+            // Empty names fall back to the default config files
+            let patterns =
+                Tester::new(FAKE_DIR, json!({ "fmt.configFileNames": [] })).get_watcher_patterns();
+            assert_eq!(patterns.len(), 5);
+            assert_eq!(patterns[0], ".oxfmtrc.json");
+        }
+
+        #[test]
+        fn test_formatter_custom_config_path() {
+            let patterns = Tester::new(
+                FAKE_DIR,
+                json!({
+                    "fmt.configPath": "configs/formatter.json"
+                }),
+            )
+            .get_watcher_patterns();
+            assert_eq!(patterns.len(), 1);
+            assert_eq!(patterns[0], "configs/formatter.json");
+        }
+
+        #[test]
+        fn test_glob_config_path() {
+            let patterns =
+                Tester::new(FAKE_DIR, json!({ "fmt.configPath": "configs/*.oxfmtrc.json" }))
+                    .get_watcher_patterns();
+            assert_eq!(patterns, vec!["configs/*.oxfmtrc.json"]);
+        }
+
+        #[test]
+        fn test_empty_string_config_path() {
+            let patterns = Tester::new(
+                FAKE_DIR,
+                json!({
+                    "fmt.configPath": ""
+                }),
+            )
+            .get_watcher_patterns();
+            assert_eq!(patterns.len(), 5);
+            assert_eq!(patterns[0], ".oxfmtrc.json");
+            assert_eq!(patterns[1], ".oxfmtrc.jsonc");
+            assert_eq!(patterns[2], ".oxfmtrc.js");
+            assert_eq!(patterns[3], ".oxfmtrc.cjs");
+            assert_eq!(patterns[4], ".oxfmtrc.mjs");
+        }
+    }
+
+    mod handle_watched_file_change {
+        use crate::lsp::{
+            server_formatter::{ServerFormatterBuilder, test_watchers::FAKE_DIR},
+            tester::Tester,
+        };
+        use oxc_language_server::Tool;
+        use serde_json::json;
+        use tower_lsp_server::ls_types::Uri;
+
+        #[test]
+        fn test_config_and_ignore_files() {
+            let tester = Tester::new(FAKE_DIR, json!({}));
+            assert!(tester.handle_watched_file_change("a.ts").tool.is_none());
+            assert!(tester.handle_watched_file_change(".gitignore").tool.is_some());
+            assert!(tester.handle_watched_file_change("nested/.editorconfig").tool.is_some());
+            // Nested config files are merged into the config of their directory
+            assert!(tester.handle_watched_file_change("nested/.oxfmtrc.json").tool.is_some());
+        }
+
+        #[test]
+        fn test_formatter_custom_config_path() {
+            let tester = Tester::new(
+                FAKE_DIR,
+                json!({
+                    "fmt.configPath": "configs/formatter.json"
+                }),
+            );
+            assert!(tester.handle_watched_file_change(".oxfmtrc.json").tool.is_none());
+            assert!(tester.handle_watched_file_change("configs/formatter.json").tool.is_some());
+        }
+
+        #[test]
+        fn test_read_prettier_config() {
+            let tester = Tester::new(FAKE_DIR, json!({}));
+            assert!(tester.handle_watched_file_change(".prettierrc").tool.is_none());
+
+            let tester = Tester::new(FAKE_DIR, json!({ "fmt.readPrettierConfig": true }));
+            assert!(tester.handle_watched_file_change(".prettierrc").tool.is_some());
+            assert!(tester.handle_watched_file_change(".prettierrc.json").tool.is_some());
+        }
+
+        #[test]
+        fn test_config_file_names() {
+            let tester =
+                Tester::new(FAKE_DIR, json!({ "fmt.configFileNames": [".config/oxfmt.json"] }));
+            assert!(tester.handle_watched_file_change(".oxfmtrc.json").tool.is_none());
+            assert!(tester.handle_watched_file_change(".config/oxfmt.json").tool.is_some());
+        }
+
+        #[test]
+        fn test_unchanged_content() {
+            let root_dir = std::env::temp_dir().join("oxfmt_test_unchanged_content");
+            std::fs::create_dir_all(&root_dir).unwrap();
+            let config_path = root_dir.join(".oxfmtrc.json");
+            std::fs::write(&config_path, r#"{ "semi": false }"#).unwrap();
+
+            let root_uri = Uri::from_file_path(&root_dir).unwrap();
+            let config_uri = Uri::from_file_path(&config_path).unwrap();
+            let builder = ServerFormatterBuilder::new(None);
+            let handle_change = || {
+                builder.build(&root_uri, json!({})).handle_watched_file_change(
+                    &builder,
+                    &config_uri,
+                    &root_uri,
+                    json!({}),
+                )
+            };
+
+            assert!(handle_change().tool.is_some());
+            // Another event for the same save, e.g. truncate and write
+            assert!(handle_change().tool.is_none());
+
+            std::fs::write(&config_path, r#"{ "semi": true }"#).unwrap();
+            assert!(handle_change().tool.is_some());
+            assert!(handle_change().tool.is_none());
+
+            // Deleting the file is a change too
+            std::fs::remove_file(&config_path).unwrap();
+            assert!(handle_change().tool.is_some());
+            assert!(handle_change().tool.is_none());
+        }
+
+        #[test]
+        fn test_unchanged_content_multi_root() {
+            // `.editorconfig` of the parent directory is watched by both workspaces
+            let root_dir = std::env::temp_dir().join("oxfmt_test_unchanged_content_multi_root");
+            std::fs::create_dir_all(root_dir.join("a")).unwrap();
+            std::fs::create_dir_all(root_dir.join("b")).unwrap();
+            let editorconfig_path = root_dir.join(".editorconfig");
+            std::fs::write(&editorconfig_path, "[*]\nindent_style = tab\n").unwrap();
+
+            let a_uri = Uri::from_file_path(root_dir.join("a")).unwrap();
+            let b_uri = Uri::from_file_path(root_dir.join("b")).unwrap();
+            let editorconfig_uri = Uri::from_file_path(&editorconfig_path).unwrap();
+            let builder = ServerFormatterBuilder::new(None);
+            let handle_change = |root_uri: &Uri| {
+                builder.build(root_uri, json!({})).handle_watched_file_change(
+                    &builder,
+                    &editorconfig_uri,
+                    root_uri,
+                    json!({}),
+                )
+            };
+
+            // Each workspace rebuilds its own formatter
+            assert!(handle_change(&a_uri).tool.is_some());
+            assert!(handle_change(&b_uri).tool.is_some());
+            assert!(handle_change(&a_uri).tool.is_none());
+            assert!(handle_change(&b_uri).tool.is_none());
+        }
+    }
+
+    mod handle_configuration_change {
+        use crate::lsp::{server_formatter::test_watchers::FAKE_DIR, tester::Tester};
+        use oxc_language_server::ToolRestartChanges;
+        use serde_json::json;
+
+        #[test]
+        fn test_no_change() {
+            let ToolRestartChanges { watch_patterns, .. } =
+                Tester::new(FAKE_DIR, json!({})).handle_configuration_change(json!({}));
+
+            assert!(watch_patterns.is_none());
+        }
+
+        #[test]
+        fn test_formatter_custom_config_path() {
+            let ToolRestartChanges { watch_patterns, .. } = Tester::new(FAKE_DIR, json!({}))
+                .handle_configuration_change(json!({
+                    "fmt.configPath": "configs/formatter.json"
+                }));
+
+            assert!(watch_patterns.is_some());
+            assert_eq!(watch_patterns.as_ref().unwrap().len(), 1);
+            assert_eq!(watch_patterns.as_ref().unwrap()[0], "configs/formatter.json");
+        }
+
+        #[test]
+        fn test_format_options_only() {
+            // The formatter is rebuilt with the same config, the watchers are kept
+            let ToolRestartChanges { tool, watch_patterns } = Tester::new(FAKE_DIR, json!({}))
+                .handle_configuration_change(json!({
+                    "fmt.organizeImports": true,
+                    "fmt.includePatterns": ["src/**"]
+                }));
+
+            assert!(tool.is_some());
+            assert!(watch_patterns.is_none());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+    };
+
+    use cow_utils::CowUtils;
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{
+        DiagnosticSeverity, FormattingOptions, Position, Range, TextEdit, Uri,
+    };
+
+    use super::{
+        CheckResult, ConfigResolver, DetectedIndent, FinalNewline, FormatError, FormatFileStrategy,
+        FormatOutcome, FormatSettings, MAX_CONSECUTIVE_EXTERNAL_FAILURES, ServerFormatter,
+        ServerFormatterBuilder, ServerFormatterConfig, SharedExternalFormatter,
+        apply_final_newline, detect_indentation, is_statement_boundary, shebang_interpreter,
+    };
+    use crate::lsp::{
+        external_formatter_bridge::{BridgeCapabilities, BridgeError, ExternalFormatterBridge},
+        tester::{BridgeCall, TestBridge, Tester, get_file_path, get_file_uri},
+    };
+
+    #[test]
+    fn test_formatter() {
+        Tester::new(
+            "test/fixtures/lsp/basic",
+            json!({
+                "fmt.experimental": true
+            }),
+        )
+        .format_and_snapshot_single_file("basic.ts");
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&root_uri, json!({ "fmt.maxFileSizeBytes": 20 }));
+
+        // Files read from disk
+        assert!(formatter.run_format(&uri, None, None, &AtomicBool::new(false)).is_none());
+        // Opened files
+        let large = "const a   =   1;\n".repeat(2);
+        assert!(formatter.run_format(&uri, Some(&large), None, &AtomicBool::new(false)).is_none());
+        let small = "const a   =   1;\n";
+        assert!(formatter.run_format(&uri, Some(small), None, &AtomicBool::new(false)).is_some());
+    }
+
+    #[test]
+    fn test_gzip_fixture() {
+        // Only `compressed.ts.gz` exists, it is decompressed on load
+        Tester::new("test/fixtures/lsp/gzip", json!({}))
+            .format_and_snapshot_single_file("compressed.ts");
+    }
+
+    #[test]
+    fn test_print_width() {
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let source = "const result = someFunction(argumentNumberOne, argumentNumberTwo, argumentNumberThree, argumentNumberFour);\n";
+        let wrapped = "const result = someFunction(\n  argumentNumberOne,\n  argumentNumberTwo,\n  argumentNumberThree,\n  argumentNumberFour,\n);\n";
+        let format = |dir, options| {
+            ServerFormatterBuilder::new(None)
+                .build(&Tester::get_root_uri(dir), options)
+                .format(&strategy, source, None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        assert_eq!(format("test/fixtures/lsp/basic", json!({ "fmt.printWidth": 40 })), wrapped);
+        assert_eq!(format("test/fixtures/lsp/basic", json!({ "fmt.printWidth": 120 })), source);
+        // The default width is used without the option
+        assert_eq!(format("test/fixtures/lsp/basic", json!({})), wrapped);
+        // The config wins over the option
+        assert_eq!(
+            format("test/fixtures/lsp/print_width", json!({ "fmt.printWidth": 40 })),
+            source
+        );
+    }
+
+    #[test]
+    fn test_max_preserved_blank_lines() {
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let source = "const a = 1;\n\n\n\nconst b = 2;\n";
+        let format = |options| {
+            ServerFormatterBuilder::new(None)
+                .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), options)
+                .format(&strategy, source, None, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        assert_eq!(
+            format(json!({ "fmt.maxPreservedBlankLines": 0 })),
+            "const a = 1;\nconst b = 2;\n"
+        );
+        assert_eq!(
+            format(json!({ "fmt.maxPreservedBlankLines": 2 })),
+            "const a = 1;\n\n\nconst b = 2;\n"
+        );
+        assert_eq!(
+            format(json!({ "fmt.maxPreservedBlankLines": 5 })),
+            "const a = 1;\n\n\n\nconst b = 2;\n"
+        );
+        // Collapsed to one blank line without the option
+        assert_eq!(format(json!({})), "const a = 1;\n\nconst b = 2;\n");
+    }
+
+    #[test]
+    fn test_format_untitled_document() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = Uri::from_str("untitled:Untitled-1").unwrap();
+        let format = |language_id: &str, content: &str| {
+            formatter.run_format_untitled(&uri, language_id, content, None, &AtomicBool::new(false))
+        };
+
+        let edits = format("typescript", "const a: number=1").unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new(Position::new(0, 15), Position::new(0, 17)));
+        assert_eq!(edits[0].new_text, " = 1;\n");
+        assert_eq!(format("json", "{\"a\":1}\n").unwrap().len(), 1);
+        assert_eq!(format("typescript", "const a = 1;\n"), Some(vec![]));
+
+        // The language decides how to parse the content
+        assert_eq!(format("javascript", "const a: number = 1;\n"), None);
+        // Unknown languages, and languages of the external formatter without it
+        assert_eq!(format("plaintext", "a"), None);
+        assert_eq!(format("css", "a{}"), None);
+    }
+
+    #[test]
+    fn test_client_formatting_options() {
+        fn format(config: serde_json::Value) -> String {
+            let mut config_resolver = ConfigResolver::from_value(config);
+            config_resolver.build_and_validate().expect("config should be valid");
+            let formatter = ServerFormatter::new(config_resolver, ServerFormatterConfig::default());
+            let options =
+                FormattingOptions { tab_size: 4, insert_spaces: false, ..Default::default() };
+            let edits = formatter
+                .run_format(
+                    &get_file_uri("test/fixtures/lsp/basic/basic.ts"),
+                    Some("function a() {\nreturn 1;\n}\n"),
+                    Some(&options),
+                    &AtomicBool::new(false),
+                )
+                .expect("file should be formatted");
+            assert_eq!(edits.len(), 1);
+            edits[0].new_text.clone()
+        }
+
+        // Client options are used when the config does not set the indentation
+        assert_eq!(format(json!({})), "\t");
+        // Config wins over client options
+        assert_eq!(format(json!({ "useTabs": false, "tabWidth": 2 })), "  ");
+        assert_eq!(format(json!({ "useTabs": false })), "    ");
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        let mut config_resolver = ConfigResolver::from_value(json!({}));
+        config_resolver.build_and_validate().expect("config should be valid");
+        let formatter = ServerFormatter::new(
+            config_resolver,
+            ServerFormatterConfig {
+                external: SharedExternalFormatter {
+                    // Every pass changes the output
+                    bridge: Some(Arc::new(
+                        TestBridge::default()
+                            .with_format(|code| Ok(Some(format!("{code}/* */\n")))),
+                    )),
+                    languages: std::iter::once("css".to_string()).collect(),
+                    ..Default::default()
+                },
+                settings: FormatSettings { verify_idempotent: true, ..Default::default() },
+                ..Default::default()
+            },
+        );
+        let is_cancelled = AtomicBool::new(false);
+
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        assert!(formatter.is_idempotent(&strategy, "const a = 1;\n", None, &is_cancelled));
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.css")).unwrap();
+        assert!(!formatter.is_idempotent(&strategy, "a {\n}\n", None, &is_cancelled));
+    }
+
+    #[test]
+    fn test_format_source() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/root_config"), json!({}));
+
+        // The config of the workspace is applied, `semi: false`
+        assert_eq!(
+            formatter.format_source("const a   =   1;", "ts", None),
+            Some("const a = 1\n".into())
+        );
+        assert_eq!(
+            formatter.format_source(r#"{"a":1}"#, "tsconfig.json", None),
+            Some("{ \"a\": 1 }\n".into())
+        );
+        // Not supported
+        assert_eq!(formatter.format_source("a {}", "css", None), None);
+        assert_eq!(formatter.format_source("a", "unknown", None), None);
+        // Parse errors
+        assert_eq!(formatter.format_source("const a = ;", "ts", None), None);
+        // The parser takes precedence over the hint
+        assert_eq!(
+            formatter.format_source("const a:number   =   1;", "txt", Some("typescript")),
+            Some("const a: number = 1\n".into())
+        );
+        assert_eq!(formatter.format_source("const a = 1;", "ts", Some("unknown")), None);
+    }
+
+    #[test]
+    fn test_detect_indentation() {
+        assert_eq!(detect_indentation("a\n  b\n    c\n  d\n"), Some(DetectedIndent::Spaces(2)));
+        assert_eq!(detect_indentation("a\n    b\n\n    c\n"), Some(DetectedIndent::Spaces(4)));
+        assert_eq!(detect_indentation("a\n\tb\n\t\tc\n"), Some(DetectedIndent::Tabs));
+        // Block comments are skipped
+        assert_eq!(
+            detect_indentation("/**\n * a\n */\nb\n    c\n"),
+            Some(DetectedIndent::Spaces(4))
+        );
+        assert_eq!(detect_indentation("a\nb\n"), None);
+    }
+
+    #[test]
+    fn test_shebang_interpreter() {
+        assert_eq!(shebang_interpreter("#!/usr/bin/gjs\nlet a;\n"), Some("gjs"));
+        assert_eq!(shebang_interpreter("#!/usr/bin/env node\n"), Some("node"));
+        assert_eq!(shebang_interpreter("#!/usr/bin/env -S NODE_ENV=test gjs -m\n"), Some("gjs"));
+        assert_eq!(shebang_interpreter("\u{feff}#!/usr/bin/env gjs"), Some("gjs"));
+        assert_eq!(shebang_interpreter("#!/usr/bin/env\n"), None);
+        assert_eq!(shebang_interpreter("// #!/usr/bin/gjs\n"), None);
+    }
+
+    #[test]
+    fn test_skip_shebang_interpreters() {
+        let tester = Tester::new(
+            "test/fixtures/lsp/basic",
+            json!({ "fmt.skipShebangInterpreters": ["gjs"] }),
+        );
+        let format = |content: &str| tester.format("script.js", Some(content));
+        assert!(format("#!/usr/bin/env gjs\nconst a   =   1;\n").is_none());
+        assert!(format("#!/usr/bin/env node\nconst a   =   1;\n").is_some());
+        assert!(format("const a   =   1;\n").is_some());
+
+        // Nothing is skipped by default
+        let tester = Tester::new("test/fixtures/lsp/basic", json!({}));
+        assert!(
+            tester.format("script.js", Some("#!/usr/bin/env gjs\nconst a   =   1;\n")).is_some()
+        );
+    }
+
+    #[test]
+    fn test_detect_indentation_option() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("a.ts")).unwrap();
+        let format = |options, indent| {
+            ServerFormatterBuilder::new(None)
+                .build(&root_uri, options)
+                .format(&strategy, "if (a) {\n    b( );\n}\n", indent, &AtomicBool::new(false))
+                .unwrap()
+        };
+
+        // The default indentation is used
+        assert_eq!(format(json!({}), None), "if (a) {\n  b();\n}\n");
+        // The indentation of the file is kept, even if the editor sends another one
+        let options = json!({ "fmt.detectIndentation": true });
+        assert_eq!(format(options.clone(), None), "if (a) {\n    b();\n}\n");
+        assert_eq!(format(options, Some((false, 2))), "if (a) {\n    b();\n}\n");
+    }
+
+    #[test]
+    fn test_check() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/ignore-pattern"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/ignore-pattern/not-ignored.js");
+
+        // `semi: false` is set in the config
+        assert_eq!(formatter.check(&uri, Some("const a = 1\n")), CheckResult::Formatted);
+        assert_eq!(formatter.check(&uri, Some("const a = 1;\n")), CheckResult::NeedsFormatting);
+        assert_eq!(formatter.check(&uri, Some("const a = ;\n")), CheckResult::ParseError);
+        assert_eq!(
+            formatter.check(&get_file_uri("test/fixtures/lsp/ignore-pattern/ignored.ts"), None),
+            CheckResult::Ignored
+        );
+        assert_eq!(
+            formatter.check(&get_file_uri("test/fixtures/lsp/ignore-pattern/unknown.txt"), None),
+            CheckResult::Skipped
+        );
+
+        // Files of the external formatter are skipped unless it formats them
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/prettier_only");
+        let uri = get_file_uri("test/fixtures/lsp/prettier_only/styles.css");
+        let check = |bridge: Option<TestBridge>| {
+            ServerFormatterBuilder::new(bridge.map(|bridge| Arc::new(bridge) as _))
+                .build(&root_uri, json!({}))
+                .check(&uri, Some("a{}\n"))
+        };
+        assert_eq!(check(None), CheckResult::Skipped);
+        assert_eq!(
+            check(Some(TestBridge::default().with_result(
+                "css",
+                "styles.css",
+                Err("Unexpected token")
+            ))),
+            CheckResult::Skipped
+        );
+        assert_eq!(
+            check(Some(TestBridge::default().with_result("css", "styles.css", Ok(None)))),
+            CheckResult::Skipped
+        );
+        assert_eq!(check(Some(TestBridge::default())), CheckResult::Formatted);
+    }
+
+    #[test]
+    fn test_run_format_with_stats() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+
+        let (edits, stats) = formatter.run_format_with_stats(&uri, Some("const a   =   1;\n"));
+        assert_eq!(edits.map(|edits| edits.len()), Some(1));
+        assert_eq!(stats.bytes_in, 17);
+        assert_eq!(stats.bytes_out, 13);
+        assert_eq!(stats.edit_count, 1);
+
+        // Parse errors are still measured
+        let (edits, stats) = formatter.run_format_with_stats(&uri, Some("const a = ;\n"));
+        assert!(edits.is_none());
+        assert_eq!(stats.bytes_in, 12);
+        assert_eq!(stats.bytes_out, 0);
+    }
+
+    #[test]
+    fn test_run_format_result() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+
+        assert!(matches!(
+            formatter.run_format_result(&uri, Some("const a   =   1;\n")),
+            Ok(FormatOutcome::Edits(edits)) if edits.len() == 1
+        ));
+        assert_eq!(
+            formatter.run_format_result(&uri, Some("const a = 1;\n")),
+            Ok(FormatOutcome::Unchanged)
+        );
+        assert_eq!(
+            formatter.run_format_result(&uri, Some("const a = ;\n")),
+            Err(FormatError::ParseError)
+        );
+        assert_eq!(
+            formatter.run_format_result(&get_file_uri("test/fixtures/lsp/basic/file.xyz"), None),
+            Err(FormatError::Unsupported)
+        );
+        assert!(matches!(
+            formatter.run_format_result(&get_file_uri("test/fixtures/lsp/basic/missing.ts"), None),
+            Err(FormatError::ReadFailed(_))
+        ));
+
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/ignore-file"), json!({}));
+        assert_eq!(
+            formatter
+                .run_format_result(&get_file_uri("test/fixtures/lsp/ignore-file/ignored.ts"), None),
+            Err(FormatError::Ignored)
+        );
+
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/prettier_only"), json!({}));
+        assert_eq!(
+            formatter.run_format_result(
+                &get_file_uri("test/fixtures/lsp/prettier_only/styles.css"),
+                None
+            ),
+            Err(FormatError::ExternalFormatterUnavailable)
+        );
+    }
+
+    #[test]
+    fn test_format_with_parse_errors() {
+        let tester = Tester::new("test/fixtures/lsp/basic", json!({}));
+        assert!(tester.format("basic.ts", Some("const a   =   1;\n")).is_some());
+        // The unsaved content can not be parsed, the save must not be blocked by any edits
+        assert!(tester.format("basic.ts", Some("const a   =   ;\n")).is_none());
+    }
+
+    #[test]
+    fn test_format_valid_regions() {
+        let tester =
+            Tester::new("test/fixtures/lsp/basic", json!({ "fmt.formatValidRegions": true }));
+        let source = "const a   =   1;\n\nfunction f() {\n  let x =   ;\n}\n\nconst   b=2\n";
+        // Only the statements before and after the broken function are formatted
+        assert_eq!(
+            tester.format("basic.ts", Some(source)),
+            Some(vec![
+                TextEdit::new(Range::new(Position::new(0, 8), Position::new(0, 13)), "=".into()),
+                TextEdit::new(
+                    Range::new(Position::new(6, 6), Position::new(6, 11)),
+                    "b = 2;".into()
+                ),
+            ])
+        );
+
+        // No valid region
+        assert!(tester.format("basic.ts", Some("const a   =   ;\n")).is_none());
+        // Disabled by default
+        let tester = Tester::new("test/fixtures/lsp/basic", json!({}));
+        assert!(tester.format("basic.ts", Some(source)).is_none());
+    }
+
+    #[test]
+    fn test_is_statement_boundary() {
+        assert!(is_statement_boundary("const a = 1;"));
+        assert!(is_statement_boundary("@decorator\nclass A {}"));
+        assert!(!is_statement_boundary("  const a = 1;"));
+        assert!(!is_statement_boundary("(a, b);"));
+        assert!(!is_statement_boundary("+ 1;"));
+        assert!(!is_statement_boundary("else {}"));
+        assert!(!is_statement_boundary("instanceof A;"));
+        assert!(!is_statement_boundary(""));
+    }
+
+    #[test]
+    fn test_apply_final_newline() {
+        let code = "a\nb\n".to_string();
+
+        assert_eq!(apply_final_newline(code.clone(), "", true, true, FinalNewline::Lf), "a\nb\n");
+        assert_eq!(
+            apply_final_newline(code.clone(), "", true, true, FinalNewline::CrLf),
+            "a\nb\r\n"
+        );
+        assert_eq!(apply_final_newline(code.clone(), "", true, true, FinalNewline::Cr), "a\nb\r");
+        // Exactly one trailing newline
+        assert_eq!(
+            apply_final_newline("a\n\r\n\n".to_string(), "", true, true, FinalNewline::Lf),
+            "a\n"
+        );
+        // Empty files stay empty
+        assert_eq!(apply_final_newline(String::new(), "", true, true, FinalNewline::CrLf), "");
+
+        // The line ending of the source text is used
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\nb", true, true, FinalNewline::Auto),
+            "a\nb\n"
+        );
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\r\nb", true, true, FinalNewline::Auto),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\rb", true, true, FinalNewline::Auto),
+            "a\rb\r"
+        );
+        assert_eq!(
+            apply_final_newline(code.clone(), "a", true, true, FinalNewline::Auto),
+            "a\nb\n"
+        );
+        // Mixed line endings follow the first one
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\r\nb\nc\n", true, true, FinalNewline::Auto),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\nb\r\nc\r\n", true, true, FinalNewline::Auto),
+            "a\nb\n"
+        );
+
+        // The trailing newline is trimmed
+        assert_eq!(apply_final_newline(code.clone(), "", false, true, FinalNewline::CrLf), "a\nb");
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\r\nb", false, true, FinalNewline::Auto),
+            "a\r\nb"
+        );
+
+        // The trailing blank lines of the source are kept
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\n\n\n", true, false, FinalNewline::Lf),
+            "a\nb\n\n\n"
+        );
+        assert_eq!(
+            apply_final_newline(code.clone(), "a\r\n\r\n", false, false, FinalNewline::Auto),
+            "a\r\nb\r\n\r\n"
+        );
+        assert_eq!(apply_final_newline(code.clone(), "a", true, false, FinalNewline::Lf), "a\nb\n");
+        assert_eq!(apply_final_newline(code, "a", false, false, FinalNewline::Lf), "a\nb");
+    }
+
+    #[test]
+    fn test_end_of_line() {
+        let tester = Tester::new("test/fixtures/lsp/end_of_line", json!({}));
+        let crlf = "const a = 1;\r\nconst b = 2;\r\n";
+
+        // `end_of_line` is not set, the CRLF line endings are kept
+        assert_eq!(tester.format("auto.ts", Some(crlf)), Some(vec![]));
+        let edits = tester
+            .format("auto.ts", Some("const a   =   1;\r\nconst b = 2;\r\n"))
+            .expect("file should be formatted");
+        assert_eq!(edits.len(), 1);
+        assert!(!edits[0].new_text.contains('\n'));
+
+        // `end_of_line = lf` converts them
+        let edits = tester.format("lf/lf.ts", Some(crlf)).expect("file should be formatted");
+        assert!(!edits.is_empty());
+    }
+
+    #[test]
+    fn test_crlf_minimal_edits() {
+        let tester = Tester::new("test/fixtures/lsp/end_of_line", json!({}));
+        let unchanged = "const a = 1;\r\n".repeat(50);
+        let content = format!("{unchanged}function f(a,b){{return a+b}}\r\n{unchanged}");
+
+        // Only the changed lines are edited, and the inserted line breaks are CRLF too
+        let edits = tester.format("auto.ts", Some(&content)).expect("file should be formatted");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start.line, 50);
+        assert_eq!(edits[0].range.end.line, 50);
+        assert_eq!(edits[0].new_text.matches("\r\n").count(), 2);
+        assert_eq!(edits[0].new_text.matches('\n').count(), 2);
+    }
+
+    #[test]
+    fn test_bom() {
+        let tester = Tester::new("test/fixtures/lsp/bom", json!({}));
+
+        let edits = tester.format("bom.ts", None).expect("file should be formatted");
+        assert_eq!(edits.len(), 1);
+        // The BOM is kept as is
+        assert!(edits[0].range.start.character > 0);
+        assert!(!edits[0].new_text.contains('\u{feff}'));
+
+        assert_eq!(tester.format("bom.ts", Some("\u{feff}const a = 1;\n")), Some(vec![]));
+        // Removing the BOM is up to the user
+        assert_eq!(tester.format("bom.ts", Some("const a = 1;\n")), Some(vec![]));
+    }
+
+    #[test]
+    fn test_vue_formatted_by_external_formatter() {
+        let bridge =
+            Arc::new(TestBridge::default().with_format(|code| {
+                Ok(Some(code.cow_replace("msg   =   ", "msg = ").into_owned()))
+            }));
+        let formatter = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/vue"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/vue/App.vue");
+
+        let edits = formatter
+            .run_format(&uri, None, None, &AtomicBool::new(false))
+            .expect("vue file should be formatted");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new(Position::new(5, 10), Position::new(5, 15)));
+        assert_eq!(edits[0].new_text, "=");
+        assert_eq!(bridge.take_parser_names(), vec!["vue".to_string()]);
+    }
+
+    #[test]
+    fn test_markdown_formatted_by_external_formatter() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/markdown");
+        // Code blocks are formatted by the external formatter itself
+        let bridge =
+            Arc::new(TestBridge::default().with_format(|code| {
+                Ok(Some(code.cow_replace("a   =   1", "a = 1;").into_owned()))
+            }));
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _)).build(&root_uri, json!({}));
+
+        for file in ["README.md", "page.mdx"] {
+            let uri = get_file_uri(&format!("test/fixtures/lsp/markdown/{file}"));
+            let edits = formatter
+                .run_format(&uri, None, None, &AtomicBool::new(false))
+                .expect("markdown file should be formatted");
+            assert_eq!(edits.len(), 1);
+            assert_eq!(edits[0].new_text, "= 1;");
+        }
+        // The whole file is sent, and embedded code is formatted unless disabled
+        let requests = bridge
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                BridgeCall::FormatFile { parser_name, options, .. } => {
+                    Some((parser_name, options["embeddedLanguageFormatting"].clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests,
+            vec![("markdown".to_string(), json!("auto")), ("mdx".to_string(), json!("auto"))]
+        );
+
+        // Skipped without the external formatter
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/markdown/README.md");
+        assert!(formatter.run_format(&uri, None, None, &AtomicBool::new(false)).is_none());
+    }
+
+    #[test]
+    fn test_external_formatter_with_test_bridge() {
+        let bridge = TestBridge::default()
+            .with_result("css", "styles.css", Ok(Some("a {\n  color: red;\n}\n")))
+            .with_result("json-stringify", "package.json", Err("Unexpected token"));
+        let tester = Tester::new("test/fixtures/lsp/prettier_only", json!({}))
+            .with_external_bridge(Arc::new(bridge));
+
+        assert_eq!(
+            tester.format("styles.css", None),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(0, 2), Position::new(1, 8)),
+                "{\n  color: ".into()
+            )])
+        );
+        // Formatting errors are not applied
+        assert_eq!(tester.format("package.json", None), None);
+
+        // No parser or plugin can handle the file, and it has no native fallback
+        let bridge = TestBridge::default().with_result("css", "styles.css", Ok(None));
+        let tester = Tester::new("test/fixtures/lsp/prettier_only", json!({}))
+            .with_external_bridge(Arc::new(bridge));
+        assert_eq!(tester.format("styles.css", None), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_external_formatter_non_utf8_file_name() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let bridge = TestBridge::default().with_result(
+            "css",
+            "st\u{fffd}yles.css",
+            Ok(Some("a {\n  color: red;\n}\n")),
+        );
+        let formatter = ServerFormatterBuilder::new(Some(Arc::new(bridge)))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/prettier_only"), json!({}));
+        let path = PathBuf::from(OsStr::from_bytes(b"st\xffyles.css"));
+        let strategy = FormatFileStrategy::ExternalFormatter { path, parser_name: "css" };
+
+        // The bridge receives a best-effort name instead of an empty one
+        assert_eq!(
+            formatter.format(&strategy, "a{color:red}\n", None, &AtomicBool::new(false)).unwrap(),
+            "a {\n  color: red;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_yaml_keep_block_scalar() {
+        let tester = Tester::new("test/fixtures/lsp/prettier_only", json!({}))
+            .with_external_bridge(Arc::new(TestBridge::default()));
+
+        // Trailing line breaks are part of the value of the block scalar
+        assert_eq!(tester.format("config.yaml", Some("a: |+\n  text\n\n\n")), Some(vec![]));
+        assert_eq!(
+            tester.format("config.yaml", Some("a: |\n  text\n\n\n")),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(2, 0), Position::new(4, 0)),
+                String::new()
+            )])
+        );
+
+        // Without the external formatter, YAML files are skipped
+        let tester = Tester::new("test/fixtures/lsp/prettier_only", json!({}));
+        assert_eq!(tester.format("config.yaml", Some("a: |+\n  text\n\n\n")), None);
+    }
+
+    #[test]
+    fn test_format_cache() {
+        let bridge =
+            Arc::new(TestBridge::default().with_format(|code| {
+                Ok(Some(code.cow_replace("msg   =   ", "msg = ").into_owned()))
+            }));
+        let formatter = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/vue"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/vue/App.vue");
+        let content = std::fs::read_to_string(get_file_path("test/fixtures/lsp/vue/App.vue"))
+            .expect("fixture should exist");
+
+        let edits = formatter.run_format(&uri, Some(&content), None, &AtomicBool::new(false));
+        assert!(edits.is_some());
+        assert_eq!(
+            formatter.run_format(&uri, Some(&content), None, &AtomicBool::new(false)),
+            edits
+        );
+        assert_eq!(bridge.take_parser_names().len(), 1);
+
+        // Changed content is formatted again
+        let changed = content.cow_replace("hello", "world");
+        assert!(
+            formatter.run_format(&uri, Some(&changed), None, &AtomicBool::new(false)).is_some()
+        );
+        assert_eq!(bridge.take_parser_names().len(), 1);
+
+        // The file read from disk is the same as the first content
+        formatter.run_format(&uri, None, None, &AtomicBool::new(false));
+        assert!(bridge.take_parser_names().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_format() {
+        // Emulates `$/cancelRequest` arriving while the external formatter is running
+        let bridge = TestBridge::default()
+            .with_format(|code| Ok(Some(code.cow_replace("msg   =   ", "msg = ").into_owned())))
+            .with_cancellation();
+        let formatter = ServerFormatterBuilder::new(Some(Arc::new(bridge)))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/vue"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/vue/App.vue");
+        assert!(formatter.run_format(&uri, None, None, &AtomicBool::new(false)).is_none());
+
+        // Cancelled before the edits are computed
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/basic"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        assert!(formatter.run_format(&uri, None, None, &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn test_external_formatter_circuit_breaker() {
+        let fail = Arc::new(AtomicBool::new(true));
+        let reject = Arc::new(AtomicBool::new(false));
+        let bridge = Arc::new(TestBridge::default().with_format({
+            let fail = Arc::clone(&fail);
+            let reject = Arc::clone(&reject);
+            move |code| {
+                if fail.load(Ordering::Relaxed) {
+                    Err(BridgeError::Failed("crashed".to_string()))
+                } else if reject.load(Ordering::Relaxed) {
+                    Err(BridgeError::Rejected("SyntaxError".to_string()))
+                } else {
+                    Ok(Some(code.to_string()))
+                }
+            }
+        }));
+        let builder = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _));
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/vue");
+        let formatter = builder.build(&root_uri, json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/vue/App.vue");
+        // Different content every time, so the format cache is not hit
+        let format = |formatter: &ServerFormatter, i: usize| {
+            formatter.run_format(
+                &uri,
+                Some(&format!("<p>{i}</p>\n")),
+                None,
+                &AtomicBool::new(false),
+            )
+        };
+
+        for i in 0..MAX_CONSECUTIVE_EXTERNAL_FAILURES - 1 {
+            assert!(format(&formatter, i).is_none());
+        }
+        // A success resets the counter
+        fail.store(false, Ordering::Relaxed);
+        assert!(format(&formatter, 100).is_some());
+        fail.store(true, Ordering::Relaxed);
+        for i in 0..MAX_CONSECUTIVE_EXTERNAL_FAILURES - 1 {
+            assert!(format(&formatter, i).is_none());
+        }
+        // Files the external formatter rejects, e.g. with syntax errors, are not failures of the bridge
+        fail.store(false, Ordering::Relaxed);
+        reject.store(true, Ordering::Relaxed);
+        for i in 0..2 * MAX_CONSECUTIVE_EXTERNAL_FAILURES {
+            assert!(format(&formatter, i).is_none());
+        }
+        fail.store(true, Ordering::Relaxed);
+        for i in 0..MAX_CONSECUTIVE_EXTERNAL_FAILURES {
+            assert!(format(&formatter, i).is_none());
+        }
+        assert_eq!(bridge.take_parser_names().len(), 5 * MAX_CONSECUTIVE_EXTERNAL_FAILURES - 1);
+
+        // The external formatter is not called anymore, even by a rebuilt formatter
+        fail.store(false, Ordering::Relaxed);
+        reject.store(false, Ordering::Relaxed);
+        assert!(format(&formatter, 200).is_none());
+        assert!(format(&builder.build(&root_uri, json!({})), 200).is_none());
+        assert!(bridge.take_parser_names().is_empty());
+    }
+
+    #[test]
+    fn test_external_formatter_unsupported_file() {
+        let bridge = Arc::new(TestBridge::default().with_format(|_| Ok(None)));
+        let builder = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _));
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/vue");
+        let formatter = builder.build(&root_uri, json!({}));
+
+        // Without a native formatter, the file is skipped,
+        // and it is not counted as a failure of the external formatter
+        let uri = get_file_uri("test/fixtures/lsp/vue/App.vue");
+        for i in 0..=MAX_CONSECUTIVE_EXTERNAL_FAILURES {
+            let content = format!("<p>{i}</p>\n");
+            assert!(
+                formatter.run_format(&uri, Some(&content), None, &AtomicBool::new(false)).is_none()
+            );
+        }
+        assert_eq!(bridge.take_parser_names().len(), MAX_CONSECUTIVE_EXTERNAL_FAILURES + 1);
+
+        // `json-stringify` files fall back to the native JSON formatter
+        let uri = get_file_uri("test/fixtures/lsp/vue/config.importmap");
+        let edits = formatter
+            .run_format(&uri, Some("{\"imports\":{}}\n"), None, &AtomicBool::new(false))
+            .expect("file should be formatted natively");
+        assert_eq!(bridge.take_parser_names().len(), 1);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, r#" "imports": {} "#);
+    }
+
+    #[test]
+    fn test_sort_json_keys() {
+        let tester = Tester::new("test/fixtures/lsp/sort_json_keys", json!({}));
+        let edits = tester.format("data.json", None).expect("file should be formatted");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new(Position::new(1, 3), Position::new(3, 21)));
+        assert_eq!(
+            edits[0].new_text,
+            "dependencies\": { \"a\": \"1\", \"b\": \"1\" },\n  \"files\": [\"z\", \"a\"],\n  \"name\": \"a\""
+        );
+
+        // Excluded by `overrides`
+        assert_eq!(tester.format("tsconfig.json", None), Some(vec![]));
+    }
+
+    #[test]
+    fn test_prettier_only_without_bridge() {
+        // Without the external formatter, `styles.css` is skipped and `package.json` is only sorted
+        Tester::new("test/fixtures/lsp/prettier_only", json!({}))
+            .format_and_snapshot_multiple_file(&["package.json", "styles.css"]);
+    }
+
+    #[test]
+    fn test_json_without_bridge() {
+        // JSON files are formatted by `oxc_formatter`, so the external formatter is not needed
+        let tester = Tester::new("test/fixtures/lsp/prettier_only", json!({}));
+        let edits = tester.format("data.json", None).expect("file should be formatted");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, r#" "a": 1, "b": [1, 2] "#);
+    }
+
+    #[test]
+    fn test_parse_error_diagnostics() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        let content = Some("const a = ;\n");
+
+        // Disabled by default
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        assert_eq!(formatter.run_diagnostic(&uri, content), Ok(vec![]));
+
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&root_uri, json!({ "fmt.publishParseErrors": true }));
+        let diagnostics = formatter.run_diagnostic_on_change(&uri, content).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let (diagnostic_uri, diagnostics) = &diagnostics[0];
+        assert_eq!(diagnostic_uri, &uri);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 10));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+
+        // The diagnostics are cleared once the error is fixed
+        let diagnostics = formatter.run_diagnostic_on_save(&uri, Some("const a = 1;\n")).unwrap();
+        assert_eq!(diagnostics, vec![(uri, vec![])]);
+    }
+
+    #[test]
+    fn test_package_json_idempotent() {
+        let root_dir = "test/fixtures/lsp/package_json";
+        let root_uri = Tester::get_root_uri(root_dir);
+        let path = get_file_path(root_dir).join("package.json");
+        let source = std::fs::read_to_string(&path).unwrap();
+        let strategy = FormatFileStrategy::try_from(path).unwrap();
+
+        let bridges: [Option<Arc<dyn ExternalFormatterBridge>>; 2] =
+            [None, Some(Arc::new(TestBridge::default()))];
+        // Sorting and formatting must agree, otherwise every save changes the file again
+        for bridge in bridges {
+            for options in [
+                json!({}),
+                json!({ "fmt.configPath": "crlf.json" }),
+                json!({ "fmt.configPath": "sort_scripts.json" }),
+            ] {
+                let formatter =
+                    ServerFormatterBuilder::new(bridge.clone()).build(&root_uri, options);
+                let formatted =
+                    formatter.format(&strategy, &source, None, &AtomicBool::new(false)).unwrap();
+                assert!(formatted.find("\"name\"") < formatted.find("\"version\""));
+                // Sorted and formatted again, without any change
+                assert!(formatter.is_idempotent(
+                    &strategy,
+                    &formatted,
+                    None,
+                    &AtomicBool::new(false)
+                ));
+            }
+        }
+
+        // Without the external formatter, the sorted keys are reindented with the config
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&root_uri, json!({ "fmt.configPath": "crlf.json" }));
+        let formatted =
+            formatter.format(&strategy, &source, None, &AtomicBool::new(false)).unwrap();
+        assert!(formatted.starts_with("{\r\n\t\"name\": \"package-json\",\r\n\t\"version\""));
+        assert_eq!(formatted.matches('\n').count(), formatted.matches("\r\n").count());
+    }
+
+    #[test]
+    fn test_package_json_sort_scripts() {
+        let root_dir = "test/fixtures/lsp/package_json";
+        let root_uri = Tester::get_root_uri(root_dir);
+        let path = get_file_path(root_dir).join("package.json");
+        let source = std::fs::read_to_string(&path).unwrap();
+        let strategy = FormatFileStrategy::try_from(path).unwrap();
+
+        let bridges: [Option<Arc<dyn ExternalFormatterBridge>>; 2] =
+            [None, Some(Arc::new(TestBridge::default()))];
+        for bridge in bridges {
+            let scripts = |options| {
+                let formatter =
+                    ServerFormatterBuilder::new(bridge.clone()).build(&root_uri, options);
+                let formatted =
+                    formatter.format(&strategy, &source, None, &AtomicBool::new(false)).unwrap();
+                serde_json::from_str::<serde_json::Value>(&formatted).unwrap()["scripts"]
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+
+            // Scripts keep their order by default
+            assert_eq!(scripts(json!({})), ["test", "build"]);
+            assert_eq!(
+                scripts(json!({ "fmt.configPath": "sort_scripts.json" })),
+                ["build", "test"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_package_json_sort_disabled_without_bridge() {
+        let mut config_resolver =
+            ConfigResolver::from_value(json!({ "experimentalSortPackageJson": false }));
+        config_resolver.build_and_validate().expect("config should be valid");
+        let formatter = ServerFormatter::new(config_resolver, ServerFormatterConfig::default());
+
+        let uri = get_file_uri("test/fixtures/lsp/prettier_only/package.json");
+        assert!(formatter.run_format(&uri, None, None, &AtomicBool::new(false)).is_none());
+    }
+
+    #[test]
+    fn test_shebang() {
+        // The shebang is kept as is, only the code below it is formatted
+        Tester::new("test/fixtures/lsp/shebang", json!({}))
+            .format_and_snapshot_multiple_file(&["shebang.ts", "blank-lines.js"]);
+    }
+
+    #[test]
+    fn test_declaration_files() {
+        // `*.d.ts` overrides only apply to declaration files
+        Tester::new("test/fixtures/lsp/declaration", json!({}))
+            .format_and_snapshot_multiple_file(&["ambient.d.ts", "code.ts"]);
+    }
+
+    #[test]
+    fn test_module_kind_extensions() {
+        // Top-level `await` only parses in modules, `await` as an identifier and legacy octals only in scripts
+        Tester::new("test/fixtures/lsp/module_kind", json!({}))
+            .format_and_snapshot_multiple_file(&["top_level_await.mts", "commonjs.cts"]);
+    }
+
+    #[test]
+    fn test_deno_import_attributes() {
+        // Import attributes, and the legacy `assert` keyword still used by older Deno code,
+        // are always parsed, so the files of Deno projects are not skipped as unparsable
+        Tester::new("test/fixtures/lsp/deno", json!({}))
+            .format_and_snapshot_single_file("import_attributes.ts");
+    }
+
+    #[test]
+    fn test_bridge_capabilities() {
+        // Implements every method, but claims to support none of the optional ones
+        let bridge = Arc::new(
+            TestBridge::default()
+                .with_format_embedded(|_| panic!("embedded formatting is not supported"))
+                .with_config(json!({}))
+                .with_capabilities(BridgeCapabilities::default()),
+        );
+
+        // The JS config file is not loaded
+        let formatter = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/js_config"), json!({}));
+        let warnings = formatter.get_setup_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(".oxfmtrc.js"));
+
+        // Embedded code is left as is
+        let formatter = ServerFormatterBuilder::new(Some(Arc::clone(&bridge) as _))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/lit"), json!({}));
+        let source = "const a = css`color:red;`;\n";
+        let strategy =
+            FormatFileStrategy::try_from(get_file_path("test/fixtures/lsp/lit/my-element.ts"))
+                .unwrap();
+        assert_eq!(
+            formatter.format(&strategy, source, None, &AtomicBool::new(false)).as_deref(),
+            Ok(source)
+        );
+    }
+}
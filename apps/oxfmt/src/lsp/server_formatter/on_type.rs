@@ -0,0 +1,209 @@
+use std::sync::atomic::AtomicBool;
+
+use oxc_ast::ast::{JSXOpeningElement, Program};
+use oxc_ast_visit::{Visit, walk};
+use oxc_data_structures::rope::Rope;
+use oxc_language_server::utils::position_to_offset;
+use oxc_parser::Parser;
+use oxc_span::Span;
+use tower_lsp_server::ls_types::{FormattingOptions, Position, Range, TextEdit, Uri};
+
+use super::{
+    ServerFormatter,
+    diff::{compute_minimal_text_edit, create_text_edit},
+};
+use crate::core::FormatFileStrategy;
+
+impl ServerFormatter {
+    /// Formats only the statement around the typed character.
+    ///
+    /// - `}`: the block closed by the brace. Its opening brace always belongs to the same top-level statement,
+    ///   so the range formatting expands to the whole block.
+    /// - `;`: the statement terminated by the semicolon.
+    /// - `\n`: the statement ending on the previous line.
+    /// - `>`: the JSX opening tag closed by the angle bracket, e.g. to wrap its attributes.
+    ///
+    /// Only files handled by the oxc formatter are supported, other files return `None`.
+    pub(super) fn format_on_type(
+        &self,
+        uri: &Uri,
+        position: Position,
+        ch: &str,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        let start = match ch {
+            "}" | ";" => Position::new(position.line, position.character.checked_sub(1)?),
+            // `position_to_offset` clamps the character to the end of the line
+            "\n" => Position::new(position.line.checked_sub(1)?, u32::MAX),
+            ">" => {
+                return self.run_jsx_opening_element_format(
+                    uri,
+                    position,
+                    content,
+                    options,
+                    is_cancelled,
+                );
+            }
+            _ => return None,
+        };
+
+        self.format_range(uri, Range::new(start, position), content, options, is_cancelled)
+    }
+
+    /// Formats only the JSX opening tag ending at `position`, leaving its children and the rest of the file untouched.
+    /// Returns no edits if the `>` does not close a JSX opening tag, e.g. in `a > b`.
+    fn run_jsx_opening_element_format(
+        &self,
+        uri: &Uri,
+        position: Position,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+
+        let FormatFileStrategy::OxcFormatter { source_type, .. } = &strategy else {
+            return None;
+        };
+        let source_type = *source_type;
+
+        let allocator = self.allocator_pool.get();
+        let ret = Parser::new(&allocator, &source_text, source_type)
+            .with_options(self.parse_options)
+            .parse();
+
+        if !ret.errors.is_empty() {
+            return None;
+        }
+
+        let rope = Rope::from(&*source_text);
+        let offset = u32::try_from(position_to_offset(&source_text, position)).ok()?;
+        let source_spans = get_jsx_opening_element_spans(&ret.program);
+        let Some(index) = source_spans.iter().position(|span| span.end == offset) else {
+            return Some(vec![]);
+        };
+
+        // Same as `run_format_range()`, the whole file is formatted as by `run_format()`
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+        let code = self.format_cached(&path, &strategy, &source_text, indent, is_cancelled)?;
+
+        // Same as for the statements in `run_format_range()`,
+        // the N-th opening tag of the source is the N-th opening tag of the formatted code.
+        let formatted_allocator = self.allocator_pool.get();
+        let formatted_ret = Parser::new(&formatted_allocator, &code, source_type)
+            .with_options(self.parse_options)
+            .parse();
+        let formatted_spans = get_jsx_opening_element_spans(&formatted_ret.program);
+
+        if !formatted_ret.errors.is_empty() || source_spans.len() != formatted_spans.len() {
+            return Some(vec![]);
+        }
+
+        let source_span = source_spans[index];
+        let source_slice = source_span.source_text(&source_text);
+        let formatted_slice = formatted_spans[index].source_text(&code);
+
+        if source_slice == formatted_slice {
+            return Some(vec![]);
+        }
+
+        let (start, end, replacement) = compute_minimal_text_edit(source_slice, formatted_slice);
+        Some(vec![create_text_edit(
+            &rope,
+            &source_text,
+            source_span.start + start,
+            source_span.start + end,
+            replacement,
+        )])
+    }
+}
+
+/// Returns the spans of all JSX opening tags, in source order.
+fn get_jsx_opening_element_spans(program: &Program<'_>) -> Vec<Span> {
+    #[derive(Default)]
+    struct JSXOpeningElementCollector {
+        spans: Vec<Span>,
+    }
+
+    impl<'a> Visit<'a> for JSXOpeningElementCollector {
+        fn visit_jsx_opening_element(&mut self, element: &JSXOpeningElement<'a>) {
+            self.spans.push(element.span);
+            walk::walk_jsx_opening_element(self, element);
+        }
+    }
+
+    let mut collector = JSXOpeningElementCollector::default();
+    collector.visit_program(program);
+    collector.spans
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::Position;
+
+    use crate::lsp::{
+        ServerFormatterBuilder,
+        server_formatter::diff::build_text_edits,
+        tester::{Tester, get_file_uri},
+    };
+
+    #[test]
+    fn test_on_type_formatting() {
+        Tester::new("test/fixtures/lsp/on_type", json!({}))
+            .format_on_type_and_snapshot_single_file("on_type.ts", Position::new(2, 1), "}");
+    }
+
+    #[test]
+    fn test_on_type_formatting_jsx_opening_element() {
+        let tester = Tester::new("test/fixtures/lsp/on_type", json!({}));
+        tester.format_on_type_and_snapshot_single_file("on_type.tsx", Position::new(1, 48), ">");
+
+        // Not closing a JSX opening tag
+        let edits = tester.format_on_type("on_type.ts", Position::new(3, 10), ">");
+        assert_eq!(edits, Some(vec![]));
+    }
+
+    #[test]
+    fn test_on_type_formatting_cached_and_cancelled() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/on_type"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/on_type/on_type.tsx");
+        let content = "const element = <div   id=\"b\">text</div>;\n";
+        let position = Position::new(0, 30);
+        let format = |is_cancelled: &AtomicBool| {
+            formatter.run_on_type_format(&uri, position, ">", Some(content), None, is_cancelled)
+        };
+
+        // The client cancelled the request, e.g. the user kept typing
+        assert_eq!(format(&AtomicBool::new(true)), None);
+
+        // The whole file is formatted once, then the result of `run_format` is reused
+        let cached = "const element = <div data-cached>text</div>;\n";
+        let path = uri.to_file_path().unwrap();
+        formatter.format_cache.insert(
+            &path,
+            &(content, None::<(bool, u32)>),
+            build_text_edits(content, cached),
+        );
+        let edits = format(&AtomicBool::new(false)).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "data-cached");
+    }
+
+    #[test]
+    fn test_on_type_formatting_unknown_character() {
+        let edits = Tester::new("test/fixtures/lsp/on_type", json!({})).format_on_type(
+            "on_type.ts",
+            Position::new(2, 1),
+            "a",
+        );
+        assert!(edits.is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use std::sync::atomic::AtomicBool;
+
+use oxc_ast::ast::Statement;
+use oxc_data_structures::rope::Rope;
+use oxc_language_server::utils::position_to_offset;
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, Span};
+use tower_lsp_server::ls_types::{FormattingOptions, Range, TextEdit, Uri};
+
+use super::{
+    ServerFormatter,
+    diff::{
+        build_line_diff_text_edits, build_text_edits, compute_minimal_text_edit, create_text_edit,
+        is_range_intersecting,
+    },
+};
+use crate::core::FormatFileStrategy;
+
+impl ServerFormatter {
+    pub(super) fn format_range(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+
+        // Only JS/TS files can be split into statements
+        let FormatFileStrategy::OxcFormatter { source_type, .. } = &strategy else {
+            return None;
+        };
+        let source_type = *source_type;
+
+        let allocator = self.allocator_pool.get();
+        let ret = Parser::new(&allocator, &source_text, source_type)
+            .with_options(self.parse_options)
+            .parse();
+
+        if !ret.errors.is_empty() {
+            return None;
+        }
+
+        // The whole file is formatted as by `run_format()`, e.g. with the line endings and the BOM of the source
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+        let code = self.format_cached(&path, &strategy, &source_text, indent, is_cancelled)?;
+
+        // nothing has changed
+        if code == *source_text {
+            return Some(vec![]);
+        }
+
+        // Parse the formatted code too, so that top-level statements of both programs can be paired up.
+        // Besides the layout, the formatter only sorts the imports within runs of consecutive imports,
+        // so the N-th statement of the source is the N-th statement of the formatted code,
+        // unless both are imports of the same run.
+        let formatted_allocator = self.allocator_pool.get();
+        let formatted_ret = Parser::new(&formatted_allocator, &code, source_type)
+            .with_options(self.parse_options)
+            .parse();
+
+        let source_spans = get_statement_spans(&ret.program.body);
+        let formatted_spans = get_statement_spans(&formatted_ret.program.body);
+
+        // e.g. duplicate imports were removed
+        if !formatted_ret.errors.is_empty()
+            || source_spans.len() != formatted_spans.len()
+            || source_spans.iter().zip(&formatted_spans).any(|(a, b)| a.is_import != b.is_import)
+        {
+            // Statements can not be paired up, keep only the edits touching the range
+            return Some(
+                build_text_edits(&source_text, &code)
+                    .into_iter()
+                    .filter(|edit| is_range_intersecting(&edit.range, &range))
+                    .collect(),
+            );
+        }
+
+        let rope = Rope::from(&*source_text);
+        let range_start = u32::try_from(position_to_offset(&source_text, range.start)).ok()?;
+        let range_end = u32::try_from(position_to_offset(&source_text, range.end)).ok()?;
+
+        // Expand the range to the boundaries of the statements it touches,
+        // so we never replace only a part of a statement.
+        let mut touched_statements = source_spans
+            .iter()
+            .enumerate()
+            .filter(|(_, statement)| {
+                statement.span.start <= range_end && range_start <= statement.span.end
+            })
+            .map(|(index, _)| index);
+        let Some(mut first) = touched_statements.next() else {
+            return Some(vec![]);
+        };
+        let mut last = touched_statements.next_back().unwrap_or(first);
+        // Sorted imports move within their run, so touched imports are expanded to the whole run,
+        // which contains the same imports in both programs.
+        while first > 0 && source_spans[first].is_import && source_spans[first - 1].is_import {
+            first -= 1;
+        }
+        while last + 1 < source_spans.len()
+            && source_spans[last].is_import
+            && source_spans[last + 1].is_import
+        {
+            last += 1;
+        }
+
+        let source_span = Span::new(source_spans[first].span.start, source_spans[last].span.end);
+        let formatted_span =
+            Span::new(formatted_spans[first].span.start, formatted_spans[last].span.end);
+        let source_slice = source_span.source_text(&source_text);
+        let formatted_slice = formatted_span.source_text(&code);
+
+        if source_slice == formatted_slice {
+            return Some(vec![]);
+        }
+
+        let (start, end, replacement) = compute_minimal_text_edit(source_slice, formatted_slice);
+        Some(vec![create_text_edit(
+            &rope,
+            &source_text,
+            source_span.start + start,
+            source_span.start + end,
+            replacement,
+        )])
+    }
+
+    /// Formats the whole file once, and keeps only the edits touching any of `ranges`.
+    /// The edits are split into line hunks, so each edit is kept or dropped as a whole,
+    /// and the edits kept for different ranges never overlap, even if the ranges do.
+    pub(super) fn format_ranges(
+        &self,
+        uri: &Uri,
+        ranges: &[Range],
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        let path = uri.to_file_path()?;
+        let (strategy, source_text) = self.get_source_text(&path, content).ok()?;
+        let indent = options.map(|options| (options.insert_spaces, options.tab_size));
+        let code = self.format(&strategy, &source_text, indent, &AtomicBool::new(false)).ok()?;
+        if code == *source_text {
+            return Some(vec![]);
+        }
+
+        Some(
+            build_line_diff_text_edits(&source_text, &code)
+                .into_iter()
+                .filter(|edit| ranges.iter().any(|range| is_range_intersecting(&edit.range, range)))
+                .collect(),
+        )
+    }
+}
+
+/// Span of a top-level statement, see [`get_statement_spans`].
+struct StatementSpan {
+    span: Span,
+    /// Imports can be reordered by sorting them, other statements keep their order.
+    is_import: bool,
+}
+
+/// Returns the spans of the given statements.
+/// Empty statements are skipped, because the formatter removes them.
+fn get_statement_spans(statements: &[Statement<'_>]) -> Vec<StatementSpan> {
+    statements
+        .iter()
+        .filter(|statement| !matches!(statement, Statement::EmptyStatement(_)))
+        .map(|statement| StatementSpan {
+            span: statement.span(),
+            is_import: matches!(statement, Statement::ImportDeclaration(_)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::AtomicBool};
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{FormattingOptions, Position, Range, TextEdit};
+
+    use crate::{
+        core::ConfigResolver,
+        lsp::{
+            ServerFormatter, ServerFormatterBuilder, ServerFormatterConfig,
+            tester::{TestBridge, Tester, get_file_uri},
+        },
+    };
+
+    #[test]
+    fn test_range_formatting() {
+        // Only the statement touched by the range is formatted
+        Tester::new("test/fixtures/lsp/range", json!({})).format_range_and_snapshot_single_file(
+            "range.ts",
+            Range::new(Position::new(1, 10), Position::new(1, 11)),
+        );
+    }
+
+    #[test]
+    fn test_range_formatting_sorted_imports() {
+        let mut config_resolver =
+            ConfigResolver::from_value(json!({ "experimentalSortImports": {} }));
+        config_resolver.build_and_validate().expect("config should be valid");
+        let formatter = ServerFormatter::new(config_resolver, ServerFormatterConfig::default());
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let content = "import { b } from \"b\";\nimport { a } from \"a\";\nconst c   =   1;\n";
+
+        // The sorted imports are replaced as a whole, the statement after them is left untouched
+        let range = Range::new(Position::new(0, 0), Position::new(0, 1));
+        assert_eq!(
+            formatter.run_format_range(&uri, range, Some(content), None),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(0, 9), Position::new(1, 20)),
+                "a } from \"a\";\nimport { b } from \"b".into()
+            )])
+        );
+
+        let range = Range::new(Position::new(2, 0), Position::new(2, 1));
+        assert_eq!(
+            formatter.run_format_range(&uri, range, Some(content), None),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(2, 8), Position::new(2, 13)),
+                "=".into()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_format_paste() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/range"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let pasted = Range::new(Position::new(1, 0), Position::new(2, 0));
+        let edits = formatter.run_format_paste(&uri, pasted, None).unwrap();
+        assert_eq!(edits, formatter.run_format_range(&uri, pasted, None, None).unwrap());
+        assert!(!edits.is_empty());
+
+        // Files of the external formatter are not formatted partially
+        let formatter = ServerFormatterBuilder::new(Some(Arc::new(TestBridge::default())))
+            .build(&Tester::get_root_uri("test/fixtures/lsp/prettier_only"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/prettier_only/styles.css");
+        assert_eq!(formatter.run_format_paste(&uri, pasted, None), Some(vec![]));
+    }
+
+    #[test]
+    fn test_format_ranges() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/range"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let content = "const a=1;\nconst b = 2;\nconst c=3;\nconst d = 4;\nconst e=5;\n";
+        let edit = |line| {
+            TextEdit::new(Range::new(Position::new(line, 7), Position::new(line, 8)), " = ".into())
+        };
+
+        // Overlapping ranges do not return the same edit twice, and `c` is left untouched
+        let ranges = [
+            Range::new(Position::new(0, 0), Position::new(0, 9)),
+            Range::new(Position::new(0, 3), Position::new(1, 0)),
+            Range::new(Position::new(4, 0), Position::new(4, 10)),
+        ];
+        assert_eq!(
+            formatter.run_format_ranges(&uri, &ranges, Some(content), None),
+            Some(vec![edit(0), edit(4)])
+        );
+        assert_eq!(
+            formatter.run_format_ranges(
+                &uri,
+                &[Range::new(Position::new(1, 0), Position::new(1, 12))],
+                Some(content),
+                None
+            ),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_client_formatting_options_range() {
+        let mut config_resolver = ConfigResolver::from_value(json!({}));
+        config_resolver.build_and_validate().expect("config should be valid");
+        let formatter = ServerFormatter::new(config_resolver, ServerFormatterConfig::default());
+        let uri = get_file_uri("test/fixtures/lsp/basic/basic.ts");
+        let content = "function a() {\nreturn 1;\n}\n";
+        let options = FormattingOptions { tab_size: 4, insert_spaces: false, ..Default::default() };
+        let indent = Range::new(Position::new(1, 0), Position::new(1, 0));
+
+        let range = Range::new(Position::new(1, 0), Position::new(1, 1));
+        let edits = formatter.run_format_range(&uri, range, Some(content), Some(&options));
+        assert_eq!(edits, Some(vec![TextEdit::new(indent, "\t".into())]));
+        let edits = formatter.run_format_ranges(&uri, &[range], Some(content), Some(&options));
+        assert_eq!(edits, Some(vec![TextEdit::new(indent, "\t".into())]));
+        let edits = formatter.run_on_type_format(
+            &uri,
+            Position::new(2, 1),
+            "}",
+            Some(content),
+            Some(&options),
+            &AtomicBool::new(false),
+        );
+        assert_eq!(edits, Some(vec![TextEdit::new(indent, "\t".into())]));
+
+        // Without client options, the default indentation is used
+        let edits = formatter.run_format_range(&uri, range, Some(content), None);
+        assert_eq!(edits, Some(vec![TextEdit::new(indent, "  ".into())]));
+    }
+
+    #[test]
+    fn test_crlf_range_formatting() {
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/end_of_line"), json!({}));
+        let uri = get_file_uri("test/fixtures/lsp/end_of_line/auto.ts");
+        let content = "const a = 1;\r\nfunction f(a,b){return a+b}\r\nconst b = 2;\r\n";
+
+        // The line breaks inserted into the statement are CRLF, as when formatting the whole file
+        let range = Range::new(Position::new(1, 0), Position::new(1, 1));
+        assert_eq!(
+            formatter.run_format_range(&uri, range, Some(content), None),
+            Some(vec![TextEdit::new(
+                Range::new(Position::new(1, 13), Position::new(1, 26)),
+                " b) {\r\n  return a + b;\r\n".into()
+            )])
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::atomic::AtomicBool;
+
+use oxc_span::VALID_EXTENSIONS;
+use serde_json::json;
+use tower_lsp_server::ls_types::Uri;
+
+use super::{NATIVE_EXTENSIONS, ServerFormatter};
+
+impl ServerFormatter {
+    /// Returns the sorted extensions formatted by oxc
+    /// and the parser names of the external formatter.
+    pub(super) fn supported_languages(&self) -> serde_json::Value {
+        let mut languages = VALID_EXTENSIONS
+            .iter()
+            .chain(NATIVE_EXTENSIONS)
+            .map(|extension| (*extension).to_string())
+            .collect::<Vec<_>>();
+        if self.external_bridge().is_some() {
+            languages.extend(self.external.languages.iter().cloned());
+        }
+        languages.sort_unstable();
+        languages.dedup();
+        json!(languages)
+    }
+
+    /// Returns how the config of the document at `uri` is resolved:
+    /// the config file, the `.editorconfig` files and the overrides applied, and the effective options.
+    pub(super) fn resolve_config_status(&self, uri: &Uri) -> serde_json::Value {
+        let Some(path) = uri.to_file_path() else {
+            return serde_json::Value::Null;
+        };
+
+        let config_path = self
+            .config_path
+            .as_ref()
+            .filter(|_| self.config_error.is_none())
+            .map(|path| path.to_string_lossy().to_string());
+        let editorconfig_paths = self
+            .config_resolver
+            .editorconfig_paths(&path)
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let overrides = self
+            .config_resolver
+            .matched_overrides(&path)
+            .into_iter()
+            .map(|(index, files)| json!({ "index": index, "files": files }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "configPath": config_path,
+            "configError": self.config_error,
+            "editorconfigPaths": editorconfig_paths,
+            "overrides": overrides,
+            "ignored": self.is_disabled || self.is_ignored(&path),
+            "options": self.config_resolver.resolve_effective_config(&path),
+        })
+    }
+
+    /// Returns the whole formatted text of the document at `uri`,
+    /// or `null` if it is ignored, not supported or can not be formatted, e.g. because of parse errors.
+    /// Unlike [`Tool::run_format`], the text is not diffed into edits.
+    pub(super) fn preview_formatted(&self, uri: &Uri, content: Option<&str>) -> serde_json::Value {
+        let Some(path) = uri.to_file_path() else {
+            return serde_json::Value::Null;
+        };
+        self.get_source_text(&path, content)
+            .and_then(|(strategy, source_text)| {
+                self.format(&strategy, &source_text, None, &AtomicBool::new(false))
+            })
+            .map_or(serde_json::Value::Null, serde_json::Value::String)
+    }
+
+    /// Returns whether the external formatter is connected and responds,
+    /// the sorted languages it loaded, and why it is not connected, if so.
+    pub(super) fn bridge_status(&self) -> serde_json::Value {
+        let error = match self.external_bridge() {
+            None if self.external.bridge.is_some() => {
+                Some("disabled after failing too many times in a row".to_string())
+            }
+            None => Some("not available".to_string()),
+            Some(external_bridge) => external_bridge.ping().err(),
+        };
+
+        let mut languages = self.external.languages.iter().collect::<Vec<_>>();
+        languages.sort_unstable();
+        json!({
+            "connected": error.is_none(),
+            "languages": languages,
+            "error": error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+
+    use crate::lsp::{
+        ServerFormatter, ServerFormatterBuilder,
+        server_formatter::{
+            BRIDGE_STATUS_REQUEST, PREVIEW_FORMATTED_REQUEST, RESOLVE_CONFIG_REQUEST,
+            SUPPORTED_LANGUAGES_REQUEST,
+        },
+        tester::{TestBridge, Tester, get_file_uri},
+    };
+
+    #[test]
+    fn test_supported_languages() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        assert_eq!(formatter.handle_custom_request("unknown/request"), None);
+        assert_eq!(
+            formatter.handle_custom_request(SUPPORTED_LANGUAGES_REQUEST),
+            Some(json!([
+                "cjs", "cts", "js", "json", "json5", "jsonc", "jsx", "mjs", "mts", "toml", "ts",
+                "tsx"
+            ]))
+        );
+
+        // The languages of the external formatter and its plugins are included
+        let bridge = TestBridge::default().with_init_result(Ok(vec!["svelte".to_string()]));
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::new(bridge))).build(&root_uri, json!({}));
+        let languages = formatter.handle_custom_request(SUPPORTED_LANGUAGES_REQUEST).unwrap();
+        let languages = languages.as_array().unwrap();
+        assert!(languages.contains(&json!("svelte")));
+        assert!(languages.contains(&json!("css")));
+        assert!(languages.contains(&json!("ts")));
+    }
+
+    #[test]
+    fn test_bridge_status() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/basic");
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        assert_eq!(
+            formatter.handle_custom_request(BRIDGE_STATUS_REQUEST),
+            Some(json!({ "connected": false, "languages": [], "error": "not available" }))
+        );
+
+        let bridge = TestBridge::default().with_init_result(Ok(vec!["svelte".to_string()]));
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::new(bridge))).build(&root_uri, json!({}));
+        let status = formatter.handle_custom_request(BRIDGE_STATUS_REQUEST).unwrap();
+        assert_eq!(status["connected"], json!(true));
+        assert_eq!(status["error"], json!(null));
+        assert!(status["languages"].as_array().unwrap().contains(&json!("svelte")));
+
+        let bridge = TestBridge::default()
+            .with_init_result(Ok(vec!["svelte".to_string()]))
+            .with_ping_result(Err("timed out".to_string()));
+        let formatter =
+            ServerFormatterBuilder::new(Some(Arc::new(bridge))).build(&root_uri, json!({}));
+        let status = formatter.handle_custom_request(BRIDGE_STATUS_REQUEST).unwrap();
+        assert_eq!(status["connected"], json!(false));
+        assert_eq!(status["error"], json!("timed out"));
+    }
+
+    #[test]
+    fn test_resolve_config_request() {
+        let root = "test/fixtures/lsp/overrides";
+        let formatter =
+            ServerFormatterBuilder::new(None).build(&Tester::get_root_uri(root), json!({}));
+        let resolve = |relative_file_path: &str| {
+            formatter
+                .handle_custom_text_document_request(
+                    RESOLVE_CONFIG_REQUEST,
+                    &get_file_uri(&format!("{root}/{relative_file_path}")),
+                    None,
+                )
+                .expect("request should be handled")
+        };
+
+        let resolved = resolve("code.ts");
+        assert!(resolved["configPath"].as_str().unwrap().ends_with(".oxfmtrc.json"));
+        assert_eq!(resolved["configError"], json!(null));
+        assert_eq!(resolved["overrides"], json!([]));
+        assert_eq!(resolved["ignored"], json!(false));
+        assert_eq!(resolved["options"]["tabWidth"], json!(2));
+
+        let resolved = resolve("nested/data.json");
+        assert_eq!(
+            resolved["overrides"],
+            json!([{ "index": 0, "files": "*.json" }, { "index": 1, "files": "nested/*.json" }])
+        );
+        assert_eq!(resolved["options"]["tabWidth"], json!(8));
+
+        assert!(
+            formatter
+                .handle_custom_text_document_request(
+                    "oxfmt/unknown",
+                    &get_file_uri(&format!("{root}/code.ts")),
+                    None,
+                )
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_preview_formatted_request() {
+        let root_uri = Tester::get_root_uri("test/fixtures/lsp/range");
+        let uri = get_file_uri("test/fixtures/lsp/range/range.ts");
+        let preview = |formatter: &ServerFormatter, content: Option<&str>| {
+            formatter
+                .handle_custom_text_document_request(PREVIEW_FORMATTED_REQUEST, &uri, content)
+                .expect("request should be handled")
+        };
+
+        let formatter = ServerFormatterBuilder::new(None).build(&root_uri, json!({}));
+        // The whole formatted text, of the file on disk or the opened document
+        assert_eq!(preview(&formatter, None), json!("const a = 1;\nconst b = 2;\nconst c = 3;\n"));
+        assert_eq!(preview(&formatter, Some("let x=1")), json!("let x = 1;\n"));
+        // Not formatted because of parse errors
+        assert_eq!(preview(&formatter, Some("let x =")), json!(null));
+
+        // Ignored files are not formatted
+        let formatter = ServerFormatterBuilder::new(None)
+            .build(&root_uri, json!({ "fmt.includePatterns": ["src/**"] }));
+        assert_eq!(preview(&formatter, None), json!(null));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use ignore::gitignore::Gitignore;
+use log::warn;
+use oxc_language_server::Tool;
+use rayon::prelude::*;
+use tower_lsp_server::ls_types::{
+    DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier, TextDocumentEdit, Uri,
+    WorkspaceEdit,
+};
+
+use super::ServerFormatter;
+use crate::core::collect_formattable;
+
+impl ServerFormatter {
+    /// Formats all files under the workspace root, with at most [`MAX_WORKSPACE_FORMAT_THREADS`] threads,
+    /// and calls `progress` after each file.
+    /// Each file is formatted by [`Tool::run_format`], so ignored files are skipped.
+    ///
+    /// Documents opened in the editor are formatted from their content returned by `open_document`,
+    /// and their edits carry the version of that content, so the client rejects them if the document changed since.
+    /// Other files are read from the file system.
+    /// Returns `None` if all files are already formatted, or if `is_cancelled` is set.
+    pub(super) fn format_workspace(
+        &self,
+        open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        progress: &(dyn Fn(usize, usize) + Sync),
+        is_cancelled: &AtomicBool,
+    ) -> Option<WorkspaceEdit> {
+        // Only the ignore patterns are applied while walking, `run_format` checks the rest
+        let paths = collect_formattable(
+            &self.root_path,
+            self.gitignore_glob.as_ref().unwrap_or(&Gitignore::empty()),
+        );
+
+        let total = paths.len();
+        progress(0, total);
+
+        let pool = workspace_format_pool()?;
+
+        let done = AtomicUsize::new(0);
+        let document_edits = pool.install(|| {
+            paths
+                .par_iter()
+                .filter_map(|path| {
+                    if is_cancelled.load(Ordering::Relaxed) {
+                        return None;
+                    }
+                    let uri = Uri::from_file_path(path)?;
+                    let document = open_document(&uri);
+                    let content = document.as_ref().map(|(content, _)| content.as_str());
+                    let edits = self.run_format(&uri, content, None, is_cancelled);
+                    progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+                    let edits = edits.filter(|edits| !edits.is_empty())?;
+                    Some(TextDocumentEdit {
+                        text_document: OptionalVersionedTextDocumentIdentifier {
+                            uri,
+                            version: document.map(|(_, version)| version),
+                        },
+                        edits: edits.into_iter().map(OneOf::Left).collect(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        if document_edits.is_empty() || is_cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        Some(WorkspaceEdit {
+            changes: None,
+            document_changes: Some(DocumentChanges::Edits(document_edits)),
+            change_annotations: None,
+        })
+    }
+}
+
+/// Maximum number of threads formatting files for [`FORMAT_WORKSPACE_COMMAND_ID`],
+/// so formatting a large workspace does not starve the other requests.
+const MAX_WORKSPACE_FORMAT_THREADS: usize = 4;
+
+/// Thread pool of [`FORMAT_WORKSPACE_COMMAND_ID`], with at most [`MAX_WORKSPACE_FORMAT_THREADS`] threads.
+/// It is created on first use and shared by all formatters, instead of spawning threads on every command.
+/// Returns `None` if it cannot be created.
+fn workspace_format_pool() -> Option<&'static rayon::ThreadPool> {
+    static POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let num_threads = std::thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(MAX_WORKSPACE_FORMAT_THREADS);
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .inspect_err(|err| warn!("Failed to create thread pool to format the workspace: {err}"))
+            .ok()
+    })
+    .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, atomic::AtomicBool};
+
+    use oxc_language_server::Tool;
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{DocumentChanges, OneOf, TextDocumentEdit, Uri};
+
+    use crate::lsp::{
+        ServerFormatter, ServerFormatterBuilder,
+        server_formatter::FORMAT_WORKSPACE_COMMAND_ID,
+        tester::{Tester, get_file_uri},
+    };
+
+    fn formatter() -> ServerFormatter {
+        ServerFormatterBuilder::new(None)
+            .build(&Tester::get_root_uri("test/fixtures/lsp/prettier_only"), json!({}))
+    }
+
+    fn workspace_edits(
+        formatter: &ServerFormatter,
+        open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Vec<TextDocumentEdit> {
+        let edit = formatter
+            .execute_command(
+                FORMAT_WORKSPACE_COMMAND_ID,
+                vec![],
+                open_document,
+                progress,
+                &AtomicBool::new(false),
+            )
+            .expect("command should be executed")
+            .expect("files should be formatted");
+        let Some(DocumentChanges::Edits(mut document_edits)) = edit.document_changes else {
+            panic!("expected text document edits");
+        };
+        document_edits
+            .sort_by(|a, b| a.text_document.uri.as_str().cmp(b.text_document.uri.as_str()));
+        document_edits
+    }
+
+    #[test]
+    fn test_format_workspace() {
+        let formatter = formatter();
+        assert!(formatter.is_responsible_for_command(FORMAT_WORKSPACE_COMMAND_ID));
+
+        let reported = Mutex::new(vec![]);
+        // Without the external formatter, `styles.css` is skipped
+        let document_edits = workspace_edits(&formatter, &|_| None, &|done, total| {
+            reported.lock().unwrap().push((done, total));
+        });
+        let uris = document_edits
+            .iter()
+            .map(|edit| (edit.text_document.uri.clone(), edit.text_document.version))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            uris,
+            vec![
+                (get_file_uri("test/fixtures/lsp/prettier_only/data.json"), None),
+                (get_file_uri("test/fixtures/lsp/prettier_only/package.json"), None),
+            ]
+        );
+
+        let reported = reported.into_inner().unwrap();
+        assert_eq!(reported.len(), 4);
+        assert!(reported.contains(&(0, 3)));
+        assert!(reported.contains(&(3, 3)));
+    }
+
+    #[test]
+    fn test_format_workspace_open_document() {
+        let formatter = formatter();
+        let data_uri = get_file_uri("test/fixtures/lsp/prettier_only/data.json");
+        // The unsaved content in the editor differs from the file on disk
+        let content = "{\"c\":[3,4]}";
+        let open_document = |uri: &Uri| (*uri == data_uri).then(|| (content.to_string(), 7));
+
+        let document_edits = workspace_edits(&formatter, &open_document, &|_, _| {});
+        assert_eq!(document_edits[0].text_document.uri, data_uri);
+        assert_eq!(document_edits[0].text_document.version, Some(7));
+        let expected = formatter
+            .run_format(&data_uri, Some(content), None, &AtomicBool::new(false))
+            .unwrap()
+            .into_iter()
+            .map(OneOf::Left)
+            .collect::<Vec<_>>();
+        assert_eq!(document_edits[0].edits, expected);
+
+        // Formatted unsaved content is not edited, even if the file on disk is not formatted
+        let formatted = "{ \"a\": 1, \"b\": [1, 2] }\n";
+        assert_eq!(
+            formatter.run_format(&data_uri, Some(formatted), None, &AtomicBool::new(false)),
+            Some(vec![])
+        );
+        let open_document = |uri: &Uri| (*uri == data_uri).then(|| (formatted.to_string(), 8));
+        let document_edits = workspace_edits(&formatter, &open_document, &|_, _| {});
+        assert_eq!(document_edits.len(), 1);
+        assert_eq!(
+            document_edits[0].text_document.uri,
+            get_file_uri("test/fixtures/lsp/prettier_only/package.json")
+        );
+    }
+
+    #[test]
+    fn test_format_workspace_cancelled() {
+        let is_cancelled = AtomicBool::new(true);
+        let edit = formatter()
+            .execute_command(
+                FORMAT_WORKSPACE_COMMAND_ID,
+                vec![],
+                &|_| None,
+                &|_, _| {},
+                &is_cancelled,
+            )
+            .expect("command should be executed");
+        assert!(edit.is_none());
+    }
+}
@@ -1,8 +1,23 @@
-use std::{fmt::Write, path::PathBuf};
+use std::{
+    fmt::Write,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use tower_lsp_server::ls_types::{TextEdit, Uri};
+use flate2::read::GzDecoder;
+use rustc_hash::FxHashMap;
 
-use crate::lsp::server_formatter::{ServerFormatter, ServerFormatterBuilder};
+use tower_lsp_server::ls_types::{Position, Range, TextEdit, Uri};
+
+use crate::lsp::{
+    external_formatter_bridge::{BridgeCapabilities, BridgeError, ExternalFormatterBridge},
+    server_formatter::{ServerFormatter, ServerFormatterBuilder},
+};
 use oxc_language_server::{Tool, ToolRestartChanges};
 
 /// Given a file path relative to the crate root directory, return the absolute path of the file.
@@ -16,6 +31,15 @@ pub fn get_file_uri(relative_file_path: &str) -> Uri {
         .expect("failed to convert file path to URL")
 }
 
+/// Read the gzip-compressed variant (e.g. `file.ts.gz` for `file.ts`) of a fixture file, if it exists.
+/// Large fixtures are stored compressed to keep the repository small.
+fn read_gzip_fixture(relative_file_path: &str) -> Option<String> {
+    let file = std::fs::File::open(get_file_path(&format!("{relative_file_path}.gz"))).ok()?;
+    let mut content = String::new();
+    GzDecoder::new(file).read_to_string(&mut content).expect("failed to decompress fixture");
+    Some(content)
+}
+
 fn get_snapshot_from_text_edits(edits: &[TextEdit]) -> String {
     if edits.len() == 1 {
         // Single edit - show range and the actual formatted content with proper indentation
@@ -40,22 +64,274 @@ fn get_snapshot_from_text_edits(edits: &[TextEdit]) -> String {
     }
 }
 
+/// A call of [`TestBridge`], see [`TestBridge::take_calls`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeCall {
+    FormatFile { parser_name: String, file_name: String, options: serde_json::Value },
+    FormatEmbedded { tag_name: String, code: String },
+    LoadConfig(PathBuf),
+    Warmup(Vec<String>),
+}
+
+type FormatFn = dyn Fn(&str) -> Result<Option<String>, BridgeError> + Send + Sync;
+type FormatEmbeddedFn = dyn Fn(&str) -> String + Send + Sync;
+
+/// [`ExternalFormatterBridge`] returning canned results of `format_file`,
+/// to test the files formatted by the external formatter without the JS side.
+///
+/// Files without a canned result are returned as is, i.e. already formatted.
+/// Every call is recorded, so tests can check what was sent to the external formatter.
+pub struct TestBridge {
+    /// Result of `init`, i.e. the languages of the loaded plugins.
+    init_result: Result<Vec<String>, String>,
+    warmup_result: Result<(), String>,
+    ping_result: Result<(), String>,
+    capabilities: BridgeCapabilities,
+    /// Canned results keyed by `(parser_name, file_name)`.
+    results: FxHashMap<(String, String), Result<Option<String>, BridgeError>>,
+    /// Formats the files without a canned result, which are returned as is if `None`.
+    format: Option<Box<FormatFn>>,
+    /// Formats the embedded code, which is not supported if `None`.
+    format_embedded: Option<Box<FormatEmbeddedFn>>,
+    /// Default export of the JS config files, which are not supported if `None`.
+    config: Option<serde_json::Value>,
+    /// Whether `format_file` sets `is_cancelled`, as if `$/cancelRequest` arrived meanwhile.
+    cancels: bool,
+    calls: Mutex<Vec<BridgeCall>>,
+}
+
+impl Default for TestBridge {
+    fn default() -> Self {
+        Self {
+            init_result: Ok(vec![]),
+            warmup_result: Ok(()),
+            ping_result: Ok(()),
+            capabilities: BridgeCapabilities::default(),
+            results: FxHashMap::default(),
+            format: None,
+            format_embedded: None,
+            config: None,
+            cancels: false,
+            calls: Mutex::default(),
+        }
+    }
+}
+
+impl TestBridge {
+    /// Returns `result` for the file named `file_name` formatted with `parser_name`.
+    /// `Ok(None)` means no parser or plugin can handle the file,
+    /// and `Err` that the external formatter rejected it, e.g. because of a syntax error.
+    pub fn with_result(
+        mut self,
+        parser_name: &str,
+        file_name: &str,
+        result: Result<Option<&str>, &str>,
+    ) -> Self {
+        self.results.insert(
+            (parser_name.to_string(), file_name.to_string()),
+            result
+                .map(|code| code.map(ToString::to_string))
+                .map_err(|err| BridgeError::Rejected(err.to_string())),
+        );
+        self
+    }
+
+    /// Formats the files without a canned result with `format`.
+    pub fn with_format(
+        mut self,
+        format: impl Fn(&str) -> Result<Option<String>, BridgeError> + Send + Sync + 'static,
+    ) -> Self {
+        self.format = Some(Box::new(format));
+        self
+    }
+
+    /// Formats the embedded code with `format_embedded`, and claims to support it.
+    pub fn with_format_embedded(
+        mut self,
+        format_embedded: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.format_embedded = Some(Box::new(format_embedded));
+        self.capabilities.supports_embedded = true;
+        self
+    }
+
+    /// Loads `config` from every JS config file, and claims to support it.
+    pub fn with_config(mut self, config: serde_json::Value) -> Self {
+        self.config = Some(config);
+        self.capabilities.supports_config_load = true;
+        self
+    }
+
+    /// Claims to support `capabilities` only, whatever the bridge implements.
+    pub fn with_capabilities(mut self, capabilities: BridgeCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn with_init_result(mut self, init_result: Result<Vec<String>, String>) -> Self {
+        self.init_result = init_result;
+        self
+    }
+
+    pub fn with_warmup_result(mut self, warmup_result: Result<(), String>) -> Self {
+        self.warmup_result = warmup_result;
+        self
+    }
+
+    pub fn with_ping_result(mut self, ping_result: Result<(), String>) -> Self {
+        self.ping_result = ping_result;
+        self
+    }
+
+    /// Sets `is_cancelled` on every `format_file`, as if `$/cancelRequest` arrived meanwhile.
+    pub fn with_cancellation(mut self) -> Self {
+        self.cancels = true;
+        self
+    }
+
+    /// Returns the calls so far, and forgets them.
+    pub fn take_calls(&self) -> Vec<BridgeCall> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+
+    /// Returns the parser names of the `format_file` calls so far, and forgets all calls.
+    pub fn take_parser_names(&self) -> Vec<String> {
+        self.take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                BridgeCall::FormatFile { parser_name, .. } => Some(parser_name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the code of the `format_embedded` calls so far, and forgets all calls.
+    pub fn take_embedded_codes(&self) -> Vec<String> {
+        self.take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                BridgeCall::FormatEmbedded { code, .. } => Some(code),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the tag names of the `format_embedded` calls so far, and forgets all calls.
+    pub fn take_tag_names(&self) -> Vec<String> {
+        self.take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                BridgeCall::FormatEmbedded { tag_name, .. } => Some(tag_name),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl ExternalFormatterBridge for TestBridge {
+    fn init(&self, _num_threads: usize) -> Result<Vec<String>, String> {
+        self.init_result.clone()
+    }
+
+    fn capabilities(&self) -> BridgeCapabilities {
+        self.capabilities
+    }
+
+    fn format_file(
+        &self,
+        options: &serde_json::Value,
+        parser_name: &str,
+        file_name: &str,
+        code: &str,
+        _timeout: Duration,
+        is_cancelled: &AtomicBool,
+    ) -> Result<Option<String>, BridgeError> {
+        self.calls.lock().unwrap().push(BridgeCall::FormatFile {
+            parser_name: parser_name.to_string(),
+            file_name: file_name.to_string(),
+            options: options.clone(),
+        });
+        if self.cancels {
+            is_cancelled.store(true, Ordering::Relaxed);
+        }
+        if let Some(result) = self.results.get(&(parser_name.to_string(), file_name.to_string())) {
+            return result.clone();
+        }
+        match &self.format {
+            Some(format) => format(code),
+            None => Ok(Some(code.to_string())),
+        }
+    }
+
+    fn format_embedded(
+        &self,
+        _options: &Arc<serde_json::Value>,
+        tag_name: &str,
+        code: &str,
+        _timeout: Duration,
+    ) -> Result<String, String> {
+        self.calls.lock().unwrap().push(BridgeCall::FormatEmbedded {
+            tag_name: tag_name.to_string(),
+            code: code.to_string(),
+        });
+        match &self.format_embedded {
+            Some(format_embedded) => Ok(format_embedded(code)),
+            None => Err(format!("embedded formatting is not supported for tag '{tag_name}'")),
+        }
+    }
+
+    fn load_config(&self, path: &Path) -> Result<serde_json::Value, String> {
+        self.calls.lock().unwrap().push(BridgeCall::LoadConfig(path.to_path_buf()));
+        self.config
+            .clone()
+            .ok_or_else(|| format!("JS config files are not supported: {}", path.display()))
+    }
+
+    fn warmup(&self, languages: &[String]) -> Result<(), String> {
+        self.calls.lock().unwrap().push(BridgeCall::Warmup(languages.to_vec()));
+        self.warmup_result.clone()
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        self.ping_result.clone()
+    }
+}
+
 /// Testing struct for the [formatter server][crate::formatter::server_formatter::ServerFormatter].
 pub struct Tester<'t> {
     relative_root_dir: &'t str,
     options: serde_json::Value,
+    external_bridge: Option<Arc<dyn ExternalFormatterBridge>>,
 }
 
 impl Tester<'_> {
     pub fn new(relative_root_dir: &'static str, options: serde_json::Value) -> Self {
-        Self { relative_root_dir, options }
+        Self { relative_root_dir, options, external_bridge: None }
+    }
+
+    /// Format with `external_bridge`, e.g. a [`TestBridge`], instead of only natively supported files.
+    pub fn with_external_bridge(
+        mut self,
+        external_bridge: Arc<dyn ExternalFormatterBridge>,
+    ) -> Self {
+        self.external_bridge = Some(external_bridge);
+        self
+    }
+
+    /// Returns the URI of a fixture file, and its decompressed content if only a `.gz` variant exists.
+    fn get_fixture(&self, relative_file_path: &str) -> (Uri, Option<String>) {
+        let relative_file_path = format!("{}/{}", self.relative_root_dir, relative_file_path);
+        let content = if get_file_path(&relative_file_path).exists() {
+            None
+        } else {
+            read_gzip_fixture(&relative_file_path)
+        };
+        (get_file_uri(&relative_file_path), content)
     }
 
     fn create_formatter(&self) -> ServerFormatter {
-        ServerFormatterBuilder::build(
-            &Self::get_root_uri(self.relative_root_dir),
-            self.options.clone(),
-        )
+        ServerFormatterBuilder::new(self.external_bridge.clone())
+            .build(&Self::get_root_uri(self.relative_root_dir), self.options.clone())
     }
 
     pub fn get_root_uri(relative_root_dir: &str) -> Uri {
@@ -69,26 +345,89 @@ impl Tester<'_> {
         self.format_and_snapshot_multiple_file(&[relative_file_path]);
     }
 
-    #[expect(clippy::disallowed_methods)]
     pub fn format_and_snapshot_multiple_file(&self, relative_file_paths: &[&str]) {
         let mut snapshot_result = String::new();
         for relative_file_path in relative_file_paths {
-            let uri = get_file_uri(&format!("{}/{}", self.relative_root_dir, relative_file_path));
-            let formatted = self.create_formatter().run_format(&uri, None);
-
-            let snapshot = if let Some(formatted) = formatted {
-                get_snapshot_from_text_edits(&formatted)
-            } else {
-                "File is ignored".to_string()
-            };
-
-            let _ = write!(
-                snapshot_result,
-                "========================================\nFile: {}/{}\n========================================\n{}\n",
-                self.relative_root_dir, relative_file_path, snapshot
+            let (uri, content) = self.get_fixture(relative_file_path);
+            let formatted = self.create_formatter().run_format(
+                &uri,
+                content.as_deref(),
+                None,
+                &AtomicBool::new(false),
             );
+            self.write_snapshot_result(&mut snapshot_result, relative_file_path, formatted);
         }
 
+        self.assert_snapshot(relative_file_paths, &snapshot_result);
+    }
+
+    pub fn format(&self, relative_file_path: &str, content: Option<&str>) -> Option<Vec<TextEdit>> {
+        let (uri, fixture_content) = self.get_fixture(relative_file_path);
+        let content = content.or(fixture_content.as_deref());
+        self.create_formatter().run_format(&uri, content, None, &AtomicBool::new(false))
+    }
+
+    pub fn format_range_and_snapshot_single_file(&self, relative_file_path: &str, range: Range) {
+        let mut snapshot_result = String::new();
+        let (uri, content) = self.get_fixture(relative_file_path);
+        let formatted =
+            self.create_formatter().run_format_range(&uri, range, content.as_deref(), None);
+        self.write_snapshot_result(&mut snapshot_result, relative_file_path, formatted);
+
+        self.assert_snapshot(&[relative_file_path], &snapshot_result);
+    }
+
+    pub fn format_on_type(
+        &self,
+        relative_file_path: &str,
+        position: Position,
+        ch: &str,
+    ) -> Option<Vec<TextEdit>> {
+        let (uri, content) = self.get_fixture(relative_file_path);
+        self.create_formatter().run_on_type_format(
+            &uri,
+            position,
+            ch,
+            content.as_deref(),
+            None,
+            &AtomicBool::new(false),
+        )
+    }
+
+    pub fn format_on_type_and_snapshot_single_file(
+        &self,
+        relative_file_path: &str,
+        position: Position,
+        ch: &str,
+    ) {
+        let mut snapshot_result = String::new();
+        let formatted = self.format_on_type(relative_file_path, position, ch);
+        self.write_snapshot_result(&mut snapshot_result, relative_file_path, formatted);
+
+        self.assert_snapshot(&[relative_file_path], &snapshot_result);
+    }
+
+    fn write_snapshot_result(
+        &self,
+        snapshot_result: &mut String,
+        relative_file_path: &str,
+        formatted: Option<Vec<TextEdit>>,
+    ) {
+        let snapshot = if let Some(formatted) = formatted {
+            get_snapshot_from_text_edits(&formatted)
+        } else {
+            "File is ignored".to_string()
+        };
+
+        let _ = write!(
+            snapshot_result,
+            "========================================\nFile: {}/{}\n========================================\n{}\n",
+            self.relative_root_dir, relative_file_path, snapshot
+        );
+    }
+
+    #[expect(clippy::disallowed_methods)]
+    fn assert_snapshot(&self, relative_file_paths: &[&str], snapshot_result: &str) {
         let snapshot_name = self.relative_root_dir.replace('/', "_");
         let mut settings = insta::Settings::clone_current();
         settings.set_prepend_module_to_snapshot(false);
@@ -103,11 +442,21 @@ impl Tester<'_> {
         self.create_formatter().get_watcher_patterns(self.options.clone())
     }
 
+    pub fn handle_watched_file_change(&self, relative_file_path: &str) -> ToolRestartChanges {
+        let builder = ServerFormatterBuilder::new(None);
+        self.create_formatter().handle_watched_file_change(
+            &builder,
+            &get_file_uri(&format!("{}/{relative_file_path}", self.relative_root_dir)),
+            &Self::get_root_uri(self.relative_root_dir),
+            self.options.clone(),
+        )
+    }
+
     pub fn handle_configuration_change(
         &self,
         new_options: serde_json::Value,
     ) -> ToolRestartChanges {
-        let builder = ServerFormatterBuilder;
+        let builder = ServerFormatterBuilder::new(None);
         self.create_formatter().handle_configuration_change(
             &builder,
             &Self::get_root_uri(self.relative_root_dir),
@@ -1,5 +1,6 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use napi_derive::napi;
 
@@ -9,10 +10,11 @@ use serde_json::Value;
 use crate::{
     cli::{FormatRunner, Mode, format_command, init_miette, init_rayon, init_tracing},
     core::{
-        ConfigResolver, ExternalFormatter, FormatFileStrategy, FormatResult as CoreFormatResult,
-        JsFormatEmbeddedCb, JsFormatFileCb, JsInitExternalFormatterCb, SourceFormatter,
+        ConfigResolver, DEFAULT_CALL_RETRIES, ExternalFormatter, FormatFileStrategy,
+        FormatResult as CoreFormatResult, JsFormatEmbeddedCb, JsFormatFileCb,
+        JsInitExternalFormatterCb, SourceFormatter,
     },
-    lsp::run_lsp,
+    lsp::{JsFormatFilesCb, JsLoadConfigCb, NapiExternalFormatterBridge, run_lsp},
     stdin::StdinRunner,
 };
 
@@ -24,6 +26,8 @@ use crate::{
 /// 2. `init_external_formatter_cb`: Callback to initialize external formatter
 /// 3. `format_embedded_cb`: Callback to format embedded code in templates
 /// 4. `format_file_cb`: Callback to format files
+/// 5. `format_files_cb`: Callback to format multiple files at once, used by the language server
+/// 6. `load_config_cb`: Callback to load JS config files, used by the language server
 ///
 /// Returns a tuple of `[mode, exitCode]`:
 /// - `mode`: If main logic will run in JS side, use this to indicate which mode
@@ -40,9 +44,13 @@ pub async fn run_cli(
     )]
     format_embedded_cb: JsFormatEmbeddedCb,
     #[napi(
-        ts_arg_type = "(options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string>"
+        ts_arg_type = "(options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string | null>"
     )]
     format_file_cb: JsFormatFileCb,
+    #[napi(ts_arg_type = "(requests: Array<Record<string, any>>) => Promise<FormatFileResult[]>")]
+    format_files_cb: JsFormatFilesCb,
+    #[napi(ts_arg_type = "(path: string) => Promise<Record<string, any>>")]
+    load_config_cb: JsLoadConfigCb,
 ) -> (String, Option<u8>) {
     // Convert String args to OsString for compatibility with bpaf
     let args: Vec<OsString> = args.into_iter().map(OsString::from).collect();
@@ -62,7 +70,16 @@ pub async fn run_cli(
         Mode::Init => ("init".to_string(), None),
         Mode::Migrate(_) => ("migrate:prettier".to_string(), None),
         Mode::Lsp => {
-            run_lsp().await;
+            // Create external formatter bridge from JS callbacks
+            run_lsp(Some(Arc::new(NapiExternalFormatterBridge::new(
+                init_external_formatter_cb,
+                format_embedded_cb,
+                format_file_cb,
+                format_files_cb,
+                load_config_cb,
+                DEFAULT_CALL_RETRIES,
+            ))))
+            .await;
             ("lsp".to_string(), Some(0))
         }
         Mode::Stdin(_) => {
@@ -75,6 +92,7 @@ pub async fn run_cli(
                     init_external_formatter_cb,
                     format_embedded_cb,
                     format_file_cb,
+                    DEFAULT_CALL_RETRIES,
                 )))
                 .run();
 
@@ -91,6 +109,7 @@ pub async fn run_cli(
                     init_external_formatter_cb,
                     format_embedded_cb,
                     format_file_cb,
+                    DEFAULT_CALL_RETRIES,
                 )))
                 .run();
 
@@ -126,14 +145,18 @@ pub async fn format(
     )]
     format_embedded_cb: JsFormatEmbeddedCb,
     #[napi(
-        ts_arg_type = "(options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string>"
+        ts_arg_type = "(options: Record<string, any>, parserName: string, fileName: string, code: string) => Promise<string | null>"
     )]
     format_file_cb: JsFormatFileCb,
 ) -> FormatResult {
     let num_of_threads = 1;
 
-    let external_formatter =
-        ExternalFormatter::new(init_external_formatter_cb, format_embedded_cb, format_file_cb);
+    let external_formatter = ExternalFormatter::new(
+        init_external_formatter_cb,
+        format_embedded_cb,
+        format_file_cb,
+        DEFAULT_CALL_RETRIES,
+    );
 
     // Create resolver from options and resolve format options
     let mut config_resolver = ConfigResolver::from_value(options.unwrap_or_default());
@@ -0,0 +1,21 @@
+declare module "virtual:config" {
+  export const config: { name: string; debug?: boolean };
+  export default config;
+}
+
+declare module "*.svg" {
+    const content: string;
+    export default content;
+}
+
+declare global {
+  interface Window { __APP_VERSION__: string }
+}
+
+declare namespace App.Utils {
+  function format(value: unknown): string;
+  function format(value: unknown, pretty: boolean): string;
+}
+
+export declare function init(options?: { root: string }): Promise<void>;
+export {};
@@ -0,0 +1,3 @@
+export function init(options?: { root: string }) {
+  return Promise.resolve(options)
+}
@@ -0,0 +1,7 @@
+import data from "./data.json" with {type:"json"};
+import legacy from "./legacy.json" assert {type:"json"};
+import { assertEquals } from "@std/assert";
+export {default as config} from "./config.json" with {type:"json"};
+
+const lazy = await import("./lazy.json", {with:{type:"json"}});
+assertEquals(data.name, legacy.name)
@@ -0,0 +1 @@
+export default { semi: false };
@@ -0,0 +1,11 @@
+import { LitElement, html, svg } from "lit";
+
+export class MyElement extends LitElement {
+  render() {
+    return html`<div><p>Hello</p></div>`;
+  }
+
+  renderIcon() {
+    return svg`<circle r="1"/>`;
+  }
+}
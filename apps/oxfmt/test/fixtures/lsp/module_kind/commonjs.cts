@@ -0,0 +1,3 @@
+const await   =   require("./await.cjs");
+const mode = 0644;
+module.exports = { await, mode };
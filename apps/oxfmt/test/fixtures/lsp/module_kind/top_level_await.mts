@@ -0,0 +1,2 @@
+const config   =   await import("./config.mjs");
+export default config.default;
@@ -0,0 +1,4 @@
+function a() {
+return   1;
+}
+const b   =   2;
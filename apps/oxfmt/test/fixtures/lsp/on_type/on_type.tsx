@@ -0,0 +1,4 @@
+const a   =   1;
+const element = <div   className="a"     id="b">
+  text
+</div>;
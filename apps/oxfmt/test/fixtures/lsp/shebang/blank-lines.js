@@ -0,0 +1,4 @@
+#!/usr/bin/env node
+
+
+console.log(   "hello"   )
@@ -19,15 +19,30 @@ pub struct EmbeddedFormatter {
 /// See <apps/oxfmt/src-js/embedded.ts> for supported tags.
 const SUPPORTED_TAGS: &[&str] = &["css", "styled", "gql", "graphql", "html", "md", "markdown"];
 
+/// Tags of libraries like Lit and uhtml, formatted as the tag they alias.
+const TAG_ALIASES: &[(&str, &str)] =
+    &[("svg", "html"), ("staticHtml", "html"), ("staticSvg", "html")];
+
 impl EmbeddedFormatter {
     /// Create a new embedded formatter with the given callback.
     pub fn new(callback: EmbeddedFormatterCallback) -> Self {
         Self { callback }
     }
 
+    /// Resolve an alias of a supported tag, e.g. `svg` of Lit to `html`.
+    /// Other tags are returned as is.
+    pub fn resolve_tag_alias(tag_name: &str) -> &str {
+        TAG_ALIASES
+            .iter()
+            .find_map(|(alias, tag)| (*alias == tag_name).then_some(*tag))
+            .unwrap_or(tag_name)
+    }
+
     /// Check if the given tag name is supported for embedded formatting.
+    /// Member expression tags (e.g. `styled.div`) are supported if their object is.
     pub fn is_supported_tag(tag_name: &str) -> bool {
-        SUPPORTED_TAGS.contains(&tag_name)
+        let object = tag_name.split('.').next().unwrap_or(tag_name);
+        SUPPORTED_TAGS.contains(&object)
     }
 
     /// Format embedded code with the given tag name.
@@ -146,6 +146,42 @@ pub const fn empty_line() -> Line {
     Line::new(LineMode::Empty)
 }
 
+/// An additional empty line after an [empty_line]. Unlike [empty_line], extra empty lines
+/// are never collapsed, so `n` blank lines are printed as an [empty_line] followed by `n - 1` extra empty lines.
+///
+/// Prefer [blank_lines], which caps the number of blank lines at the `max_preserved_blank_lines` option.
+#[inline]
+pub const fn extra_empty_line() -> Line {
+    Line::new(LineMode::ExtraEmpty)
+}
+
+/// Preserves `count` blank lines of the source, capped at the `max_preserved_blank_lines` option.
+/// Prints a hard line break if no blank line is kept.
+#[inline]
+pub const fn blank_lines(count: usize) -> BlankLines {
+    BlankLines { count }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BlankLines {
+    count: usize,
+}
+
+impl Format<'_> for BlankLines {
+    fn fmt(&self, f: &mut Formatter) {
+        let max = usize::from(f.options().max_preserved_blank_lines.value());
+        match self.count.min(max) {
+            0 => write!(f, [hard_line_break()]),
+            count => {
+                write!(f, [empty_line()]);
+                for _ in 1..count {
+                    write!(f, [extra_empty_line()]);
+                }
+            }
+        }
+    }
+}
+
 /// A line break if the enclosing `Group` doesn't fit on a single line, a space otherwise.
 ///
 /// # Examples
@@ -2395,8 +2431,9 @@ where
     /// that appear before the node in the input source.
     pub fn entry(&mut self, span: Span, content: &dyn Format<'ast>) {
         if self.has_elements {
-            if self.has_lines_before(span) {
-                write!(self.fmt, empty_line());
+            let lines_before = self.fmt.source_text().get_lines_before(span, self.fmt.comments());
+            if lines_before > 1 {
+                write!(self.fmt, blank_lines(lines_before - 1));
             } else {
                 self.separator.fmt(self.fmt);
             }
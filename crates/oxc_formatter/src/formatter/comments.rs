@@ -365,14 +365,17 @@ impl<'a> Comments<'a> {
         &[]
     }
 
-    /// Checks if the node has a suppression comment (prettier-ignore).
+    /// Checks if the node has a suppression comment (oxfmt-ignore or prettier-ignore).
     pub fn is_suppressed(&self, start: u32) -> bool {
         self.comments_before(start).iter().any(|comment| self.is_suppression_comment(comment))
     }
 
+    /// `prettier-ignore` is also supported for compatibility with Prettier.
     pub fn is_suppression_comment(&self, comment: &Comment) -> bool {
-        // TODO: Consider using `oxfmt-ignore` instead of `prettier-ignore`
-        self.source_text.text_for(&comment.content_span()).trim() == "prettier-ignore"
+        matches!(
+            self.source_text.text_for(&comment.content_span()).trim(),
+            "oxfmt-ignore" | "prettier-ignore"
+        )
     }
 
     /// Checks if a comment is a type cast comment containing `@type` or `@satisfies`.
@@ -115,7 +115,9 @@ impl Document<'_> {
                     // `FormatElement::Token` cannot contain line breaks
                     FormatElement::Text { text: _, width } => width.is_multiline(),
                     FormatElement::ExpandParent
-                    | FormatElement::Line(LineMode::Hard | LineMode::Empty) => true,
+                    | FormatElement::Line(
+                        LineMode::Hard | LineMode::Empty | LineMode::ExtraEmpty,
+                    ) => true,
                     _ => false,
                 };
 
@@ -241,6 +243,9 @@ impl<'a> Format<'a> for &[FormatElement<'a>] {
                     LineMode::Empty => {
                         write!(f, [token("empty_line")]);
                     }
+                    LineMode::ExtraEmpty => {
+                        write!(f, [token("extra_empty_line")]);
+                    }
                 },
                 FormatElement::ExpandParent => {
                     write!(f, [token("expand_parent")]);
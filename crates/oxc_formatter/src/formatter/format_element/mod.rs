@@ -125,6 +125,8 @@ pub enum LineMode {
     Hard,
     /// See [crate::builders::empty_line] for documentation.
     Empty,
+    /// See [crate::builders::extra_empty_line] for documentation.
+    ExtraEmpty,
 }
 
 impl LineMode {
@@ -133,7 +135,7 @@ impl LineMode {
     }
 
     pub const fn will_break(self) -> bool {
-        matches!(self, LineMode::Hard | LineMode::Empty)
+        matches!(self, LineMode::Hard | LineMode::Empty | LineMode::ExtraEmpty)
     }
 }
 
@@ -114,7 +114,7 @@ impl<'a> Printer<'a> {
                             }
                             return Ok(());
                         }
-                        LineMode::Hard | LineMode::Empty => {
+                        LineMode::Hard | LineMode::Empty | LineMode::ExtraEmpty => {
                             self.state.measured_group_fits = false;
                         }
                     }
@@ -137,6 +137,12 @@ impl<'a> Printer<'a> {
                     self.state.has_empty_line = true;
                 }
 
+                // Extra empty lines are never collapsed, they always follow an empty line
+                if line_mode == &LineMode::ExtraEmpty {
+                    self.print_char('\n');
+                    self.state.has_empty_line = true;
+                }
+
                 self.state.pending_space = false;
                 self.state.pending_indent = indent_stack.indention();
             }
@@ -976,7 +982,7 @@ impl<'a, 'print> FitsMeasurer<'a, 'print> {
                             self.state.pending_space = true;
                         }
                         LineMode::Soft => {}
-                        LineMode::Hard | LineMode::Empty => {
+                        LineMode::Hard | LineMode::Empty | LineMode::ExtraEmpty => {
                             // Even in flat mode, content that _directly_ contains a hard or empty
                             // line is considered to fit when a hard break is reached, since that
                             // break is always going to exist, regardless of the print mode.
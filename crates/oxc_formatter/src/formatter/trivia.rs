@@ -129,12 +129,12 @@ impl<'a> Format<'a> for FormatLeadingComments<'a> {
                                     write!(f, [hard_line_break()]);
                                 }
                             }
-                            _ => write!(f, [empty_line()]),
+                            lines_after => write!(f, [blank_lines(lines_after - 1)]),
                         }
                     }
                     CommentKind::Line => match f.source_text().lines_after(comment.span.end) {
                         0 | 1 => write!(f, [hard_line_break()]),
-                        _ => write!(f, [empty_line()]),
+                        lines_after => write!(f, [blank_lines(lines_after - 1)]),
                     },
                 }
             }
@@ -83,6 +83,11 @@ impl SortImportsTransform {
                 if matches!(mode, LineMode::Empty) {
                     lines.push(SourceLine::Empty);
                 }
+            } else if matches!(el, FormatElement::Line(LineMode::ExtraEmpty)) {
+                // Always directly follows a `Line(Empty)`, see `blank_lines()`.
+                // Kept as a boundary, so imports separated by more than one empty line are sorted separately.
+                lines.push(SourceLine::Others(idx..idx + 1, LineMode::Hard));
+                current_line_start = idx + 1;
             }
         }
         if current_line_start < prev_elements.len() {
@@ -34,6 +34,10 @@ pub struct FormatOptions {
     /// When properties in objects are quoted. Defaults to as-needed.
     pub quote_properties: QuoteProperties,
 
+    /// Print string literals as they are written, without normalizing their quotes and escapes.
+    /// Used for JSON files, where only double quotes are valid. Defaults to false.
+    pub preserve_string_literals: bool,
+
     /// Print trailing commas wherever possible in multi-line comma-separated syntactic structures. Defaults to "all".
     pub trailing_commas: TrailingCommas,
 
@@ -55,6 +59,10 @@ pub struct FormatOptions {
     /// Whether to expand object and array literals to multiple lines. Defaults to "auto".
     pub expand: Expand,
 
+    /// The maximum number of consecutive blank lines kept between statements, members and comments.
+    /// Longer runs of blank lines are collapsed to this number. Defaults to 1.
+    pub max_preserved_blank_lines: MaxPreservedBlankLines,
+
     /// Controls the position of operators in binary expressions. [**NOT SUPPORTED YET**]
     ///
     /// Accepted values are:
@@ -86,6 +94,7 @@ impl FormatOptions {
             quote_style: QuoteStyle::default(),
             jsx_quote_style: QuoteStyle::default(),
             quote_properties: QuoteProperties::default(),
+            preserve_string_literals: false,
             trailing_commas: TrailingCommas::default(),
             semicolons: Semicolons::default(),
             arrow_parentheses: ArrowParentheses::default(),
@@ -93,6 +102,7 @@ impl FormatOptions {
             bracket_same_line: BracketSameLine::default(),
             attribute_position: AttributePosition::default(),
             expand: Expand::default(),
+            max_preserved_blank_lines: MaxPreservedBlankLines::default(),
             experimental_operator_position: OperatorPosition::default(),
             experimental_ternaries: false,
             embedded_language_formatting: EmbeddedLanguageFormatting::default(),
@@ -121,6 +131,7 @@ impl fmt::Display for FormatOptions {
         writeln!(f, "Bracket same line: {}", self.bracket_same_line.value())?;
         writeln!(f, "Attribute Position: {}", self.attribute_position)?;
         writeln!(f, "Expand lists: {}", self.expand)?;
+        writeln!(f, "Max preserved blank lines: {}", self.max_preserved_blank_lines.value())?;
         writeln!(f, "Experimental operator position: {}", self.experimental_operator_position)?;
         writeln!(f, "Embedded language formatting: {}", self.embedded_language_formatting)?;
         writeln!(f, "Experimental sort imports: {:?}", self.experimental_sort_imports)
@@ -288,6 +299,29 @@ impl fmt::Debug for IndentWidth {
     }
 }
 
+/// The maximum number of consecutive blank lines kept by the formatter, see [`FormatOptions::max_preserved_blank_lines`].
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct MaxPreservedBlankLines(u8);
+
+impl MaxPreservedBlankLines {
+    /// Return the numeric value for this [MaxPreservedBlankLines]
+    pub fn value(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for MaxPreservedBlankLines {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl From<u8> for MaxPreservedBlankLines {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
 /// Validated value for the `line_width` formatter options
 ///
 /// The allowed range of values is 1..=320
@@ -88,17 +88,49 @@ pub struct Oxfmtrc {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub insert_final_newline: Option<bool>,
 
+    /// Whether to remove extra newlines at the end of the file. (Default: `true`)
+    /// If disabled, trailing blank lines of the original file are kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_trailing_newlines: Option<bool>,
+
     /// Experimental: Sort import statements. Disabled by default.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub experimental_sort_imports: Option<SortImportsConfig>,
 
     /// Experimental: Sort `package.json` keys. (Default: `true`)
+    /// Pass an object to configure how they are sorted.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub experimental_sort_package_json: Option<bool>,
+    pub experimental_sort_package_json: Option<SortPackageJsonUserConfig>,
+
+    /// Sort the keys of objects in JSON, JSONC and JSON5 files,
+    /// except for objects containing comments. (Default: `false`)
+    /// Use `overrides` to enable it only for some files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_json_keys: Option<bool>,
 
     /// Ignore files matching these glob patterns. Current working directory is used as the root.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ignore_patterns: Option<Vec<String>>,
+
+    /// Stop looking for config files in parent directories. (Default: `false`)
+    /// Otherwise, the options of `.oxfmtrc.json` files in parent directories are merged, the closest one wins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<bool>,
+
+    /// Override options for files matching these glob patterns.
+    /// When multiple overrides match a file, the last one wins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<Vec<OxfmtOverride>>,
+}
+
+/// Options applied on top of the base config for files matching `files`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OxfmtOverride {
+    /// Glob patterns of files to apply these options to. Current working directory is used as the root.
+    pub files: Vec<String>,
+    /// Options to override. `ignorePatterns`, `root` and `overrides` are not allowed here.
+    pub options: Oxfmtrc,
 }
 
 // ---
@@ -180,6 +212,24 @@ pub struct SortImportsConfig {
     pub groups: Option<Vec<Vec<String>>>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum SortPackageJsonUserConfig {
+    Bool(bool),
+    Object(SortPackageJsonConfig),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SortPackageJsonConfig {
+    /// Sort the `scripts` field alphabetically. (Default: `false`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_scripts: Option<bool>,
+    /// Print the sorted `package.json` with 2-space indentation before formatting it. (Default: `false`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pretty: Option<bool>,
+}
+
 /// Custom deserializer for groups field to support both `string` and `string[]` as group elements
 fn deserialize_groups<'de, D>(deserializer: D) -> Result<Option<Vec<Vec<String>>>, D::Error>
 where
@@ -242,13 +292,33 @@ pub enum SortOrderConfig {
 #[derive(Debug, Clone)]
 pub struct OxfmtOptions {
     pub ignore_patterns: Vec<String>,
-    pub sort_package_json: bool,
+    /// `None` if `package.json` keys are not sorted.
+    pub sort_package_json: Option<SortPackageJsonOptions>,
+    pub sort_json_keys: bool,
     pub insert_final_newline: bool,
+    pub trim_trailing_newlines: bool,
+    /// Whether to keep the existing line endings of the file, `true` if `endOfLine` is not set.
+    /// `oxc_formatter` always prints `FormatOptions::line_ending`, so it is up to the caller.
+    pub keep_line_endings: bool,
+}
+
+/// Options of sorting `package.json`, resolved from `experimentalSortPackageJson`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SortPackageJsonOptions {
+    pub sort_scripts: bool,
+    pub pretty: bool,
 }
 
 impl Default for OxfmtOptions {
     fn default() -> Self {
-        Self { ignore_patterns: vec![], sort_package_json: true, insert_final_newline: true }
+        Self {
+            ignore_patterns: vec![],
+            sort_package_json: Some(SortPackageJsonOptions::default()),
+            sort_json_keys: false,
+            insert_final_newline: true,
+            trim_trailing_newlines: true,
+            keep_line_endings: true,
+        }
     }
 }
 
@@ -428,12 +498,26 @@ impl Oxfmtrc {
         if let Some(patterns) = self.ignore_patterns {
             oxfmt_options.ignore_patterns = patterns;
         }
-        if let Some(sort_package_json) = self.experimental_sort_package_json {
-            oxfmt_options.sort_package_json = sort_package_json;
+        match self.experimental_sort_package_json {
+            None | Some(SortPackageJsonUserConfig::Bool(true)) => {}
+            Some(SortPackageJsonUserConfig::Bool(false)) => oxfmt_options.sort_package_json = None,
+            Some(SortPackageJsonUserConfig::Object(config)) => {
+                oxfmt_options.sort_package_json = Some(SortPackageJsonOptions {
+                    sort_scripts: config.sort_scripts.unwrap_or(false),
+                    pretty: config.pretty.unwrap_or(false),
+                });
+            }
+        }
+        if let Some(sort_json_keys) = self.sort_json_keys {
+            oxfmt_options.sort_json_keys = sort_json_keys;
         }
         if let Some(insert_final_newline) = self.insert_final_newline {
             oxfmt_options.insert_final_newline = insert_final_newline;
         }
+        if let Some(trim_trailing_newlines) = self.trim_trailing_newlines {
+            oxfmt_options.trim_trailing_newlines = trim_trailing_newlines;
+        }
+        oxfmt_options.keep_line_endings = self.end_of_line.is_none();
 
         Ok((format_options, oxfmt_options))
     }
@@ -564,9 +648,12 @@ impl Oxfmtrc {
 
         // Below are our own extensions, just remove them
         obj.remove("ignorePatterns");
+        obj.remove("root");
         obj.remove("insertFinalNewline");
+        obj.remove("trimTrailingNewlines");
         obj.remove("experimentalSortImports");
         obj.remove("experimentalSortPackageJson");
+        obj.remove("sortJsonKeys");
 
         // Any other unknown fields are preserved as-is.
         // e.g. `plugins`, `htmlWhitespaceSensitivity`, `vueIndentScriptAndStyle`, etc.
@@ -749,6 +836,30 @@ mod tests {
         assert_eq!(format_options.expand, Expand::Always);
     }
 
+    #[test]
+    fn test_sort_package_json_config() {
+        let sort_package_json = |json: &str| {
+            let config: Oxfmtrc = serde_json::from_str(json).unwrap();
+            config.into_options().unwrap().1.sort_package_json
+        };
+
+        // Sorted by default, without sorting scripts
+        assert_eq!(sort_package_json("{}"), Some(SortPackageJsonOptions::default()));
+        assert_eq!(
+            sort_package_json(r#"{"experimentalSortPackageJson": true}"#),
+            Some(SortPackageJsonOptions::default())
+        );
+        assert_eq!(sort_package_json(r#"{"experimentalSortPackageJson": false}"#), None);
+        assert_eq!(
+            sort_package_json(r#"{"experimentalSortPackageJson": { "sortScripts": true }}"#),
+            Some(SortPackageJsonOptions { sort_scripts: true, pretty: false })
+        );
+        assert_eq!(
+            sort_package_json(r#"{"experimentalSortPackageJson": { "pretty": true }}"#),
+            Some(SortPackageJsonOptions { sort_scripts: false, pretty: true })
+        );
+    }
+
     #[test]
     fn test_sort_imports_config() {
         let config: Oxfmtrc = serde_json::from_str(
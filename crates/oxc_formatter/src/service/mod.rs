@@ -11,6 +11,8 @@ pub fn get_parse_options() -> ParseOptions {
         allow_v8_intrinsics: true,
         // `oxc_formatter` expects this to be `false`, otherwise panics
         preserve_parens: false,
+        // NOTE: Import attributes, e.g. `with { type: "json" }`, and the legacy `assert { ... }` syntax
+        // still found in Deno projects, are always parsed, so no option is needed for them.
     }
 }
 
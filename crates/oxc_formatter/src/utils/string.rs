@@ -41,6 +41,9 @@ impl<'a> FormatLiteralStringToken<'a> {
 
     pub fn clean_text(&self, f: &Formatter<'_, 'a>) -> CleanedStringLiteralText<'a> {
         let options = f.options();
+        if options.preserve_string_literals {
+            return CleanedStringLiteralText { text: Cow::Borrowed(self.string) };
+        }
         let source_type = f.context().source_type();
 
         let chosen_quote_style =
@@ -690,11 +690,13 @@ fn try_format_embedded_template<'a>(
         return false;
     }
 
-    let Expression::Identifier(ident) = &tagged.tag else {
-        return false;
+    let tag_name = match &tagged.tag {
+        Expression::Identifier(ident) => ident.name.as_str(),
+        // Pass the full tag text, e.g. `styled.div`
+        Expression::StaticMemberExpression(_) => f.source_text().text_for(&tagged.tag),
+        _ => return false,
     };
-
-    let tag_name = ident.name.as_str();
+    let tag_name = EmbeddedFormatter::resolve_tag_alias(tag_name);
     // Check if the tag is supported by the embedded formatter
     if !EmbeddedFormatter::is_supported_tag(tag_name) {
         return false;
@@ -713,13 +715,18 @@ fn try_format_embedded_template<'a>(
     // Format with proper template literal structure:
     // - Opening backtick
     // - Hard line break (newline after backtick)
-    // - Indented content (each line will be indented)
+    // - Indented content (each line is indented relative to the template, however deeply it is nested)
     // - Hard line break (newline before closing backtick)
-    // - Closing backtick
+    // - Closing backtick, at the indentation of the line the template starts on
     let format_content = format_with(|f: &mut Formatter<'_, 'a>| {
         let content = f.context().allocator().alloc_str(&formatted);
         for line in content.split('\n') {
-            write!(f, [text(line), hard_line_break()]);
+            if line.trim().is_empty() {
+                // Not indented, so that blank lines have no trailing whitespace
+                write!(f, [empty_line()]);
+            } else {
+                write!(f, [text(line), hard_line_break()]);
+            }
         }
     });
 
@@ -0,0 +1,36 @@
+import a from "a";
+
+
+
+import b from "b";
+const x = 1;
+
+const y = 2;
+
+
+
+// comment after three blank lines
+
+
+
+function foo() {
+  bar();
+
+
+  baz();
+}
+
+class A {
+  a = 1;
+
+
+
+  b() {}
+}
+
+const obj = {
+  a: 1,
+
+
+  b: 2,
+};
@@ -0,0 +1,25 @@
+export function createTheme(dark) {
+  return {
+    components: {
+      Button: {
+        root: styled.button`color:red;background:blue;padding:10px 20px;`,
+        variant: dark ? css`color:white;background:black;` : css`color:black;background:white;`,
+        outlined: [
+          styled.a`
+                border:1px solid;
+
+                    text-decoration:none;
+          `,
+        ],
+      },
+    },
+  };
+}
+
+class Card {
+  render() {
+    if (this.props.open) {
+      return css`display:flex;`;
+    }
+  }
+}
@@ -0,0 +1,26 @@
+// oxfmt-ignore
+const matrix = {
+  a: [1,   0,   0],
+  b: [0,   1,   0],
+  c: [0,   0,   1],
+};
+
+/* oxfmt-ignore */
+const aligned = {
+  short:       1,
+  longerName:  2,
+};
+
+const formatted = {
+  short:       1,
+  longerName:  2,
+};
+
+const config = {
+  // oxfmt-ignore
+  identity: {
+    x: [1,   0],
+    y: [0,   1],
+  },
+  other:   1,
+};
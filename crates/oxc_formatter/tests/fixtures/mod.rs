@@ -1,10 +1,10 @@
-use std::{env::current_dir, fs, path::Path};
+use std::{env::current_dir, fs, path::Path, sync::Arc};
 
 use oxc_allocator::Allocator;
 use oxc_formatter::{
-    ArrowParentheses, BracketSameLine, BracketSpacing, FormatOptions, Formatter, IndentStyle,
-    IndentWidth, LineEnding, LineWidth, QuoteProperties, QuoteStyle, Semicolons, TrailingCommas,
-    get_parse_options,
+    ArrowParentheses, BracketSameLine, BracketSpacing, EmbeddedFormatter, FormatOptions, Formatter,
+    IndentStyle, IndentWidth, LineEnding, LineWidth, QuoteProperties, QuoteStyle, Semicolons,
+    TrailingCommas, get_parse_options,
 };
 use oxc_parser::Parser;
 use oxc_span::SourceType;
@@ -129,6 +129,16 @@ fn parse_format_options(json: &OptionSet) -> FormatOptions {
                     };
                 }
             }
+            "maxPreservedBlankLines" => {
+                if let Some(n) = value.as_u64() {
+                    options.max_preserved_blank_lines = u8::try_from(n).unwrap().into();
+                }
+            }
+            "embeddedLanguageFormatting" => {
+                if let Some(s) = value.as_str() {
+                    options.embedded_language_formatting = s.parse().unwrap_or_default();
+                }
+            }
             _ => {}
         }
     }
@@ -154,8 +164,36 @@ fn format_source(source_text: &str, source_type: SourceType, options: FormatOpti
         Parser::new(&allocator, source_text, source_type).with_options(get_parse_options()).parse();
     assert!(ret.errors.is_empty());
 
+    let embedded_language_formatting = options.embedded_language_formatting;
     let formatter = Formatter::new(&allocator, options);
-    formatter.build(&ret.program)
+    if embedded_language_formatting.is_off() {
+        return formatter.build(&ret.program);
+    }
+    formatter
+        .format_with_embedded(&ret.program, EmbeddedFormatter::new(Arc::new(format_embedded)))
+        .print()
+        .unwrap()
+        .into_code()
+}
+
+/// Stand-in for the external formatter of embedded code,
+/// which puts each `;` terminated declaration on its own line and keeps blank lines between them.
+#[expect(clippy::unnecessary_wraps)]
+fn format_embedded(_tag_name: &str, code: &str) -> Result<String, String> {
+    let lines = code
+        .trim()
+        .lines()
+        .flat_map(|line| {
+            let declarations = line
+                .split(';')
+                .map(str::trim)
+                .filter(|declaration| !declaration.is_empty())
+                .map(|declaration| format!("{declaration};"))
+                .collect::<Vec<_>>();
+            if declarations.is_empty() { vec![String::new()] } else { declarations }
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
 }
 
 /// Generate snapshot for a test file
@@ -1,22 +1,36 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 use futures::future::join_all;
 use log::{debug, error, info, warn};
 use rustc_hash::FxBuildHasher;
+use serde::Deserialize;
 use serde_json::Value;
-use tokio::sync::{OnceCell, RwLock, SetError};
+use tokio::{
+    sync::{Mutex, OnceCell, RwLock, SetError, mpsc},
+    task::JoinHandle,
+};
 use tower_lsp_server::{
     Client, LanguageServer,
     jsonrpc::{Error, ErrorCode, Result},
     ls_types::{
-        CodeActionParams, CodeActionResponse, ConfigurationItem, Diagnostic,
+        CodeAction, CodeActionParams, CodeActionResponse, ConfigurationItem, Diagnostic,
         DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
         DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
         DidSaveTextDocumentParams, DocumentDiagnosticParams, DocumentDiagnosticReport,
         DocumentDiagnosticReportKind, DocumentDiagnosticReportResult, DocumentFormattingParams,
-        ExecuteCommandParams, FullDocumentDiagnosticReport, InitializeParams, InitializeResult,
-        InitializedParams, MessageType, RelatedFullDocumentDiagnosticReport, ServerInfo, TextEdit,
-        Uri,
+        DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, ExecuteCommandParams,
+        FileEvent, FormattingOptions, FullDocumentDiagnosticReport, InitializeParams,
+        InitializeResult, InitializedParams, MessageType, ProgressParams, ProgressParamsValue,
+        ProgressToken, Range, RelatedFullDocumentDiagnosticReport, ServerInfo,
+        TextDocumentIdentifier, TextEdit, Uri, WillSaveTextDocumentParams, WorkDoneProgress,
+        WorkDoneProgressBegin, WorkDoneProgressEnd, WorkDoneProgressReport, notification::Progress,
     },
 };
 
@@ -70,8 +84,14 @@ pub struct Backend {
     // The client will send the content of in-memory files on `textDocument/didOpen` and `textDocument/didChange`.
     // This is only needed when the client supports `textDocument/formatting` request.
     file_system: Arc<RwLock<LSPFileSystem>>,
+    // Watched file changes waiting for `WATCHED_FILES_DEBOUNCE` to pass before they are handled.
+    pending_watched_files: Mutex<Vec<FileEvent>>,
 }
 
+/// Watched file changes arriving within this time are handled together,
+/// e.g. when an editor fires several events for one save, so each tool is restarted at most once.
+const WATCHED_FILES_DEBOUNCE: Duration = Duration::from_millis(100);
+
 impl LanguageServer for Backend {
     /// Initialize the language server with the given parameters.
     /// This method sets up workspace workers, capabilities, and starts the
@@ -158,7 +178,8 @@ impl LanguageServer for Backend {
                 let option = options
                     .iter()
                     .find(|workspace_option| {
-                        worker.is_responsible_for_uri(&workspace_option.workspace_uri)
+                        find_worker(&workers, &workspace_option.workspace_uri)
+                            .is_some_and(|found| std::ptr::eq(found, worker))
                     })
                     .map(|workspace_options| workspace_options.options.clone())
                     .unwrap_or_default();
@@ -233,7 +254,8 @@ impl LanguageServer for Backend {
                 // run diagnostics for all known files in the workspace of the worker.
                 // This is necessary because the worker was not started before.
                 for uri in &known_files {
-                    if !worker.is_responsible_for_uri(uri) {
+                    if !find_worker(workers, uri).is_some_and(|found| std::ptr::eq(found, *worker))
+                    {
                         continue;
                     }
                     let content = self.file_system.read().await.get(uri);
@@ -255,6 +277,8 @@ impl LanguageServer for Backend {
             }
         }
 
+        self.show_setup_warnings(workers).await;
+
         let mut registrations = vec![];
 
         // init all file watchers
@@ -368,9 +392,7 @@ impl LanguageServer for Backend {
         let fs_ref = fs_guard.as_deref();
 
         for option in resolved_options {
-            let Some(worker) =
-                workers.iter().find(|worker| worker.is_responsible_for_uri(&option.workspace_uri))
-            else {
+            let Some(worker) = find_worker(&workers, &option.workspace_uri) else {
                 continue;
             };
 
@@ -386,6 +408,8 @@ impl LanguageServer for Backend {
             adding_registrations.extend(registrations);
         }
 
+        self.show_setup_warnings(&workers).await;
+
         if is_push_diagnostics && !new_diagnostics.is_empty() {
             self.publish_all_diagnostics(new_diagnostics, ConcurrentHashMap::default()).await;
         }
@@ -411,11 +435,26 @@ impl LanguageServer for Backend {
 
     /// This notification is sent when a configuration file of a tool changes (example: `.oxlintrc.json`).
     /// The server will re-lint the affected files and send updated diagnostics.
+    /// Changes of notifications arriving within [`WATCHED_FILES_DEBOUNCE`] are handled together.
     ///
     /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#workspace_didChangeWatchedFiles>
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        if params.changes.is_empty() {
+            return;
+        }
+        // The first notification waits for the debounce time and handles the changes of all notifications in between
+        {
+            let mut pending_watched_files = self.pending_watched_files.lock().await;
+            let is_first = pending_watched_files.is_empty();
+            pending_watched_files.extend(params.changes);
+            if !is_first {
+                return;
+            }
+        }
+        tokio::time::sleep(WATCHED_FILES_DEBOUNCE).await;
+        let changes = std::mem::take(&mut *self.pending_watched_files.lock().await);
+
         let workers = self.workspace_workers.read().await;
-        // ToDo: what if an empty changes flag is passed?
         debug!("watched file did change");
 
         let mut new_diagnostics = Vec::new();
@@ -428,17 +467,21 @@ impl LanguageServer for Backend {
         let fs_guard = if is_push_diagnostics { Some(self.file_system.read().await) } else { None };
         let fs_ref = fs_guard.as_deref();
 
-        for file_event in &params.changes {
-            // We do not expect multiple changes from the same workspace folder.
-            // If we should consider it, we need to map the events to the workers first,
-            // to only restart the internal linter / diagnostics for once
-            let Some(worker) =
-                workers.iter().find(|worker| worker.is_responsible_for_uri(&file_event.uri))
-            else {
+        // Map the events to the workers first, so each worker restarts its tools only once
+        let mut events_by_worker: Vec<(&WorkspaceWorker, Vec<FileEvent>)> = vec![];
+        for file_event in changes {
+            let Some(worker) = find_worker(&workers, &file_event.uri) else {
                 continue;
             };
+            match events_by_worker.iter_mut().find(|(w, _)| std::ptr::eq(*w, worker)) {
+                Some((_, file_events)) => file_events.push(file_event),
+                None => events_by_worker.push((worker, vec![file_event])),
+            }
+        }
+
+        for (worker, file_events) in events_by_worker {
             let (diagnostics, registrations, unregistrations) = worker
-                .did_change_watched_files(file_event, &mut needs_diagnostics_refresh, fs_ref)
+                .did_change_watched_files(&file_events, &mut needs_diagnostics_refresh, fs_ref)
                 .await;
 
             if let Some(diagnostics) = diagnostics {
@@ -448,6 +491,8 @@ impl LanguageServer for Backend {
             adding_registrations.extend(registrations);
         }
 
+        self.show_setup_warnings(&workers).await;
+
         if is_push_diagnostics && !new_diagnostics.is_empty() {
             self.publish_all_diagnostics(new_diagnostics, ConcurrentHashMap::default()).await;
         }
@@ -487,13 +532,12 @@ impl LanguageServer for Backend {
         let mut removed_registrations = vec![];
 
         for folder in params.event.removed {
-            let Some((index, worker)) = workers
-                .iter()
-                .enumerate()
-                .find(|(_, worker)| worker.is_responsible_for_uri(&folder.uri))
+            let Some(index) = find_worker(&workers, &folder.uri)
+                .and_then(|found| workers.iter().position(|worker| std::ptr::eq(worker, found)))
             else {
                 continue;
             };
+            let worker = &workers[index];
             let (uris, unregistrations) = worker.shutdown().await;
             cleared_diagnostics.extend(uris);
             removed_registrations.extend(unregistrations);
@@ -544,6 +588,8 @@ impl LanguageServer for Backend {
             }
         }
 
+        self.show_setup_warnings(&workers).await;
+
         // tell client to stop / start watching for files
         if self.capabilities.get().is_some_and(|capabilities| capabilities.dynamic_watchers) {
             if !added_registrations.is_empty()
@@ -568,7 +614,7 @@ impl LanguageServer for Backend {
         debug!("oxc server did save");
         let uri = params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(&uri)) else {
+        let Some(worker) = find_worker(&workers, &uri) else {
             return;
         };
 
@@ -596,17 +642,20 @@ impl LanguageServer for Backend {
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let uri = params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(&uri)) else {
+        let Some(worker) = find_worker(&workers, &uri) else {
+            return;
+        };
+        let Some(content) = self.file_system.write().await.apply_changes(
+            &uri,
+            params.text_document.version,
+            params.content_changes,
+        ) else {
+            // The last known content is kept, its diagnostics are still up to date
             return;
         };
-        let content = params.content_changes.first().map(|c| c.text.clone());
-
-        if let Some(content) = &content {
-            self.file_system.write().await.set(uri.clone(), content.clone());
-        }
 
         if self.capabilities.get().is_some_and(Capabilities::use_push_diagnostics) {
-            match worker.run_diagnostic_on_change(&uri, content.as_deref()).await {
+            match worker.run_diagnostic_on_change(&uri, Some(&content)).await {
                 Err(err) => {
                     error!("running diagnostics for {} failed: {err}", uri.as_str());
                     if self.capabilities.get().is_some_and(|cap| cap.show_message) {
@@ -631,13 +680,18 @@ impl LanguageServer for Backend {
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let uri = params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(&uri)) else {
+        let Some(worker) = find_worker(&workers, &uri) else {
             return;
         };
 
         let content = params.text_document.text;
 
-        self.file_system.write().await.set(uri.clone(), content.clone());
+        self.file_system.write().await.set(
+            uri.clone(),
+            content.clone(),
+            params.text_document.version,
+            params.text_document.language_id,
+        );
 
         if self.capabilities.get().is_some_and(Capabilities::use_push_diagnostics) {
             match worker.run_diagnostic(&uri, Some(&content)).await {
@@ -665,7 +719,7 @@ impl LanguageServer for Backend {
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let uri = &params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(uri)) else {
+        let Some(worker) = find_worker(&workers, uri) else {
             return;
         };
 
@@ -680,12 +734,18 @@ impl LanguageServer for Backend {
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
         let uri = &params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(uri)) else {
+        let Some(worker) = find_worker(&workers, uri) else {
             return Ok(None);
         };
 
-        let code_actions =
-            worker.get_code_actions_or_commands(uri, &params.range, params.context.only).await;
+        let code_actions = worker
+            .get_code_actions_or_commands(
+                uri,
+                &params.range,
+                self.file_system.read().await.get(uri).as_deref(),
+                params.context.only,
+            )
+            .await;
 
         if code_actions.is_empty() {
             return Ok(None);
@@ -694,34 +754,120 @@ impl LanguageServer for Backend {
         Ok(Some(code_actions))
     }
 
+    /// Computes the edit of a code action returned without one, see [`Tool::resolve_code_action`].
+    /// The `data` of such a code action contains the URI of its document.
+    /// Code actions of unknown documents are returned as is.
+    ///
+    /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#codeAction_resolve>
+    ///
+    /// [`Tool::resolve_code_action`]: crate::Tool::resolve_code_action
+    async fn code_action_resolve(&self, params: CodeAction) -> Result<CodeAction> {
+        let Some(uri) = params
+            .data
+            .as_ref()
+            .and_then(|data| data.get("uri"))
+            .and_then(Value::as_str)
+            .and_then(|uri| uri.parse::<Uri>().ok())
+        else {
+            return Ok(params);
+        };
+        let workers = self.workspace_workers.read().await;
+        let Some(worker) = find_worker(&workers, &uri) else {
+            return Ok(params);
+        };
+
+        let content = self.file_system.read().await.get(&uri);
+        Ok(worker.resolve_code_action(&params, content.as_deref()).await.unwrap_or(params))
+    }
+
     /// It will execute the given command with the provided arguments.
-    /// Currently, only the `fixAll` command is supported.
+    /// Currently, only the `fixAll` and `formatWorkspace` commands are supported.
+    ///
+    /// If the client sent a work done token, the progress of the command is reported with `$/progress`.
     ///
     /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#workspace_executeCommand>
     async fn execute_command(
         &self,
         params: ExecuteCommandParams,
     ) -> Result<Option<serde_json::Value>> {
-        for worker in self.workspace_workers.read().await.iter() {
-            match worker.execute_command(&params.command, params.arguments.clone()).await {
+        let progress = params.work_done_progress_params.work_done_token.map(|token| {
+            spawn_work_done_progress(self.client.clone(), token, params.command.clone())
+        });
+        let sender = progress.as_ref().map(|(sender, _)| sender.clone());
+        let open_documents = self.file_system.read().await.documents();
+        let workspace_workers = Arc::clone(&self.workspace_workers);
+        let command = params.command;
+        let arguments = params.arguments;
+        let is_cancelled = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(Arc::clone(&is_cancelled));
+
+        // Execute the command on a separate task, so it can stop early when the client cancels the request,
+        // see `format_file_cancellable`
+        let results = tokio::spawn(async move {
+            let report = |done: usize, total: usize| {
+                if let Some(sender) = &sender {
+                    // The receiver is only dropped after all commands are executed
+                    let _ = sender.send((done, total));
+                }
+            };
+            let open_document = |uri: &Uri| open_documents.get(uri).cloned();
+
+            let mut results = vec![];
+            for worker in workspace_workers.read().await.iter() {
+                let result = worker
+                    .execute_command(
+                        &command,
+                        arguments.clone(),
+                        &open_document,
+                        &report,
+                        &is_cancelled,
+                    )
+                    .await;
+                let is_err = result.is_err();
+                results.push(result);
+                if is_err {
+                    break;
+                }
+            }
+            results
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut result = Ok(None);
+        for changes in results {
+            match changes {
                 Ok(changes) => {
                     let Some(edit) = changes else {
                         continue;
                     };
 
                     if !self.capabilities.get().unwrap().workspace_apply_edit {
-                        return Err(Error::invalid_params(
+                        result = Err(Error::invalid_params(
                             "client does not support workspace apply edit",
                         ));
+                        break;
                     }
 
-                    self.client.apply_edit(edit).await?;
+                    if let Err(err) = self.client.apply_edit(edit).await {
+                        result = Err(err);
+                        break;
+                    }
+                }
+                Err(err) => {
+                    result = Err(Error::new(err));
+                    break;
                 }
-                Err(err) => return Err(Error::new(err)),
             }
         }
 
-        Ok(None)
+        // End the progress even if the command failed
+        if let Some((sender, task)) = progress {
+            drop(sender);
+            let _ = task.await;
+        }
+
+        result
     }
 
     async fn diagnostic(
@@ -730,7 +876,7 @@ impl LanguageServer for Backend {
     ) -> Result<DocumentDiagnosticReportResult> {
         let uri = &params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(uri)) else {
+        let Some(worker) = find_worker(&workers, uri) else {
             return Ok(DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(
                 RelatedFullDocumentDiagnosticReport::default(),
             )));
@@ -793,12 +939,76 @@ impl LanguageServer for Backend {
     ///
     /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#textDocument_formatting>
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        Ok(self.format_file_cancellable(params.text_document.uri, Some(params.options)).await)
+    }
+
+    /// It will return text edits to format the document before it is saved,
+    /// for clients that format on save via `willSaveWaitUntil` instead of `textDocument/formatting`.
+    ///
+    /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#textDocument_willSaveWaitUntil>
+    async fn will_save_wait_until(
+        &self,
+        params: WillSaveTextDocumentParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        Ok(self.format_file_cancellable(params.text_document.uri, None).await)
+    }
+
+    /// It will return text edits to format the given range of the document if formatting is enabled for the workspace.
+    ///
+    /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#textDocument_rangeFormatting>
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
         let uri = &params.text_document.uri;
         let workers = self.workspace_workers.read().await;
-        let Some(worker) = workers.iter().find(|worker| worker.is_responsible_for_uri(uri)) else {
+        let Some(worker) = find_worker(&workers, uri) else {
             return Ok(None);
         };
-        Ok(worker.format_file(uri, self.file_system.read().await.get(uri).as_deref()).await)
+        Ok(worker
+            .format_file_range(
+                uri,
+                params.range,
+                self.file_system.read().await.get(uri).as_deref(),
+                Some(&params.options),
+            )
+            .await)
+    }
+
+    /// It will be called when the user types one of the trigger characters advertised by the tools.
+    /// It will return text edits to format the code around the typed character if formatting is enabled for the workspace.
+    /// The formatting runs on a separate task, so it stops early when the client cancels the request,
+    /// same as [`Self::format_file_cancellable`].
+    ///
+    /// See: <https://microsoft.github.io/language-server-protocol/specifications/specification-current/#textDocument_onTypeFormatting>
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let DocumentOnTypeFormattingParams { text_document_position, ch, options } = params;
+        let uri = text_document_position.text_document.uri;
+        let content = self.file_system.read().await.get(&uri);
+        let workspace_workers = Arc::clone(&self.workspace_workers);
+        let is_cancelled = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(Arc::clone(&is_cancelled));
+
+        Ok(tokio::spawn(async move {
+            let workers = workspace_workers.read().await;
+            let worker = find_worker(&workers, &uri)?;
+            worker
+                .format_file_on_type(
+                    &uri,
+                    text_document_position.position,
+                    &ch,
+                    content.as_deref(),
+                    Some(&options),
+                    &is_cancelled,
+                )
+                .await
+        })
+        .await
+        .ok()
+        .flatten())
     }
 }
 
@@ -815,6 +1025,146 @@ impl Backend {
             workspace_workers: Arc::new(RwLock::new(vec![])),
             capabilities: OnceCell::new(),
             file_system: Arc::new(RwLock::new(LSPFileSystem::default())),
+            pending_watched_files: Mutex::new(vec![]),
+        }
+    }
+
+    /// Handle a custom request registered by [`ToolBuilder::custom_request_methods`],
+    /// with the first workspace whose tools respond to the method.
+    ///
+    /// The returned future does not borrow the backend,
+    /// so it can be registered for any method name with [`LspServiceBuilder::custom_method`].
+    ///
+    /// [`LspServiceBuilder::custom_method`]: tower_lsp_server::LspServiceBuilder::custom_method
+    pub(crate) fn custom_request(
+        &self,
+        method: &'static str,
+    ) -> impl Future<Output = Result<Value>> + Send + use<> {
+        let workspace_workers = Arc::clone(&self.workspace_workers);
+        async move {
+            for worker in workspace_workers.read().await.iter() {
+                if let Some(result) = worker.handle_custom_request(method).await {
+                    return Ok(result);
+                }
+            }
+            Err(Error::method_not_found())
+        }
+    }
+
+    /// Handle a custom request registered by [`ToolBuilder::custom_text_document_request_methods`],
+    /// with the workspace of the document and its content opened in the editor, if any.
+    /// Responds with `null` if no workspace contains the document.
+    ///
+    /// Same as [`Backend::custom_request`], the returned future does not borrow the backend.
+    pub(crate) fn custom_text_document_request(
+        &self,
+        method: &'static str,
+        params: CustomTextDocumentParams,
+    ) -> impl Future<Output = Result<Value>> + Send + use<> {
+        let workspace_workers = Arc::clone(&self.workspace_workers);
+        let file_system = Arc::clone(&self.file_system);
+        async move {
+            let uri = params.text_document.uri;
+            let workers = workspace_workers.read().await;
+            let Some(worker) = find_worker(&workers, &uri) else {
+                return Ok(Value::Null);
+            };
+            let content = file_system.read().await.get(&uri);
+            worker
+                .handle_custom_text_document_request(method, &uri, content.as_deref())
+                .await
+                .ok_or_else(Error::method_not_found)
+        }
+    }
+
+    /// It will return text edits to format the code pasted into the given range of the document,
+    /// for clients supporting the proposed `textDocument/documentPaste` request.
+    /// The range of the pasted code is sent by the client.
+    pub(crate) async fn document_paste(
+        &self,
+        params: DocumentPasteParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let workers = self.workspace_workers.read().await;
+        let Some(worker) = find_worker(&workers, uri) else {
+            return Ok(None);
+        };
+        Ok(worker
+            .format_file_paste(uri, params.range, self.file_system.read().await.get(uri).as_deref())
+            .await)
+    }
+
+    /// It will return text edits to format multiple ranges of the document at once,
+    /// for clients supporting the `textDocument/rangesFormatting` request of LSP 3.18.
+    /// The file is formatted once, instead of once per range with `textDocument/rangeFormatting`.
+    pub(crate) async fn ranges_formatting(
+        &self,
+        params: DocumentRangesFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let workers = self.workspace_workers.read().await;
+        let Some(worker) = find_worker(&workers, uri) else {
+            return Ok(None);
+        };
+        Ok(worker
+            .format_file_ranges(
+                uri,
+                &params.ranges,
+                self.file_system.read().await.get(uri).as_deref(),
+                Some(&params.options),
+            )
+            .await)
+    }
+
+    /// Format the file on a separate task, so formatting can stop early when the client cancels the request.
+    ///
+    /// `tower_lsp_server` drops the request future on `$/cancelRequest`,
+    /// which drops the [`CancelOnDrop`] guard and marks the formatting as cancelled.
+    async fn format_file_cancellable(
+        &self,
+        uri: Uri,
+        options: Option<FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        let (content, language_id) = {
+            let file_system = self.file_system.read().await;
+            (file_system.get(&uri), file_system.get_language_id(&uri))
+        };
+        let workspace_workers = Arc::clone(&self.workspace_workers);
+        let is_cancelled = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(Arc::clone(&is_cancelled));
+
+        tokio::spawn(async move {
+            let workers = workspace_workers.read().await;
+            let worker = find_worker(&workers, &uri)?;
+            if is_untitled_uri(&uri) {
+                // Only the in-memory content exists, the language id tells how to format it
+                return worker
+                    .format_untitled_file(
+                        &uri,
+                        &language_id?,
+                        &content?,
+                        options.as_ref(),
+                        &is_cancelled,
+                    )
+                    .await;
+            }
+            worker.format_file(&uri, content.as_deref(), options.as_ref(), &is_cancelled).await
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Show the warnings of the (re)built tools to the user, e.g. an invalid config file.
+    async fn show_setup_warnings(&self, workers: &[WorkspaceWorker]) {
+        let show_message = self.capabilities.get().is_some_and(|cap| cap.show_message);
+        for worker in workers {
+            for warning in worker.take_setup_warnings().await {
+                warn!("{warning}");
+                if show_message {
+                    self.client.show_message(MessageType::WARNING, warning).await;
+                }
+            }
         }
     }
 
@@ -882,3 +1232,141 @@ impl Backend {
         Ok(())
     }
 }
+
+/// Sets the flag when dropped, e.g. when the request future is dropped because of `$/cancelRequest`.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Params of the custom requests registered by [`ToolBuilder::custom_text_document_request_methods`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTextDocumentParams {
+    text_document: TextDocumentIdentifier,
+}
+
+/// Method of the proposed request to format pasted code.
+pub const DOCUMENT_PASTE_METHOD: &str = "textDocument/documentPaste";
+
+/// Params of the [`DOCUMENT_PASTE_METHOD`] request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentPasteParams {
+    text_document: TextDocumentIdentifier,
+    /// The range of the pasted code in the document.
+    range: Range,
+}
+
+/// Method of the LSP 3.18 request to format multiple ranges at once.
+pub const RANGES_FORMATTING_METHOD: &str = "textDocument/rangesFormatting";
+
+/// Params of the [`RANGES_FORMATTING_METHOD`] request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentRangesFormattingParams {
+    text_document: TextDocumentIdentifier,
+    /// The ranges to format, in any order.
+    ranges: Vec<Range>,
+    /// The formatting options of the client, same as for `textDocument/rangeFormatting`.
+    options: FormattingOptions,
+}
+
+/// Finds the worker responsible for the URI.
+/// With nested workspace folders, the worker with the closest root wins,
+/// so each file is handled with the options and config of its nearest workspace folder.
+/// Untitled documents are handled by the first workspace folder.
+fn find_worker<'a>(workers: &'a [WorkspaceWorker], uri: &Uri) -> Option<&'a WorkspaceWorker> {
+    if is_untitled_uri(uri) {
+        return workers.first();
+    }
+
+    workers
+        .iter()
+        .filter(|worker| worker.is_responsible_for_uri(uri))
+        .max_by_key(|worker| worker.get_root_uri().as_str().len())
+}
+
+/// Whether the URI is an untitled document which is not saved yet, e.g. `untitled:Untitled-1`.
+fn is_untitled_uri(uri: &Uri) -> bool {
+    uri.scheme().as_str().eq_ignore_ascii_case("untitled")
+}
+
+/// Reports the progress of a command with `$/progress` notifications for the work done `token`.
+///
+/// Each `(done, total)` sent to the returned sender is reported as a percentage,
+/// and the progress ends once the sender is dropped.
+fn spawn_work_done_progress(
+    client: Client,
+    token: ProgressToken,
+    title: String,
+) -> (mpsc::UnboundedSender<(usize, usize)>, JoinHandle<()>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(usize, usize)>();
+
+    let task = tokio::spawn(async move {
+        let notify = |progress: WorkDoneProgress| {
+            client.send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+        };
+
+        notify(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title,
+            cancellable: Some(false),
+            message: None,
+            percentage: Some(0),
+        }))
+        .await;
+
+        while let Some((done, total)) = receiver.recv().await {
+            let percentage = u32::try_from(done * 100 / total.max(1)).unwrap_or(100);
+            notify(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(false),
+                message: Some(format!("{done}/{total}")),
+                percentage: Some(percentage),
+            }))
+            .await;
+        }
+
+        notify(WorkDoneProgress::End(WorkDoneProgressEnd { message: None })).await;
+    });
+
+    (sender, task)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::Uri;
+
+    use super::find_worker;
+    use crate::worker::WorkspaceWorker;
+
+    #[test]
+    fn test_find_worker_nested_workspace_folders() {
+        let workers = [
+            WorkspaceWorker::new(Uri::from_str("file:///root").unwrap(), Arc::new([]), false),
+            WorkspaceWorker::new(
+                Uri::from_str("file:///root/nested").unwrap(),
+                Arc::new([]),
+                false,
+            ),
+        ];
+        let root_of = |uri: &str| {
+            find_worker(&workers, &Uri::from_str(uri).unwrap())
+                .map(|worker| worker.get_root_uri().as_str().to_string())
+        };
+
+        assert_eq!(root_of("file:///root/file.js").as_deref(), Some("file:///root"));
+        // The closest workspace folder wins, regardless of the order of the workers
+        assert_eq!(root_of("file:///root/nested/file.js").as_deref(), Some("file:///root/nested"));
+        assert_eq!(root_of("file:///other/file.js"), None);
+        // Untitled documents do not belong to any workspace folder
+        assert_eq!(root_of("untitled:Untitled-1").as_deref(), Some("file:///root"));
+    }
+}
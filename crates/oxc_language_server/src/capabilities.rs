@@ -10,6 +10,8 @@ pub struct Capabilities {
     pub workspace_configuration: bool,
     pub dynamic_watchers: bool,
     pub show_message: bool,
+    /// Whether the client can compute the edit of a code action later with `codeAction/resolve`.
+    pub code_action_resolve: bool,
     /// Whether the client supports pull diagnostics.
     pull_diagnostics: bool,
     /// Whether the client supports the `workspace/diagnostic/refresh` request.
@@ -35,6 +37,15 @@ impl From<ClientCapabilities> for Capabilities {
         let show_message =
             value.window.as_ref().is_some_and(|window| window.show_message.is_some());
 
+        let code_action_resolve = value
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.code_action.as_ref())
+            .and_then(|code_action| code_action.resolve_support.as_ref())
+            .is_some_and(|resolve_support| {
+                resolve_support.properties.iter().any(|property| property == "edit")
+            });
+
         let pull_diagnostics = value
             .text_document
             .as_ref()
@@ -51,6 +62,7 @@ impl From<ClientCapabilities> for Capabilities {
             workspace_configuration,
             dynamic_watchers,
             show_message,
+            code_action_resolve,
             pull_diagnostics,
             refresh_diagnostics,
         }
@@ -69,7 +81,7 @@ impl Capabilities {
 pub fn server_capabilities() -> ServerCapabilities {
     ServerCapabilities {
         text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
-            change: Some(TextDocumentSyncKind::FULL),
+            change: Some(TextDocumentSyncKind::INCREMENTAL),
             open_close: Some(true),
             save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
                 include_text: Some(false),
@@ -90,7 +102,9 @@ pub fn server_capabilities() -> ServerCapabilities {
 #[cfg(test)]
 mod test {
     use tower_lsp_server::ls_types::{
-        ClientCapabilities, DidChangeWatchedFilesClientCapabilities, WorkspaceClientCapabilities,
+        ClientCapabilities, CodeActionCapabilityResolveSupport, CodeActionClientCapabilities,
+        DidChangeWatchedFilesClientCapabilities, TextDocumentClientCapabilities,
+        WorkspaceClientCapabilities,
     };
 
     use super::Capabilities;
@@ -175,4 +189,24 @@ mod test {
         let capabilities = Capabilities::from(client_capabilities);
         assert!(capabilities.dynamic_watchers);
     }
+
+    #[test]
+    fn test_code_action_resolve_vscode() {
+        let client_capabilities = ClientCapabilities {
+            text_document: Some(TextDocumentClientCapabilities {
+                code_action: Some(CodeActionClientCapabilities {
+                    resolve_support: Some(CodeActionCapabilityResolveSupport {
+                        properties: vec!["edit".to_string()],
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let capabilities = Capabilities::from(client_capabilities);
+        assert!(capabilities.code_action_resolve);
+        assert!(!Capabilities::from(ClientCapabilities::default()).code_action_resolve);
+    }
 }
@@ -1,10 +1,21 @@
-use tower_lsp_server::ls_types::Uri;
+use log::warn;
+use rustc_hash::FxHashMap;
+use tower_lsp_server::ls_types::{TextDocumentContentChangeEvent, Uri};
 
-use crate::ConcurrentHashMap;
+use crate::{ConcurrentHashMap, utils::position_to_offset};
+
+/// In-memory content of an opened document, synced by `textDocument/did*` notifications.
+#[derive(Debug)]
+struct Document {
+    content: String,
+    version: i32,
+    /// Language id sent by the client, e.g. `typescript`.
+    language_id: String,
+}
 
 #[derive(Debug, Default)]
 pub struct LSPFileSystem {
-    files: ConcurrentHashMap<Uri, String>,
+    files: ConcurrentHashMap<Uri, Document>,
 }
 
 impl LSPFileSystem {
@@ -12,12 +23,75 @@ impl LSPFileSystem {
         self.files.pin().clear();
     }
 
-    pub fn set(&self, uri: Uri, content: String) {
-        self.files.pin().insert(uri, content);
+    pub fn set(&self, uri: Uri, content: String, version: i32, language_id: String) {
+        self.files.pin().insert(uri, Document { content, version, language_id });
     }
 
     pub fn get(&self, uri: &Uri) -> Option<String> {
-        self.files.pin().get(uri).cloned()
+        self.files.pin().get(uri).map(|document| document.content.clone())
+    }
+
+    /// Returns the content and version of each opened document.
+    pub fn documents(&self) -> FxHashMap<Uri, (String, i32)> {
+        self.files
+            .pin()
+            .iter()
+            .map(|(uri, document)| (uri.clone(), (document.content.clone(), document.version)))
+            .collect()
+    }
+
+    pub fn get_language_id(&self, uri: &Uri) -> Option<String> {
+        self.files.pin().get(uri).map(|document| document.language_id.clone())
+    }
+
+    /// Apply the changes of `textDocument/didChange` in order and return the updated content.
+    /// Changes with a range are applied incrementally, others replace the whole content.
+    ///
+    /// Returns `None` if the changes cannot be applied,
+    /// e.g. the document is not opened, or `version` is not newer than the stored one.
+    /// The stored document is left as is then.
+    pub fn apply_changes(
+        &self,
+        uri: &Uri,
+        version: i32,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Option<String> {
+        let files = self.files.pin();
+        let document = files.get(uri);
+        if let Some(document) = document
+            && document.version >= version
+        {
+            warn!(
+                "Ignoring the changes of version {version} of {}, version {} is already applied",
+                uri.as_str(),
+                document.version
+            );
+            return None;
+        }
+
+        let mut content = document.map(|document| document.content.clone());
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let Some(content) = content.as_mut() else {
+                        warn!(
+                            "Ignoring the incremental changes of {}, the document is not opened",
+                            uri.as_str()
+                        );
+                        return None;
+                    };
+                    let start = position_to_offset(content, range.start);
+                    let end = position_to_offset(content, range.end).max(start);
+                    content.replace_range(start..end, &change.text);
+                }
+                None => content = Some(change.text),
+            }
+        }
+
+        let content = content?;
+        let language_id = document.map(|document| document.language_id.clone()).unwrap_or_default();
+        files.insert(uri.clone(), Document { content: content.clone(), version, language_id });
+        Some(content)
     }
 
     pub fn remove(&self, uri: &Uri) {
@@ -28,3 +102,69 @@ impl LSPFileSystem {
         self.files.pin().keys().cloned().collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{Position, Range, TextDocumentContentChangeEvent, Uri};
+
+    use super::LSPFileSystem;
+
+    fn change(
+        range: Option<((u32, u32), (u32, u32))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|(start, end)| Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let fs = LSPFileSystem::default();
+        let uri = Uri::from_str("file:///root/file.js").unwrap();
+        fs.set(
+            uri.clone(),
+            "const a = 1;\nconst b = 2;\n".to_string(),
+            1,
+            "javascript".to_string(),
+        );
+
+        // Incremental changes are applied in order
+        assert_eq!(
+            fs.apply_changes(
+                &uri,
+                2,
+                vec![change(Some(((1, 6), (1, 7))), "c"), change(Some(((0, 10), (0, 11))), "10")]
+            )
+            .as_deref(),
+            Some("const a = 10;\nconst c = 2;\n")
+        );
+
+        // UTF-16 columns
+        fs.set(uri.clone(), "const 𝒜 = 1;\n".to_string(), 3, "javascript".to_string());
+        assert_eq!(
+            fs.apply_changes(&uri, 4, vec![change(Some(((0, 11), (0, 12))), "2")]).as_deref(),
+            Some("const 𝒜 = 2;\n")
+        );
+
+        // Full content changes
+        assert_eq!(fs.apply_changes(&uri, 5, vec![change(None, "full")]).as_deref(), Some("full"));
+
+        // Stale versions are ignored
+        assert_eq!(fs.apply_changes(&uri, 5, vec![change(None, "stale")]), None);
+        assert_eq!(fs.get(&uri).as_deref(), Some("full"));
+        assert_eq!(fs.get_language_id(&uri).as_deref(), Some("javascript"));
+
+        // Incremental changes of unknown documents cannot be applied
+        let unknown = Uri::from_str("file:///root/unknown.js").unwrap();
+        assert_eq!(fs.apply_changes(&unknown, 1, vec![change(Some(((0, 0), (0, 0))), "a")]), None);
+        assert_eq!(fs.get(&unknown), None);
+    }
+}
@@ -1,6 +1,10 @@
 use rustc_hash::FxBuildHasher;
 use tower_lsp_server::{LspService, Server, ls_types::ServerInfo};
 
+use crate::backend::{
+    Backend, CustomTextDocumentParams, DOCUMENT_PASTE_METHOD, RANGES_FORMATTING_METHOD,
+};
+
 mod backend;
 mod capabilities;
 mod file_system;
@@ -31,14 +35,32 @@ pub async fn run_server(
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::build(|client| {
-        crate::backend::Backend::new(
-            client,
-            ServerInfo { name: server_name, version: Some(server_version) },
-            tools,
-        )
+    let custom_request_methods: Vec<&'static str> =
+        tools.iter().flat_map(|tool| tool.custom_request_methods()).copied().collect();
+    let custom_text_document_request_methods: Vec<&'static str> = tools
+        .iter()
+        .flat_map(|tool| tool.custom_text_document_request_methods())
+        .copied()
+        .collect();
+
+    let mut service_builder = LspService::build(|client| {
+        Backend::new(client, ServerInfo { name: server_name, version: Some(server_version) }, tools)
     })
-    .finish();
+    .custom_method(DOCUMENT_PASTE_METHOD, Backend::document_paste)
+    .custom_method(RANGES_FORMATTING_METHOD, Backend::ranges_formatting);
+    for method in custom_request_methods {
+        service_builder = service_builder
+            .custom_method(method, move |backend: &Backend| backend.custom_request(method));
+    }
+    for method in custom_text_document_request_methods {
+        service_builder = service_builder.custom_method(
+            method,
+            move |backend: &Backend, params: CustomTextDocumentParams| {
+                backend.custom_text_document_request(method, params)
+            },
+        );
+    }
+    let (service, socket) = service_builder.finish();
 
     Server::new(stdin, stdout, socket).serve(service).await;
 }
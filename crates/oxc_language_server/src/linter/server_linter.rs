@@ -1,5 +1,5 @@
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, atomic::AtomicBool};
 
 use ignore::gitignore::Gitignore;
 use log::{debug, warn};
@@ -441,6 +441,9 @@ impl Tool for ServerLinter {
         &self,
         command: &str,
         arguments: Vec<serde_json::Value>,
+        _open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        _progress: &(dyn Fn(usize, usize) + Sync),
+        _is_cancelled: &AtomicBool,
     ) -> Result<Option<WorkspaceEdit>, ErrorCode> {
         if command != FIX_ALL_COMMAND_ID {
             return Ok(None);
@@ -477,6 +480,7 @@ impl Tool for ServerLinter {
         &self,
         uri: &Uri,
         range: &Range,
+        _content: Option<&str>,
         only_code_action_kinds: Option<&Vec<CodeActionKind>>,
     ) -> Vec<CodeActionOrCommand> {
         let actions = self.get_code_actions_for_uri(uri);
@@ -219,6 +219,7 @@ impl Tester<'_> {
                     &uri,
                     &Range::new(Position::new(0, 0), Position::new(u32::MAX, u32::MAX)),
                     None,
+                    None,
                 ),
             };
 
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, sync::atomic::AtomicBool};
 
 use serde_json::{Value, json};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
@@ -22,6 +22,8 @@ pub struct FakeTool;
 
 pub const FAKE_COMMAND: &str = "fake.command";
 
+pub const FAKE_CUSTOM_REQUEST: &str = "fake/request";
+
 const WORKSPACE: &str = "file:///path/to/workspace";
 
 const WORKSPACE_2: &str = "file:///path/to/another_workspace";
@@ -39,6 +41,9 @@ impl Tool for FakeTool {
         &self,
         command: &str,
         arguments: Vec<serde_json::Value>,
+        _open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        _progress: &(dyn Fn(usize, usize) + Sync),
+        _is_cancelled: &AtomicBool,
     ) -> Result<Option<WorkspaceEdit>, ErrorCode> {
         if command != FAKE_COMMAND {
             return Err(ErrorCode::MethodNotFound);
@@ -51,6 +56,19 @@ impl Tool for FakeTool {
         Ok(None)
     }
 
+    fn handle_custom_request(&self, method: &str) -> Option<serde_json::Value> {
+        (method == FAKE_CUSTOM_REQUEST).then(|| json!("fake response"))
+    }
+
+    fn handle_custom_text_document_request(
+        &self,
+        method: &str,
+        uri: &Uri,
+        _content: Option<&str>,
+    ) -> Option<serde_json::Value> {
+        (method == FAKE_CUSTOM_REQUEST).then(|| json!(uri.as_str()))
+    }
+
     fn handle_configuration_change(
         &self,
         builder: &dyn ToolBuilder,
@@ -110,6 +128,7 @@ impl Tool for FakeTool {
         &self,
         uri: &Uri,
         _range: &Range,
+        _content: Option<&str>,
         _only_code_action_kinds: Option<&Vec<CodeActionKind>>,
     ) -> Vec<CodeActionOrCommand> {
         if uri.as_str().ends_with("code_action.config") {
@@ -124,6 +143,16 @@ impl Tool for FakeTool {
         vec![]
     }
 
+    fn resolve_code_action(
+        &self,
+        code_action: &CodeAction,
+        _content: Option<&str>,
+    ) -> Option<CodeAction> {
+        let uri = code_action.data.as_ref()?.get("uri")?.as_str()?;
+        uri.ends_with("code_action.config")
+            .then(|| CodeAction { edit: Some(WorkspaceEdit::default()), ..code_action.clone() })
+    }
+
     fn run_diagnostic(&self, uri: &Uri, content: Option<&str>) -> DiagnosticResult {
         if uri.as_str().ends_with("diagnostics.config") {
             return Ok(vec![(
@@ -522,6 +551,16 @@ fn code_action(id: i64, uri: &str) -> Request {
     Request::build("textDocument/codeAction").id(id).params(json!(params)).finish()
 }
 
+fn code_action_resolve(id: i64, uri: &str) -> Request {
+    let params = CodeAction {
+        title: "Code Action title".to_string(),
+        data: Some(json!({ "uri": uri })),
+        ..Default::default()
+    };
+
+    Request::build("codeAction/resolve").id(id).params(json!(params)).finish()
+}
+
 fn test_configuration_request(id: i64) -> Request {
     Request::build("test/configuration").id(id).params(json!(null)).finish()
 }
@@ -554,7 +593,7 @@ mod test_suite {
         tests::{
             FAKE_COMMAND, FakeToolBuilder, InitializeRequestOptions, TestServer, WORKSPACE,
             WORKSPACE_2, acknowledge_diagnostic_refresh, acknowledge_registrations,
-            acknowledge_unregistrations, code_action, diagnostic, did_change,
+            acknowledge_unregistrations, code_action, code_action_resolve, diagnostic, did_change,
             did_change_configuration, did_change_watched_files, did_close, did_open, did_save,
             execute_command_request, initialize_request, initialize_request_workspace_folders,
             initialized_notification, response_to_configuration, shutdown_request,
@@ -1123,6 +1162,34 @@ mod test_suite {
         server.shutdown(3).await;
     }
 
+    #[tokio::test]
+    async fn test_watched_file_changed_debounced() {
+        let init_options =
+            InitializeRequestOptions { dynamic_watchers: true, ..Default::default() };
+        let mut server = TestServer::new_initialized(
+            |client| Backend::new(client, server_info(), vec![Box::new(FakeToolBuilder)]),
+            initialize_request(init_options),
+        )
+        .await;
+        acknowledge_registrations(&mut server).await;
+
+        let uri = format!("{WORKSPACE}/diagnostics.config");
+        server.send_request(did_open(&uri, "some text")).await;
+        let diagnostic_response = server.recv_notification().await;
+        assert_eq!(diagnostic_response.method(), "textDocument/publishDiagnostics");
+
+        // Editors can fire several notifications for one save
+        let tool_config = format!("{WORKSPACE}/tool.config");
+        server.send_request(did_change_watched_files(&tool_config)).await;
+        server.send_request(did_change_watched_files(&tool_config)).await;
+
+        // The tool is restarted and the diagnostics are revalidated only once
+        let diagnostic_response = server.recv_notification().await;
+        assert_eq!(diagnostic_response.method(), "textDocument/publishDiagnostics");
+
+        server.shutdown_with_diagnostic_clear(3, vec![uri.parse().unwrap()]).await;
+    }
+
     #[tokio::test]
     async fn test_watched_file_changed_revalidate_diagnostics() {
         let init_options =
@@ -1400,6 +1467,33 @@ mod test_suite {
         server.shutdown(4).await;
     }
 
+    #[tokio::test]
+    async fn test_code_action_resolve() {
+        let mut server = TestServer::new_initialized(
+            |client| Backend::new(client, server_info(), vec![Box::new(FakeToolBuilder)]),
+            initialize_request(InitializeRequestOptions::default()),
+        )
+        .await;
+
+        let file = format!("{WORKSPACE}/code_action.config");
+        server.send_request(did_open(&file, "some text")).await;
+
+        // The edit is computed by the tool
+        server.send_request(code_action_resolve(3, &file)).await;
+        let response = server.recv_response().await;
+        assert!(response.is_ok());
+        assert!(response.id() == &Id::Number(3));
+        assert!(response.result().unwrap().get("edit").is_some());
+
+        // Code actions of other documents are returned as is
+        server.send_request(code_action_resolve(4, &format!("{WORKSPACE}/file.txt"))).await;
+        let response = server.recv_response().await;
+        assert!(response.is_ok());
+        assert!(response.result().unwrap().get("edit").is_none());
+
+        server.shutdown(5).await;
+    }
+
     #[tokio::test]
     async fn test_diagnostic_on_open() {
         let mut server = TestServer::new_initialized(
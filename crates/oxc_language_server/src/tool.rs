@@ -1,8 +1,10 @@
+use std::sync::atomic::AtomicBool;
+
 use tower_lsp_server::{
     jsonrpc::ErrorCode,
     ls_types::{
-        CodeActionKind, CodeActionOrCommand, Diagnostic, Pattern, Range, ServerCapabilities,
-        TextEdit, Uri, WorkspaceEdit,
+        CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, FormattingOptions, Pattern,
+        Position, Range, ServerCapabilities, TextEdit, Uri, WorkspaceEdit,
     },
 };
 
@@ -19,6 +21,19 @@ pub trait ToolBuilder: Send + Sync {
 
     /// Build a boxed instance of the tool for the given root URI and options.
     fn build_boxed(&self, root_uri: &Uri, options: serde_json::Value) -> Box<dyn Tool>;
+
+    /// Methods of the custom requests handled by the tools, e.g. `oxfmt/supportedLanguages`.
+    /// They are registered once when the server starts, see [`Tool::handle_custom_request`].
+    fn custom_request_methods(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Methods of the custom requests for a single document, e.g. `oxfmt/resolveConfig`.
+    /// Their params are `{ textDocument: { uri } }`, and they are handled by the workspace of the document,
+    /// see [`Tool::handle_custom_text_document_request`].
+    fn custom_text_document_request_methods(&self) -> &'static [&'static str] {
+        &[]
+    }
 }
 
 pub type DiagnosticResult = Result<Vec<(Uri, Vec<Diagnostic>)>, String>;
@@ -63,33 +78,178 @@ pub trait Tool: Send + Sync {
     /// - `Ok(Some(WorkspaceEdit))` if the command was executed successfully and produced a workspace edit.
     /// - `Ok(None)` if the command was executed successfully but did not produce any workspace edit.
     ///
+    /// `open_document` returns the content and version of a document opened in the editor,
+    /// commands editing files should use it instead of the file on disk for those documents.
+    ///
+    /// Long running commands can call `progress` with the number of done and total work items,
+    /// which is reported to the client if it requested work done progress.
+    /// `is_cancelled` is set when the client cancels the request, the tool should stop then.
+    ///
     /// # Errors
     /// If there was an error executing the command, returns an `Err(ErrorCode)`.
     fn execute_command(
         &self,
         _command: &str,
         _arguments: Vec<serde_json::Value>,
+        _open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        _progress: &(dyn Fn(usize, usize) + Sync),
+        _is_cancelled: &AtomicBool,
     ) -> Result<Option<WorkspaceEdit>, ErrorCode> {
         Ok(None)
     }
 
+    /// Handle a custom request without params, registered by [`ToolBuilder::custom_request_methods`].
+    /// Returns `None` if the tool does not handle the given method.
+    fn handle_custom_request(&self, _method: &str) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Handle a custom request for the document at `uri`,
+    /// registered by [`ToolBuilder::custom_text_document_request_methods`].
+    /// `content` is the content of the document opened in the editor, if any,
+    /// otherwise the tool should read the content from the file system.
+    /// Returns `None` if the tool does not handle the given method.
+    fn handle_custom_text_document_request(
+        &self,
+        _method: &str,
+        _uri: &Uri,
+        _content: Option<&str>,
+    ) -> Option<serde_json::Value> {
+        None
+    }
+
     /// Get code actions or commands provided by this tool for the given URI and range.
+    /// If `content` is `None`, the tool should read the content from the file system.
     /// The `only_code_action_kinds` parameter can be used to filter the results based on specific code action kinds.
     fn get_code_actions_or_commands(
         &self,
         _uri: &Uri,
         _range: &Range,
+        _content: Option<&str>,
         _only_code_action_kinds: Option<&Vec<CodeActionKind>>,
     ) -> Vec<CodeActionOrCommand> {
         Vec::new()
     }
 
+    /// Compute the edit of a code action returned by [`Tool::get_code_actions_or_commands`] without one,
+    /// for clients supporting `codeAction/resolve`, see [`Capabilities::code_action_resolve`].
+    /// The `data` of such a code action must contain the URI of its document as `uri`,
+    /// `content` is the content of that document if it is opened.
+    /// Returns `None` if the code action does not belong to this tool.
+    fn resolve_code_action(
+        &self,
+        _code_action: &CodeAction,
+        _content: Option<&str>,
+    ) -> Option<CodeAction> {
+        None
+    }
+
+    /// Warnings about how the tool was built, which the user should know about, e.g. an invalid config file.
+    /// They are collected every time the tool is built, and shown to the user with `window/showMessage`.
+    fn get_setup_warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Format the content of the given URI.
     /// If `content` is `None`, the tool should read the content from the file system.
     /// Returns a vector of `TextEdit` representing the formatting changes.
+    /// `options` are the editor settings sent by the client, if any.
+    /// `is_cancelled` is set when the client cancels the request, the tool should return `None` then.
     ///
     /// Not all tools will implement formatting, so the default implementation returns `None`.
-    fn run_format(&self, _uri: &Uri, _content: Option<&str>) -> Option<Vec<TextEdit>> {
+    fn run_format(
+        &self,
+        _uri: &Uri,
+        _content: Option<&str>,
+        _options: Option<&FormattingOptions>,
+        _is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        None
+    }
+
+    /// Format an untitled document, e.g. `untitled:Untitled-1`, which does not exist on the file system.
+    /// `language_id` is the language id sent by the client, e.g. `typescript`,
+    /// the tool should use it to decide how to format `content`.
+    /// Returns a vector of `TextEdit` representing the formatting changes.
+    ///
+    /// Not all tools will implement formatting, so the default implementation returns `None`.
+    fn run_format_untitled(
+        &self,
+        _uri: &Uri,
+        _language_id: &str,
+        _content: &str,
+        _options: Option<&FormattingOptions>,
+        _is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        None
+    }
+
+    /// Format the given range of the content of the given URI.
+    /// If `content` is `None`, the tool should read the content from the file system.
+    /// `options` are the formatting options sent by the client, same as for [`Tool::run_format`].
+    /// Returns a vector of `TextEdit` representing the formatting changes inside the range.
+    ///
+    /// Not all tools will implement range formatting, so the default implementation returns `None`.
+    fn run_format_range(
+        &self,
+        _uri: &Uri,
+        _range: Range,
+        _content: Option<&str>,
+        _options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        None
+    }
+
+    /// Format the given ranges of the content of the given URI at once,
+    /// for clients supporting the `textDocument/rangesFormatting` request of LSP 3.18.
+    /// If `content` is `None`, the tool should read the content from the file system.
+    /// `options` are the formatting options sent by the client, same as for [`Tool::run_format`].
+    /// Returns a vector of `TextEdit` representing the formatting changes touching any of the ranges,
+    /// which must not overlap each other.
+    ///
+    /// Not all tools will implement multi-range formatting, so the default implementation returns `None`.
+    fn run_format_ranges(
+        &self,
+        _uri: &Uri,
+        _ranges: &[Range],
+        _content: Option<&str>,
+        _options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        None
+    }
+
+    /// Format the code pasted into the given range of the content of the given URI.
+    /// If `content` is `None`, the tool should read the content from the file system.
+    /// Returns a vector of `TextEdit` representing the formatting changes of the pasted code.
+    ///
+    /// Defaults to [`Tool::run_format_range`] with the pasted range.
+    /// The paste request has no formatting options, so none are passed.
+    fn run_format_paste(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+    ) -> Option<Vec<TextEdit>> {
+        self.run_format_range(uri, range, content, None)
+    }
+
+    /// Format the content of the given URI after the character `ch` was typed at `position`.
+    /// If `content` is `None`, the tool should read the content from the file system.
+    /// `options` are the formatting options sent by the client, same as for [`Tool::run_format`].
+    /// `is_cancelled` is set when the client cancels the request, e.g. when the user keeps typing,
+    /// the tool should return `None` then.
+    /// Returns a vector of `TextEdit` representing the formatting changes around the typed character.
+    ///
+    /// Not all tools will implement on-type formatting, so the default implementation returns `None`.
+    fn run_on_type_format(
+        &self,
+        _uri: &Uri,
+        _position: Position,
+        _ch: &str,
+        _content: Option<&str>,
+        _options: Option<&FormattingOptions>,
+        _is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
         None
     }
 
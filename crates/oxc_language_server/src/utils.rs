@@ -1,5 +1,7 @@
 use std::path::{Component, Path, PathBuf};
 
+use tower_lsp_server::ls_types::Position;
+
 /// Normalize a path by removing `.` and resolving `..` components,
 /// without touching the filesystem.
 pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
@@ -25,11 +27,35 @@ pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
     result
 }
 
+/// Convert a UTF-16 based LSP position to a byte offset of `text`.
+/// Positions past the end of a line or the text are clamped to it.
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut offset = 0;
+    for _ in 0..position.line {
+        match text[offset..].find('\n') {
+            Some(index) => offset += index + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut column = 0;
+    for c in text[offset..].chars() {
+        if column >= position.character as usize || c == '\n' || c == '\r' {
+            break;
+        }
+        column += c.len_utf16();
+        offset += c.len_utf8();
+    }
+    offset
+}
+
 #[cfg(test)]
 mod test {
     use std::path::Path;
 
-    use crate::utils::normalize_path;
+    use tower_lsp_server::ls_types::Position;
+
+    use crate::utils::{normalize_path, position_to_offset};
 
     #[test]
     fn test_normalize_path() {
@@ -38,4 +64,16 @@ mod test {
             Path::new("/root/directory/.oxlintrc.json")
         );
     }
+
+    #[test]
+    fn test_position_to_offset() {
+        let text = "a😀b\ncd\n";
+        assert_eq!(position_to_offset(text, Position::new(0, 0)), 0);
+        assert_eq!(position_to_offset(text, Position::new(0, 3)), 5);
+        assert_eq!(position_to_offset(text, Position::new(1, 1)), 8);
+        // Clamped to the end of the line
+        assert_eq!(position_to_offset(text, Position::new(1, 10)), 9);
+        // Clamped to the end of the text
+        assert_eq!(position_to_offset(text, Position::new(5, 0)), 10);
+    }
 }
@@ -1,14 +1,15 @@
 use log::debug;
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde_json::json;
-use std::sync::Arc;
+use std::sync::{Arc, atomic::AtomicBool};
 use tokio::sync::{Mutex, RwLock};
 use tower_lsp_server::{
     jsonrpc::ErrorCode,
     ls_types::{
-        CodeActionKind, CodeActionOrCommand, Diagnostic, DidChangeWatchedFilesRegistrationOptions,
-        FileEvent, FileSystemWatcher, GlobPattern, OneOf, Range, Registration, RelativePattern,
-        TextEdit, Unregistration, Uri, WatchKind, WorkspaceEdit,
+        CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic,
+        DidChangeWatchedFilesRegistrationOptions, FileEvent, FileSystemWatcher, FormattingOptions,
+        GlobPattern, OneOf, Position, Range, Registration, RelativePattern, TextEdit,
+        Unregistration, Uri, WatchKind, WorkspaceEdit,
     },
 };
 
@@ -35,6 +36,8 @@ pub struct WorkspaceWorker {
     diagnostic_pull_mode: bool,
     // Keep track of published diagnostics to clear them on shutdown (only in push mode)
     published_diagnostics: Mutex<FxHashSet<Uri>>,
+    // Warnings of the built tools, not yet shown to the user
+    setup_warnings: Mutex<Vec<String>>,
 }
 
 impl WorkspaceWorker {
@@ -53,6 +56,7 @@ impl WorkspaceWorker {
             options: Mutex::new(None),
             diagnostic_pull_mode,
             published_diagnostics: Mutex::new(FxHashSet::default()),
+            setup_warnings: Mutex::new(vec![]),
         }
     }
 
@@ -79,11 +83,16 @@ impl WorkspaceWorker {
     /// Start all programs (linter, formatter) for the worker.
     /// This should be called after the client has sent the workspace configuration.
     pub async fn start_worker(&self, options: serde_json::Value) {
-        *self.tools.write().await = self
+        let tools: Vec<Box<dyn Tool>> = self
             .builders
             .iter()
             .map(|builder| builder.build_boxed(&self.root_uri, options.clone()))
             .collect();
+        self.setup_warnings
+            .lock()
+            .await
+            .extend(tools.iter().flat_map(|tool| tool.get_setup_warnings()));
+        *self.tools.write().await = tools;
 
         *self.options.lock().await = Some(options);
     }
@@ -110,6 +119,11 @@ impl WorkspaceWorker {
             .collect()
     }
 
+    /// Take the warnings of the tools built since the last call, to show them to the user.
+    pub async fn take_setup_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.setup_warnings.lock().await)
+    }
+
     /// Check if the worker needs to be initialized with options
     pub async fn needs_init_options(&self) -> bool {
         self.options.lock().await.is_none()
@@ -207,9 +221,113 @@ impl WorkspaceWorker {
     /// Format a file with the current formatter
     /// - If no file is not formattable or ignored, [`None`] is returned
     /// - If the file is formattable, but no changes are made, an empty vector is returned
-    pub async fn format_file(&self, uri: &Uri, content: Option<&str>) -> Option<Vec<TextEdit>> {
+    /// - If `is_cancelled` is set while formatting, [`None`] is returned
+    pub async fn format_file(
+        &self,
+        uri: &Uri,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        for tool in self.tools.read().await.iter() {
+            if let Some(edits) = tool.run_format(uri, content, options, is_cancelled) {
+                return Some(edits);
+            }
+        }
+        None
+    }
+
+    /// Format an untitled document with the current formatter, see [`Tool::run_format_untitled`]
+    /// - If the language is not supported, [`None`] is returned
+    /// - If the document is formattable, but no changes are made, an empty vector is returned
+    pub async fn format_untitled_file(
+        &self,
+        uri: &Uri,
+        language_id: &str,
+        content: &str,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
+        for tool in self.tools.read().await.iter() {
+            if let Some(edits) =
+                tool.run_format_untitled(uri, language_id, content, options, is_cancelled)
+            {
+                return Some(edits);
+            }
+        }
+        None
+    }
+
+    /// Format a range of a file with the current formatter
+    /// - If no file is not formattable or ignored, [`None`] is returned
+    /// - If the range is formattable, but no changes are made, an empty vector is returned
+    pub async fn format_file_range(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        for tool in self.tools.read().await.iter() {
+            if let Some(edits) = tool.run_format_range(uri, range, content, options) {
+                return Some(edits);
+            }
+        }
+        None
+    }
+
+    /// Format multiple ranges of a file at once with the current formatter
+    /// - If no file is not formattable or ignored, [`None`] is returned
+    /// - If the ranges are formattable, but no changes are made, an empty vector is returned
+    pub async fn format_file_ranges(
+        &self,
+        uri: &Uri,
+        ranges: &[Range],
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+    ) -> Option<Vec<TextEdit>> {
+        for tool in self.tools.read().await.iter() {
+            if let Some(edits) = tool.run_format_ranges(uri, ranges, content, options) {
+                return Some(edits);
+            }
+        }
+        None
+    }
+
+    /// Format the code pasted into a range of a file with the current formatter
+    /// - If no file is not formattable or ignored, [`None`] is returned
+    /// - If the pasted code is formattable, but no changes are made, an empty vector is returned
+    pub async fn format_file_paste(
+        &self,
+        uri: &Uri,
+        range: Range,
+        content: Option<&str>,
+    ) -> Option<Vec<TextEdit>> {
+        for tool in self.tools.read().await.iter() {
+            if let Some(edits) = tool.run_format_paste(uri, range, content) {
+                return Some(edits);
+            }
+        }
+        None
+    }
+
+    /// Format a file after a character was typed with the current formatter
+    /// - If no file is not formattable or ignored, [`None`] is returned
+    /// - If the file is formattable, but no changes are made, an empty vector is returned
+    /// - If `is_cancelled` is set while formatting, [`None`] is returned
+    pub async fn format_file_on_type(
+        &self,
+        uri: &Uri,
+        position: Position,
+        ch: &str,
+        content: Option<&str>,
+        options: Option<&FormattingOptions>,
+        is_cancelled: &AtomicBool,
+    ) -> Option<Vec<TextEdit>> {
         for tool in self.tools.read().await.iter() {
-            if let Some(edits) = tool.run_format(uri, content) {
+            if let Some(edits) =
+                tool.run_on_type_format(uri, position, ch, content, options, is_cancelled)
+            {
                 return Some(edits);
             }
         }
@@ -245,6 +363,7 @@ impl WorkspaceWorker {
         &self,
         uri: &Uri,
         range: &Range,
+        content: Option<&str>,
         only_code_action_kinds: Option<Vec<CodeActionKind>>,
     ) -> Vec<CodeActionOrCommand> {
         let mut actions = Vec::new();
@@ -252,18 +371,35 @@ impl WorkspaceWorker {
             actions.extend(tool.get_code_actions_or_commands(
                 uri,
                 range,
+                content,
                 only_code_action_kinds.as_ref(),
             ));
         }
         actions
     }
 
+    /// Resolve the edit of a code action with the tool it belongs to, see [`Tool::resolve_code_action`].
+    pub async fn resolve_code_action(
+        &self,
+        code_action: &CodeAction,
+        content: Option<&str>,
+    ) -> Option<CodeAction> {
+        self.tools
+            .read()
+            .await
+            .iter()
+            .find_map(|tool| tool.resolve_code_action(code_action, content))
+    }
+
     /// Handle file changes that are watched by the client
     /// At the moment, this only handles changes to lint configuration files
     /// When a change is detected, the linter is refreshed and all diagnostics are revalidated
+    ///
+    /// The events of one notification are coalesced, e.g. when an editor fires several events for one save,
+    /// so each tool is restarted at most once.
     pub async fn did_change_watched_files(
         &self,
-        file_event: &FileEvent,
+        file_events: &[FileEvent],
         needs_diagnostic_refresh: &mut bool,
         file_system: Option<&LSPFileSystem>,
     ) -> (
@@ -280,13 +416,30 @@ impl WorkspaceWorker {
             options_guard.clone().unwrap_or_default()
         };
 
+        let mut seen_uris = FxHashSet::default();
+        let changed_uris: Vec<&Uri> = file_events
+            .iter()
+            .map(|file_event| &file_event.uri)
+            .filter(|uri| seen_uris.insert(*uri))
+            .collect();
+
         self.handle_tool_changes(file_system, needs_diagnostic_refresh, |tool, builder| {
-            tool.handle_watched_file_change(
-                builder,
-                &file_event.uri,
-                &self.root_uri,
-                options.clone(),
-            )
+            let mut changes = ToolRestartChanges { tool: None, watch_patterns: None };
+            for changed_uri in &changed_uris {
+                let change = tool.handle_watched_file_change(
+                    builder,
+                    changed_uri,
+                    &self.root_uri,
+                    options.clone(),
+                );
+                changes.watch_patterns = change.watch_patterns.or(changes.watch_patterns);
+                if change.tool.is_some() {
+                    // The restarted tool reads all its files again, including the remaining changed ones
+                    changes.tool = change.tool;
+                    break;
+                }
+            }
+            changes
         })
         .await
     }
@@ -378,6 +531,7 @@ impl WorkspaceWorker {
             if let Some(replaced_tool) = change.tool {
                 *tool = replaced_tool;
                 *needs_diagnostic_refresh = true;
+                self.setup_warnings.lock().await.extend(tool.get_setup_warnings());
 
                 let Some(file_system) = file_system else {
                     continue;
@@ -406,7 +560,8 @@ impl WorkspaceWorker {
     }
 
     /// Execute a command for the workspace.
-    /// Currently, only the `oxc.fixAll` command is supported.
+    /// The command is executed by the first tool responsible for it, e.g. `oxc.fixAll` by the linter.
+    /// See [`Tool::execute_command`] for `open_document`, `progress` and `is_cancelled`.
     ///
     /// # Errors
     /// Returns `ErrorCode` when the command is found but could not be executed.
@@ -414,14 +569,42 @@ impl WorkspaceWorker {
         &self,
         command: &str,
         arguments: Vec<serde_json::Value>,
+        open_document: &(dyn Fn(&Uri) -> Option<(String, i32)> + Sync),
+        progress: &(dyn Fn(usize, usize) + Sync),
+        is_cancelled: &AtomicBool,
     ) -> Result<Option<WorkspaceEdit>, ErrorCode> {
         for tool in self.tools.read().await.iter() {
             if tool.is_responsible_for_command(command) {
-                return tool.execute_command(command, arguments);
+                return tool.execute_command(
+                    command,
+                    arguments,
+                    open_document,
+                    progress,
+                    is_cancelled,
+                );
             }
         }
         Ok(None)
     }
+
+    /// Handle a custom request with the first tool responding to the given method.
+    pub async fn handle_custom_request(&self, method: &str) -> Option<serde_json::Value> {
+        self.tools.read().await.iter().find_map(|tool| tool.handle_custom_request(method))
+    }
+
+    /// Handle a custom request for the document at `uri` with the first tool responding to the given method.
+    pub async fn handle_custom_text_document_request(
+        &self,
+        method: &str,
+        uri: &Uri,
+        content: Option<&str>,
+    ) -> Option<serde_json::Value> {
+        self.tools
+            .read()
+            .await
+            .iter()
+            .find_map(|tool| tool.handle_custom_text_document_request(method, uri, content))
+    }
 }
 
 /// Create an unregistration for a file system watcher for the given tool
@@ -456,13 +639,13 @@ fn registration_tool_watcher_id(tool: &str, root_uri: &Uri, patterns: Vec<String
 mod tests {
     use std::str::FromStr;
 
-    use std::sync::Arc;
+    use std::sync::{Arc, atomic::AtomicBool};
     use tower_lsp_server::ls_types::{CodeActionOrCommand, FileChangeType, FileEvent, Range, Uri};
 
     use crate::{
         ToolBuilder,
         file_system::LSPFileSystem,
-        tests::{FAKE_COMMAND, FakeToolBuilder},
+        tests::{FAKE_COMMAND, FAKE_CUSTOM_REQUEST, FakeToolBuilder},
         worker::WorkspaceWorker,
     };
 
@@ -531,22 +714,66 @@ mod tests {
             WorkspaceWorker::new(Uri::from_str("file:///root/").unwrap(), create_builders(), false);
         worker.start_worker(serde_json::Value::Null).await;
 
+        let is_cancelled = AtomicBool::new(false);
+
         // Test command not found
-        let result = worker.execute_command("unknown.command", vec![]).await;
+        let result = worker
+            .execute_command("unknown.command", vec![], &|_| None, &|_, _| {}, &is_cancelled)
+            .await;
         assert!(result.is_ok());
         assert!(result.ok().unwrap().is_none());
 
         // Test command found but no arguments
-        let result = worker.execute_command(FAKE_COMMAND, vec![]).await;
+        let result = worker
+            .execute_command(FAKE_COMMAND, vec![], &|_| None, &|_, _| {}, &is_cancelled)
+            .await;
         assert!(result.is_ok());
         assert!(result.ok().unwrap().is_none());
 
         // Test command found with arguments
-        let result = worker.execute_command(FAKE_COMMAND, vec![serde_json::Value::Null]).await;
+        let result = worker
+            .execute_command(
+                FAKE_COMMAND,
+                vec![serde_json::Value::Null],
+                &|_| None,
+                &|_, _| {},
+                &is_cancelled,
+            )
+            .await;
         assert!(result.is_ok());
         assert!(result.ok().unwrap().is_some());
     }
 
+    #[tokio::test]
+    async fn test_handle_custom_request() {
+        let worker =
+            WorkspaceWorker::new(Uri::from_str("file:///root/").unwrap(), create_builders(), false);
+        worker.start_worker(serde_json::Value::Null).await;
+
+        assert_eq!(worker.handle_custom_request("unknown/request").await, None);
+        assert_eq!(
+            worker.handle_custom_request(FAKE_CUSTOM_REQUEST).await,
+            Some(serde_json::json!("fake response"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_custom_text_document_request() {
+        let worker =
+            WorkspaceWorker::new(Uri::from_str("file:///root/").unwrap(), create_builders(), false);
+        worker.start_worker(serde_json::Value::Null).await;
+        let uri = Uri::from_str("file:///root/file.js").unwrap();
+
+        assert_eq!(
+            worker.handle_custom_text_document_request("unknown/request", &uri, None).await,
+            None
+        );
+        assert_eq!(
+            worker.handle_custom_text_document_request(FAKE_CUSTOM_REQUEST, &uri, None).await,
+            Some(serde_json::json!("file:///root/file.js"))
+        );
+    }
+
     #[tokio::test]
     async fn test_watched_files_change_notification() {
         let worker =
@@ -557,15 +784,17 @@ mod tests {
         fs.set(
             Uri::from_str("file:///root/diagnostics.config").unwrap(),
             "hello world".to_string(),
+            1,
+            "plaintext".to_string(),
         );
         let mut needs_diagnostic_refresh = false;
 
         let (diagnostics, registrations, unregistrations) = worker
             .did_change_watched_files(
-                &FileEvent {
+                &[FileEvent {
                     uri: Uri::from_str("file:///root/unknown.file").unwrap(),
                     typ: FileChangeType::CHANGED,
-                },
+                }],
                 &mut needs_diagnostic_refresh,
                 Some(&fs),
             )
@@ -579,10 +808,10 @@ mod tests {
 
         let (diagnostics, registrations, unregistrations) = worker
             .did_change_watched_files(
-                &FileEvent {
+                &[FileEvent {
                     uri: Uri::from_str("file:///root/watcher.config").unwrap(),
                     typ: FileChangeType::CHANGED,
-                },
+                }],
                 &mut needs_diagnostic_refresh,
                 Some(&fs),
             )
@@ -598,10 +827,10 @@ mod tests {
 
         let (diagnostics, registrations, unregistrations) = worker
             .did_change_watched_files(
-                &FileEvent {
+                &[FileEvent {
                     uri: Uri::from_str("file:///root/tool.config").unwrap(),
                     typ: FileChangeType::CHANGED,
-                },
+                }],
                 &mut needs_diagnostic_refresh,
                 Some(&fs),
             )
@@ -617,10 +846,10 @@ mod tests {
         needs_diagnostic_refresh = false;
         let (diagnostics, registrations, unregistrations) = worker
             .did_change_watched_files(
-                &FileEvent {
+                &[FileEvent {
                     uri: Uri::from_str("file:///root/tool.config").unwrap(),
                     typ: FileChangeType::CHANGED,
-                },
+                }],
                 &mut needs_diagnostic_refresh,
                 None,
             )
@@ -633,6 +862,44 @@ mod tests {
         assert!(needs_diagnostic_refresh); // Need to refresh diagnostics
     }
 
+    #[tokio::test]
+    async fn test_watched_files_change_notification_coalesced() {
+        let worker =
+            WorkspaceWorker::new(Uri::from_str("file:///root/").unwrap(), create_builders(), false);
+        worker.start_worker(serde_json::Value::Null).await;
+
+        let fs = LSPFileSystem::default();
+        fs.set(
+            Uri::from_str("file:///root/diagnostics.config").unwrap(),
+            "hello world".to_string(),
+            1,
+            "plaintext".to_string(),
+        );
+        let mut needs_diagnostic_refresh = false;
+
+        let file_event = |uri: &str| FileEvent {
+            uri: Uri::from_str(uri).unwrap(),
+            typ: FileChangeType::CHANGED,
+        };
+        let (diagnostics, registrations, unregistrations) = worker
+            .did_change_watched_files(
+                &[
+                    file_event("file:///root/watcher.config"),
+                    file_event("file:///root/tool.config"),
+                    file_event("file:///root/tool.config"),
+                ],
+                &mut needs_diagnostic_refresh,
+                Some(&fs),
+            )
+            .await;
+
+        // The tool is restarted once for all events, and its watchers are updated
+        assert_eq!(diagnostics.unwrap().len(), 1);
+        assert_eq!(registrations.len(), 1);
+        assert_eq!(unregistrations.len(), 1);
+        assert!(needs_diagnostic_refresh);
+    }
+
     #[tokio::test]
     async fn test_did_change_configuration() {
         let worker =
@@ -643,6 +910,8 @@ mod tests {
         fs.set(
             Uri::from_str("file:///root/diagnostics.config").unwrap(),
             "hello world".to_string(),
+            1,
+            "plaintext".to_string(),
         );
         let mut needs_diagnostic_refresh = false;
 
@@ -703,6 +972,7 @@ mod tests {
                 &Uri::from_str("file:///root/file.js").unwrap(),
                 &Range::default(),
                 None,
+                None,
             )
             .await;
 
@@ -713,6 +983,7 @@ mod tests {
                 &Uri::from_str("file:///root/code_action.config").unwrap(),
                 &Range::default(),
                 None,
+                None,
             )
             .await;
 
@@ -1,7 +1,10 @@
 use oxc_allocator::Allocator;
-use oxc_benchmark::{BenchmarkId, Criterion, criterion_group, criterion_main};
-use oxc_formatter::{FormatOptions, Formatter, SortImportsOptions, get_parse_options};
+use oxc_benchmark::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use oxc_formatter::{
+    FormatOptions, Formatter, SortImportsOptions, enable_jsx_source_type, get_parse_options,
+};
 use oxc_parser::Parser;
+use oxc_span::SourceType;
 use oxc_tasks_common::TestFiles;
 
 fn bench_formatter(criterion: &mut Criterion) {
@@ -33,5 +36,40 @@ fn bench_formatter(criterion: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(formatter, bench_formatter);
+/// Parsing and formatting a small file, as on every format request of the language server,
+/// when the parse options and the JSX source type are created for every call, or created once and reused.
+fn bench_formatter_setup(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("formatter_setup");
+    let source_text = "const a = { b: 1 };\nexport const App = () => <div>{a.b}</div>;\n";
+    let source_type = SourceType::mjs();
+    let mut allocator = Allocator::default();
+
+    group.bench_function("per_call", |b| {
+        b.iter(|| {
+            allocator.reset();
+            let program = Parser::new(&allocator, source_text, enable_jsx_source_type(source_type))
+                .with_options(get_parse_options())
+                .parse()
+                .program;
+            black_box(Formatter::new(&allocator, FormatOptions::default()).build(&program));
+        });
+    });
+
+    let parse_options = get_parse_options();
+    let jsx_source_type = enable_jsx_source_type(source_type);
+    group.bench_function("reused", |b| {
+        b.iter(|| {
+            allocator.reset();
+            let program = Parser::new(&allocator, source_text, jsx_source_type)
+                .with_options(parse_options)
+                .parse()
+                .program;
+            black_box(Formatter::new(&allocator, FormatOptions::default()).build(&program));
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(formatter, bench_formatter, bench_formatter_setup);
 criterion_main!(formatter);